@@ -0,0 +1,87 @@
+#![feature(test)]
+
+extern crate test;
+
+use test::Bencher;
+
+use tdi_ray_tracer::{random_generator, ray, tree, triangle, vector3};
+
+const TRIANGLE_COUNT: usize = 20000;
+const RAY_COUNT: usize = 1000;
+
+// small triangles scattered through a 100 unit cube
+fn random_triangles(rg: &mut random_generator::RandomGenerator) -> Vec<triangle::Triangle> {
+    (0..TRIANGLE_COUNT)
+        .map(|_| {
+            let a = vector3::Vector3::new(rg.value(100.0), rg.value(100.0), rg.value(100.0));
+            let b = a + vector3::Vector3::new(rg.value(2.0) - 1.0, rg.value(2.0) - 1.0, rg.value(2.0) - 1.0);
+            let c = a + vector3::Vector3::new(rg.value(2.0) - 1.0, rg.value(2.0) - 1.0, rg.value(2.0) - 1.0);
+
+            triangle::Triangle::new(a, b, c)
+        })
+        .collect()
+}
+
+// rays from random points on one side of the cube towards random points on the other
+fn random_rays(rg: &mut random_generator::RandomGenerator) -> Vec<ray::Ray> {
+    (0..RAY_COUNT)
+        .map(|_| {
+            let origin = vector3::Vector3::new(rg.value(100.0), rg.value(100.0), -1.0);
+            let target = vector3::Vector3::new(rg.value(100.0), rg.value(100.0), 101.0);
+
+            ray::Ray::new(origin, target - origin)
+        })
+        .collect()
+}
+
+const MEDIAN: tree::TreeBuilder = tree::TreeBuilder::Median { page_size: 1 };
+
+fn sah() -> tree::TreeBuilder {
+    tree::TreeBuilder::Sah(tree::SahSettings::default())
+}
+
+// bench_cast_ray casts the rays through the flattened tree
+fn bench_cast_ray(b: &mut Bencher, builder: tree::TreeBuilder) {
+    let mut rg = random_generator::RandomGenerator::from_seed(15);
+    let tree = tree::Tree::new_with_builder(random_triangles(&mut rg), builder);
+    let rays = random_rays(&mut rg);
+
+    b.iter(|| {
+        rays.iter()
+            .filter(|ray| tree.cast_ray(ray).is_some())
+            .count()
+    });
+}
+
+// bench_cast_ray_boxed casts the same rays through the same tree before it is flattened
+fn bench_cast_ray_boxed(b: &mut Bencher, builder: tree::TreeBuilder) {
+    let mut rg = random_generator::RandomGenerator::from_seed(15);
+    let tree = tree::BoxedTree::new_with_builder(random_triangles(&mut rg), builder);
+    let rays = random_rays(&mut rg);
+
+    b.iter(|| {
+        rays.iter()
+            .filter(|ray| tree.cast_ray(ray).is_some())
+            .count()
+    });
+}
+
+#[bench]
+fn cast_ray_median(b: &mut Bencher) {
+    bench_cast_ray(b, MEDIAN);
+}
+
+#[bench]
+fn cast_ray_median_boxed(b: &mut Bencher) {
+    bench_cast_ray_boxed(b, MEDIAN);
+}
+
+#[bench]
+fn cast_ray_sah(b: &mut Bencher) {
+    bench_cast_ray(b, sah());
+}
+
+#[bench]
+fn cast_ray_sah_boxed(b: &mut Bencher) {
+    bench_cast_ray_boxed(b, sah());
+}
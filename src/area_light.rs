@@ -0,0 +1,201 @@
+use std::f64::consts;
+
+use crate::{color, light, photon, quaternion, random_generator, ray, sampling, vector3};
+
+use crate::light::LightProtectedInterface;
+pub use crate::light::LightPublicInterface;
+
+// AreaShape is the shape of the surface of an area light, which lies in the plane across its forward
+// direction, centered on its position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AreaShape {
+    // width along the light's x axis and height along its y axis
+    Rectangle(f64, f64),
+    Disk(f64),
+}
+
+struct AreaLightStrategy {
+    m_shape: AreaShape,
+}
+
+impl light::LightStrategy for AreaLightStrategy {
+    // emit starts photons at uniformly distributed points of the surface, heading in directions spread with
+    // the cosine around the forward direction like from a Lambertian surface
+    fn emit(&self, base: &light::Light<AreaLightStrategy>, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator) {
+        let point = match self.m_shape {
+            AreaShape::Rectangle(width, height) => vector3::Vector3::new(
+                (random_generator.value(1.0) - 0.5) * width,
+                (random_generator.value(1.0) - 0.5) * height,
+                0.0,
+            ),
+            AreaShape::Disk(radius) => {
+                let r = radius * random_generator.value(1.0).sqrt();
+                let phi = random_generator.value(2.0 * consts::PI);
+
+                vector3::Vector3::new(r * phi.cos(), r * phi.sin(), 0.0)
+            },
+        };
+
+        let rotation = base.object.rotation();
+        let direction = rotation * sampling::cosine_hemisphere(random_generator);
+
+        photon.ray = ray::Ray::new(base.object.position() + rotation * point, direction);
+        photon.color = base.m_color * base.m_lumens * photon_brightness;
+        photon.bounces = 0;
+    }
+}
+
+// AreaLight emits light from one side of a rectangle or a disk, like a window or a light panel, with the
+// brightness being the power emitted per unit area
+pub struct AreaLight {
+    light: light::Light<AreaLightStrategy>,
+}
+
+impl AreaLight {
+    pub fn new() -> AreaLight {
+        let mut light = AreaLight {
+            light: light::Light::<AreaLightStrategy>::new(AreaLightStrategy {
+                m_shape: AreaShape::Rectangle(1.0, 1.0),
+            }),
+        };
+        light.set_rectangle(1.0, 1.0);
+
+        light
+    }
+
+    pub fn set_rectangle(&mut self, width: f64, height: f64) {
+        self.light.specialization.m_shape = AreaShape::Rectangle(width, height);
+        self.light.m_area = width * height;
+        self.light.update_parameters();
+    }
+
+    pub fn set_disk(&mut self, radius: f64) {
+        self.light.specialization.m_shape = AreaShape::Disk(radius);
+        self.light.m_area = consts::PI * radius * radius;
+        self.light.update_parameters();
+    }
+
+    pub fn get_shape(&self) -> AreaShape {
+        self.light.specialization.m_shape
+    }
+}
+
+impl Default for AreaLight {
+    fn default() -> Self {
+        AreaLight::new()
+    }
+}
+
+impl light::LightPublicInterface for AreaLight {
+    fn get_color(&self) -> color::Color {
+        self.light.get_color()
+    }
+
+    fn set_color(&mut self, color: color::Color) {
+        self.light.set_color(color)
+    }
+
+    fn get_brightness(&self) -> f64 {
+        self.light.get_brightness()
+    }
+
+    fn set_brightness(&mut self, brightness: f64) {
+        self.light.set_brightness(brightness)
+    }
+
+    fn emit(&self, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator) {
+        self.light.emit(photon, photon_brightness, random_generator)
+    }
+
+    fn set_position(&mut self, position: vector3::Vector3) {
+        self.light.set_position(position)
+    }
+
+    fn set_rotation(&mut self, rotation: quaternion::Quaternion) {
+        self.light.set_rotation(rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn lumens() {
+        let mut light = AreaLight::new();
+        light.set_brightness(10.0);
+        assert_approx_eq!(light.light.m_lumens, 10.0, 1e-9f64);
+
+        light.set_rectangle(2.0, 3.0);
+        assert_approx_eq!(light.light.m_lumens, 60.0, 1e-9f64);
+
+        light.set_disk(2.0);
+        assert_eq!(light.get_shape(), AreaShape::Disk(2.0));
+        assert_approx_eq!(light.light.m_lumens, 40.0 * consts::PI, 1e-9f64);
+    }
+
+    // emitted emits photons from an area light moved to (0, 5, 0) and turned to face down -y, checking
+    // that they leave the surface downwards, and returns their directions and their points on the surface
+    fn emitted(light: &mut AreaLight) -> Vec<(vector3::Vector3, vector3::Vector3)> {
+        light.set_brightness(1.0);
+        light.set_position(vector3::Vector3::new(0.0, 5.0, 0.0));
+        light.set_rotation(quaternion::Quaternion::from_axis_angle(&vector3::UNIT_X, consts::FRAC_PI_2));
+
+        let mut rg = random_generator::RandomGenerator::from_seed(43);
+        let mut photon = photon::Photon::default();
+
+        (0..50_000)
+            .map(|_| {
+                light.emit(&mut photon, 1.0, &mut rg);
+
+                assert_approx_eq!(photon.ray.origin.get_y(), 5.0, 1e-9f64);
+                assert!(photon.ray.direction.get_y() <= 0.0);
+                assert_approx_eq!(photon.ray.direction.norm(), 1.0, 1e-9f64);
+
+                (photon.ray.direction, photon.ray.origin)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn emit_rectangle() {
+        let mut light = AreaLight::new();
+        light.set_rectangle(2.0, 4.0);
+
+        let photons = emitted(&mut light);
+        let samples = photons.len() as f64;
+
+        // the light's y axis is turned onto z, so the rectangle is 2 wide along x and 4 deep along z
+        for (_, origin) in &photons {
+            assert!(origin.get_x().abs() <= 1.0 && origin.get_z().abs() <= 2.0);
+        }
+        let deep = photons.iter().filter(|(_, origin)| origin.get_z().abs() > 1.0).count() as f64;
+        assert_approx_eq!(deep / samples, 0.5, 0.01f64);
+
+        // directions are cosine distributed around the forward direction
+        let mean_cos = photons.iter().map(|(direction, _)| -direction.get_y()).sum::<f64>() / samples;
+        assert_approx_eq!(mean_cos, 2.0 / 3.0, 0.01f64);
+    }
+
+    #[test]
+    fn emit_disk() {
+        let mut light = AreaLight::new();
+        light.set_disk(2.0);
+
+        let photons = emitted(&mut light);
+        let samples = photons.len() as f64;
+
+        // points are uniform over the disk, so a quarter of them are within half of its radius
+        for (_, origin) in &photons {
+            assert!(origin.get_x().hypot(origin.get_z()) <= 2.0 + 1e-9);
+        }
+        let inner = photons.iter().filter(|(_, origin)| origin.get_x().hypot(origin.get_z()) < 1.0).count() as f64;
+        assert_approx_eq!(inner / samples, 0.25, 0.01f64);
+
+        // half of the cosine distributed directions are within 45 degrees of the forward direction
+        let within_45 = photons.iter().filter(|(direction, _)| -direction.get_y() > consts::FRAC_1_SQRT_2).count() as f64;
+        assert_approx_eq!(within_45 / samples, 0.5, 0.01f64);
+    }
+}
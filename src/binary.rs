@@ -0,0 +1,109 @@
+use std::io;
+
+use crate::{bounds, vector3};
+
+// little-endian encoding of the values stored in binary files such as the mesh cache
+
+pub fn write_u32(writer: &mut impl io::Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u64(writer: &mut impl io::Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_f64(writer: &mut impl io::Write, value: f64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_string(writer: &mut impl io::Write, value: &str) -> io::Result<()> {
+    write_u32(writer, value.len() as u32)?;
+    writer.write_all(value.as_bytes())
+}
+
+pub fn write_vector(writer: &mut impl io::Write, value: &vector3::Vector3) -> io::Result<()> {
+    write_f64(writer, value.get_x())?;
+    write_f64(writer, value.get_y())?;
+    write_f64(writer, value.get_z())
+}
+
+pub fn write_bounds(writer: &mut impl io::Write, value: &bounds::Bounds) -> io::Result<()> {
+    write_vector(writer, &value.minimum())?;
+    write_vector(writer, &value.maximum())
+}
+
+pub fn read_u32(reader: &mut impl io::Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub fn read_u64(reader: &mut impl io::Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub fn read_f64(reader: &mut impl io::Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+pub fn read_string(reader: &mut impl io::Read) -> io::Result<String> {
+    let length = read_u32(reader)? as usize;
+
+    let mut bytes = Vec::new();
+    io::Read::read_to_end(&mut io::Read::take(reader, length as u64), &mut bytes)?;
+    if bytes.len() != length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    String::from_utf8(bytes).map_err(|e| invalid_data(&e.to_string()))
+}
+
+pub fn read_vector(reader: &mut impl io::Read) -> io::Result<vector3::Vector3> {
+    Ok(vector3::Vector3::new(read_f64(reader)?, read_f64(reader)?, read_f64(reader)?))
+}
+
+pub fn read_bounds(reader: &mut impl io::Read) -> io::Result<bounds::Bounds> {
+    Ok(bounds::Bounds::from_vectors(read_vector(reader)?, read_vector(reader)?))
+}
+
+pub fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut buffer = Vec::<u8>::new();
+        write_u32(&mut buffer, 7).unwrap();
+        write_u64(&mut buffer, u64::MAX).unwrap();
+        write_f64(&mut buffer, -0.5).unwrap();
+        write_string(&mut buffer, "teapot").unwrap();
+        write_vector(&mut buffer, &vector3::Vector3::new(1.0, 2.0, 3.0)).unwrap();
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_u32(&mut reader).unwrap(), 7);
+        assert_eq!(read_u64(&mut reader).unwrap(), u64::MAX);
+        assert_eq!(read_f64(&mut reader).unwrap(), -0.5);
+        assert_eq!(read_string(&mut reader).unwrap(), "teapot");
+        assert_eq!(read_vector(&mut reader).unwrap().get_z(), 3.0);
+
+        // nothing is left, so reading more fails rather than returning zeros
+        assert_eq!(read_u32(&mut reader).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn truncated_string() {
+        let mut buffer = Vec::<u8>::new();
+        write_u32(&mut buffer, 100).unwrap();
+        buffer.extend_from_slice(b"short");
+
+        assert_eq!(read_string(&mut buffer.as_slice()).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
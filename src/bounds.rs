@@ -1,11 +1,16 @@
 use std::ops;
+use std::simd;
+use std::simd::cmp::SimdPartialOrd;
+use std::simd::num::SimdFloat;
 
 use strum::IntoEnumIterator;
 
 use crate::limits;
+use crate::quaternion;
 use crate::ray;
 use crate::vector3;
 
+#[derive(Clone, Copy)]
 pub struct Bounds {
     x: limits::Limits,
     y: limits::Limits,
@@ -54,11 +59,39 @@ impl Bounds {
         vector3::Vector3::new(self.x.max, self.y.max, self.z.max)
     }
 
+    pub fn surface_area(&self) -> f64 {
+        let width = self.x.max - self.x.min;
+        let height = self.y.max - self.y.min;
+        let depth = self.z.max - self.z.min;
+
+        2.0 * (width * height + height * depth + depth * width)
+    }
+
+    // transformed returns the bounds of the corners of these bounds, rotated and then moved to position
+    pub fn transformed(&self, rotation: quaternion::Quaternion, position: vector3::Vector3) -> Bounds {
+        let mut transformed: Option<Bounds> = None;
+
+        for x in [self.x.min, self.x.max] {
+            for y in [self.y.min, self.y.max] {
+                for z in [self.z.min, self.z.max] {
+                    let corner = Bounds::from_vector(position + rotation * vector3::Vector3::new(x, y, z));
+
+                    match transformed.as_mut() {
+                        Some(bounds) => *bounds += corner,
+                        None => transformed = Some(corner),
+                    }
+                }
+            }
+        }
+
+        transformed.unwrap()
+    }
+
     pub fn get_limits(&self, axis: &vector3::Axis) -> limits::Limits {
         match axis {
-            vector3::Axis::X => self.x.clone(),
-            vector3::Axis::Y => self.y.clone(),
-            vector3::Axis::Z => self.z.clone(),
+            vector3::Axis::X => self.x,
+            vector3::Axis::Y => self.y,
+            vector3::Axis::Z => self.z,
         }
     }
 
@@ -75,6 +108,12 @@ impl Bounds {
     }
 
     pub fn ray_intersects(&self, ray: &ray::Ray) -> bool {  // TODO(cdelguercio): move to Ray?
+        self.ray_entry_distance(ray).is_some()
+    }
+
+    // ray_entry_distance returns the distance along the ray at which it enters the bounds, which is 0.0
+    // if the ray starts inside of them, or None if the ray misses them
+    pub fn ray_entry_distance(&self, ray: &ray::Ray) -> Option<f64> {
         // t is the parameter of the line equation O{x,y,z} + D{x,y,z}*t
         let mut t_min = 0.0;
         let mut t_max = f64::INFINITY;
@@ -88,7 +127,7 @@ impl Bounds {
             // If the ray is parallel to the axis, it won't intersect the bounds unless it's inside
             if direction_component.abs() < f64::EPSILON {
                 if !bounds_component.contains(origin_component) {
-                    return false;
+                    return None;
                 }
                 // For all axes if the ray hits the "far" end of a bound before the "near" end of one of
                 // the other axes, it won't intersect the bounds
@@ -109,12 +148,106 @@ impl Bounds {
                 }
 
                 if t_min > t_max {
-                    return false;
+                    return None;
                 }
             }
         }
 
-        true
+        Some(t_min)
+    }
+}
+
+// Bounds4 holds up to four bounds with every component of the four in one vector, so that a ray can be
+// tested against all of them at once
+#[derive(Clone, Copy)]
+pub struct Bounds4 {
+    minimum: [simd::f64x4; 3],
+    maximum: [simd::f64x4; 3],
+    count: usize,
+}
+
+impl Bounds4 {
+    pub fn new(bounds: &[Bounds]) -> Self {
+        assert!(bounds.len() <= 4, "Bounds4 holds at most four bounds, got {}", bounds.len());
+
+        let mut minimum = [[0.0; 4]; 3];
+        let mut maximum = [[0.0; 4]; 3];
+
+        for (lane, b) in bounds.iter().enumerate() {
+            for (axis, limits) in [b.x, b.y, b.z].iter().enumerate() {
+                minimum[axis][lane] = limits.min;
+                maximum[axis][lane] = limits.max;
+            }
+        }
+
+        Bounds4 {
+            minimum: minimum.map(simd::f64x4::from_array),
+            maximum: maximum.map(simd::f64x4::from_array),
+            count: bounds.len(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn get(&self, lane: usize) -> Bounds {
+        assert!(lane < self.count);
+
+        Bounds::from_vectors(
+            vector3::Vector3::new(self.minimum[0][lane], self.minimum[1][lane], self.minimum[2][lane]),
+            vector3::Vector3::new(self.maximum[0][lane], self.maximum[1][lane], self.maximum[2][lane]),
+        )
+    }
+
+    // ray_entry_distances returns Bounds::ray_entry_distance for each of the bounds, None past the last one
+    pub fn ray_entry_distances(&self, ray: &ray::Ray) -> [Option<f64>; 4] {
+        if cfg!(feature = "simd") {
+            self.ray_entry_distances_simd(ray)
+        } else {
+            self.ray_entry_distances_scalar(ray)
+        }
+    }
+
+    fn ray_entry_distances_scalar(&self, ray: &ray::Ray) -> [Option<f64>; 4] {
+        let mut distances = [None; 4];
+
+        for (lane, distance) in distances.iter_mut().enumerate().take(self.count) {
+            *distance = self.get(lane).ray_entry_distance(ray);
+        }
+
+        distances
+    }
+
+    // ray_entry_distances_simd is the slab test of Bounds::ray_entry_distance on all four lanes at once
+    fn ray_entry_distances_simd(&self, ray: &ray::Ray) -> [Option<f64>; 4] {
+        let mut t_min = simd::f64x4::splat(0.0);
+        let mut t_max = simd::f64x4::splat(f64::INFINITY);
+        let mut hit = simd::mask64x4::from_array([0, 1, 2, 3].map(|lane| lane < self.count));
+
+        for (i, axis) in vector3::Axis::iter().enumerate() {
+            let origin_component = simd::f64x4::splat(ray.origin.get_component(&axis));
+            let direction_component = ray.direction.get_component(&axis);
+
+            // the direction is the same for every lane, so only the test of a parallel ray differs
+            if direction_component.abs() < f64::EPSILON {
+                hit &= self.minimum[i].simd_le(origin_component) & origin_component.simd_le(self.maximum[i]);
+            } else {
+                let direction_component = simd::f64x4::splat(direction_component);
+                let t1 = (self.minimum[i] - origin_component) / direction_component;
+                let t2 = (self.maximum[i] - origin_component) / direction_component;
+
+                t_min = t_min.simd_max(t1.simd_min(t2));
+                t_max = t_max.simd_min(t1.simd_max(t2));
+            }
+        }
+
+        hit &= t_min.simd_le(t_max);
+
+        let t_min = t_min.to_array();
+        let hit = hit.to_array();
+
+        [0, 1, 2, 3].map(|lane| hit[lane].then_some(t_min[lane]))
     }
 }
 
@@ -140,6 +273,8 @@ impl ops::AddAssign for Bounds {
 mod tests {
     use super::*;
 
+    use crate::random_generator;
+
     #[test]
     fn contains() {
         let bounds = Bounds::from_vectors(vector3::Vector3::new(0.0, 0.0, 0.0), vector3::Vector3::new(1.0, 1.0, 1.0));
@@ -176,6 +311,17 @@ mod tests {
         assert!(!other.intersects(&bounds));
     }
 
+    #[test]
+    fn surface_area() {
+        let bounds = Bounds::from_vectors(vector3::Vector3::new(0.0, 0.0, 0.0), vector3::Vector3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(bounds.surface_area(), 22.0);
+
+        let flat = Bounds::from_vectors(vector3::Vector3::new(0.0, 0.0, 0.0), vector3::Vector3::new(2.0, 2.0, 0.0));
+
+        assert_eq!(flat.surface_area(), 8.0);
+    }
+
     #[test]
     fn ray_intersects() {
         let ray = ray::Ray::new(
@@ -190,4 +336,73 @@ mod tests {
 
         assert!(bounds.ray_intersects(&ray));
     }
+
+    #[test]
+    fn ray_entry_distance() {
+        let bounds = Bounds::from_vectors(
+            vector3::Vector3::new(1.0, -1.0, -1.0),
+            vector3::Vector3::new(2.0, 1.0, 1.0),
+        );
+
+        let outside_ray = ray::Ray::new(
+            vector3::Vector3::new(-2.0, 0.0, 0.0),
+            vector3::Vector3::new(1.0, 0.0, 0.0),
+        );
+        let inside_ray = ray::Ray::new(
+            vector3::Vector3::new(1.5, 0.0, 0.0),
+            vector3::Vector3::new(1.0, 0.0, 0.0),
+        );
+        let miss_ray = ray::Ray::new(
+            vector3::Vector3::new(-2.0, 0.0, 0.0),
+            vector3::Vector3::new(-1.0, 0.0, 0.0),
+        );
+
+        assert_eq!(bounds.ray_entry_distance(&outside_ray), Some(3.0));
+        assert_eq!(bounds.ray_entry_distance(&inside_ray), Some(0.0));
+        assert_eq!(bounds.ray_entry_distance(&miss_ray), None);
+        assert!(!bounds.ray_intersects(&miss_ray));
+    }
+
+    #[test]
+    fn transformed() {
+        let bounds = Bounds::from_vectors(vector3::Vector3::new(0.0, 0.0, 0.0), vector3::Vector3::new(2.0, 1.0, 1.0));
+
+        // a quarter turn around the z axis takes x to y
+        let rotation = quaternion::Quaternion::from_axis_angle(&vector3::Vector3::new(0.0, 0.0, 1.0), std::f64::consts::FRAC_PI_2);
+        let transformed = bounds.transformed(rotation, vector3::Vector3::new(10.0, 0.0, 0.0));
+
+        assert!((transformed.minimum() - vector3::Vector3::new(9.0, 0.0, 0.0)).norm() < 1e-9);
+        assert!((transformed.maximum() - vector3::Vector3::new(10.0, 2.0, 1.0)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn bounds4_matches_ray_entry_distance() {
+        let mut rg = random_generator::RandomGenerator::from_seed(16);
+
+        let random_bounds = |rg: &mut random_generator::RandomGenerator| {
+            let corner = vector3::Vector3::new(rg.value(10.0), rg.value(10.0), rg.value(10.0));
+            Bounds::from_vectors(corner, corner + vector3::Vector3::new(rg.value(3.0), rg.value(3.0), rg.value(3.0)))
+        };
+
+        for count in 0..=4 {
+            for _ in 0..200 {
+                let bounds: Vec<Bounds> = (0..count).map(|_| random_bounds(&mut rg)).collect();
+                let bounds4 = Bounds4::new(&bounds);
+
+                // some of the rays are parallel to an axis, to cover the containment test
+                let mut direction = vector3::Vector3::new(rg.value(2.0) - 1.0, rg.value(2.0) - 1.0, rg.value(2.0) - 1.0);
+                if rg.value(1.0) < 0.25 {
+                    direction = vector3::Vector3::new(direction.get_x(), 0.0, direction.get_z());
+                }
+                let ray = ray::Ray::new(vector3::Vector3::new(rg.value(12.0), rg.value(12.0), rg.value(12.0)), direction);
+
+                let expected: Vec<Option<f64>> = bounds.iter().map(|b| b.ray_entry_distance(&ray)).collect();
+
+                for distances in [bounds4.ray_entry_distances_simd(&ray), bounds4.ray_entry_distances_scalar(&ray)] {
+                    assert_eq!(&distances[..count], &expected[..]);
+                    assert!(distances[count..].iter().all(|d| d.is_none()));
+                }
+            }
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use std::cmp;
 
-use crate::{angle, object, pixel_coords, pyramid, vector3};
+use crate::{angle, object, pixel_coords, pyramid, quaternion, vector3};
 
 pub struct Camera {
     m_width: usize,
@@ -51,7 +51,7 @@ impl Camera {
         self.m_horizontal_fov = *vertical_fov * self.m_aspect_ratio;
     }
 
-    fn set_from_render_configuration(&mut self, width: usize, height: usize) {
+    pub fn set_from_render_configuration(&mut self, width: usize, height: usize) {
         if width == 0 || height == 0 {
             panic!("Cannot configure Camera with 0 width or 0 height");
         }
@@ -105,11 +105,18 @@ impl Camera {
     pub fn forward(&self) -> vector3::Vector3 {
         self.object.forward()
     }
+
+    pub fn set_position(&mut self, position: vector3::Vector3) {
+        self.object.transform.position = position;
+    }
+
+    pub fn set_rotation(&mut self, rotation: quaternion::Quaternion) {
+        self.object.transform.rotation = rotation;
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::quaternion;
     use super::*;
 
     #[test]
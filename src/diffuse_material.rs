@@ -1,10 +1,26 @@
 use std::f64::consts;
 
-use crate::{angle_generator, color, material, photon, random_generator, ray, vector3};
+use serde::Deserialize;
+
+use crate::{angle_generator, color, hit, material, photon, random_generator, ray, sampling, texture_node, vector3};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffuseMode {
+    // scatters photons in a cone around the mirror direction, and shows them brightest to a camera in the
+    // mirror direction
+    ReflectionCone,
+    // an ideal matte surface that scatters photons with the cosine of their angle to the normal and looks
+    // equally bright from every direction, reflecting at most all of the light it receives
+    Lambertian,
+}
 
 pub struct DiffuseMaterial {
     name: String,
     m_color: color::Color,
+    m_mode: DiffuseMode,
+    // an image or procedural texture evaluated at a hit and multiplied with m_color
+    m_texture: Option<Box<dyn texture_node::TextureNode<color::Color>>>,
     m_angle_generator: angle_generator::AngleGenerator,
 }
 
@@ -13,6 +29,8 @@ impl DiffuseMaterial {
         DiffuseMaterial {
             name: name.to_string(),
             m_color: color::Color::new(1.0, 1.0, 1.0),
+            m_mode: DiffuseMode::ReflectionCone,
+            m_texture: None,
             m_angle_generator: angle_generator::AngleGenerator::default(),
         }
     }
@@ -21,9 +39,57 @@ impl DiffuseMaterial {
         DiffuseMaterial {
             name: name.to_string(),
             m_color: *color,
+            m_mode: DiffuseMode::ReflectionCone,
+            m_texture: None,
             m_angle_generator: angle_generator::AngleGenerator::default(),
         }
     }
+
+    pub fn set_mode(&mut self, mode: DiffuseMode) {
+        self.m_mode = mode;
+    }
+
+    pub fn set_texture(&mut self, texture: impl texture_node::TextureNode<color::Color> + 'static) {
+        self.m_texture = Some(Box::new(texture));
+    }
+
+    // color_at returns the color of the material at a hit
+    pub fn color_at(&self, hit: &hit::Hit) -> color::Color {
+        match &self.m_texture {
+            Some(texture) => self.m_color * texture.evaluate(hit),
+            None => self.m_color,
+        }
+    }
+
+    // albedo is the fraction of the light arriving at a hit that a Lambertian surface reflects, which can't
+    // be more than all of it
+    fn albedo(&self, hit: &hit::Hit) -> color::Color {
+        let color = self.color_at(hit);
+
+        color::Color::new(color.red.clamp(0.0, 1.0), color.green.clamp(0.0, 1.0), color.blue.clamp(0.0, 1.0))
+    }
+
+    fn lambertian_bounce(&self, photon_hit: &photon::PhotonHit, random_generator: &mut random_generator::RandomGenerator) -> photon::Photon {
+        let hit = &photon_hit.hit;
+        let direction = sampling::to_world(&sampling::cosine_hemisphere(random_generator), &hit.normal);
+
+        // the cosine of the brdf cancels with the density of the direction, leaving only the albedo
+        let color = if hit.reflects_validly(&-photon_hit.photon.ray.direction, &direction) {
+            self.albedo(hit) * photon_hit.photon.color
+        } else {
+            color::Color::default()
+        };
+
+        photon::Photon {
+            bounces: photon_hit.photon.bounces + 1,
+            medium: photon_hit.photon.medium,
+            color,
+            ray: ray::Ray {
+                origin: hit.offset_origin(&direction),
+                direction,
+            },
+        }
+    }
 }
 
 impl material::Material for DiffuseMaterial {
@@ -32,32 +98,191 @@ impl material::Material for DiffuseMaterial {
     }
 
     fn color_for_hit(&self, pixel_direction: &vector3::Vector3, photon_hit: &photon::PhotonHit) -> color::Color {
+        if self.m_mode == DiffuseMode::Lambertian {
+            if !photon_hit.hit.is_above(&-photon_hit.photon.ray.direction) {
+                return color::Color::default();
+            }
+
+            return self.albedo(&photon_hit.hit) * photon_hit.photon.color / consts::PI;
+        }
+
         let reflection = vector3::Vector3::reflected(&photon_hit.photon.ray.direction, &photon_hit.hit.normal);
         let reflection_dot = vector3::Vector3::dot(&-pixel_direction, &reflection);
         let brightness = ((reflection_dot + 1.0) / 2.0).max(0.0);
 
-        self.m_color * photon_hit.photon.color * brightness
+        self.color_at(&photon_hit.hit) * photon_hit.photon.color * brightness
     }
 
     fn bounce(&self, photon_hit: &photon::PhotonHit, random_generator: &mut random_generator::RandomGenerator) -> photon::Photon {
+        if self.m_mode == DiffuseMode::Lambertian {
+            return self.lambertian_bounce(photon_hit, random_generator);
+        }
+
         let reflection = vector3::Vector3::reflected(&photon_hit.photon.ray.direction, &photon_hit.hit.normal);
 
         let mut brightness = 1.0 / consts::PI * 2.0;
 
         let offset_reflection = self.m_angle_generator.generate_offset_vector(&reflection, random_generator);
 
-        let normal_dot = vector3::Vector3::dot(&offset_reflection, &photon_hit.hit.normal);
-        if normal_dot <= 0.0 {
+        if !photon_hit.hit.reflects_validly(&-photon_hit.photon.ray.direction, &offset_reflection) {
             brightness = 0.0;
         }
 
         photon::Photon{
             bounces: photon_hit.photon.bounces + 1,
-            color: self.m_color * photon_hit.photon.color * brightness,
+            medium: photon_hit.photon.medium,
+            color: self.color_at(&photon_hit.hit) * photon_hit.photon.color * brightness,
             ray: ray::Ray{
-                origin: photon_hit.hit.position,
+                origin: photon_hit.hit.offset_origin(&offset_reflection),
                 direction: offset_reflection,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::{mesh, mesh_volume, renderer, texture, triangle, uv, volume_tree};
+    use crate::material::Material;
+
+    fn lambertian(color: color::Color) -> DiffuseMaterial {
+        let mut material = DiffuseMaterial::from_color("Lambertian", &color);
+        material.set_mode(DiffuseMode::Lambertian);
+
+        material
+    }
+
+    // the inside of a cube around the origin, with its normals facing in
+    fn closed_box() -> volume_tree::VolumeTree {
+        let mut triangles = Vec::<triangle::Triangle>::new();
+
+        for axis in [vector3::UNIT_X, vector3::UNIT_Y, vector3::UNIT_Z] {
+            let u = vector3::Vector3::new(axis.get_y(), axis.get_z(), axis.get_x());
+            let v = vector3::Vector3::cross(&axis, &u);
+
+            for side in [-1.0, 1.0] {
+                let center = axis * side;
+                let corners = [center - u - v, center + u - v, center + u + v, center - u + v];
+
+                for [a, b, c] in [[corners[0], corners[1], corners[2]], [corners[0], corners[2], corners[3]]] {
+                    let faces_in = vector3::Vector3::dot(&vector3::Vector3::cross(&(b - a), &(c - a)), &center) < 0.0;
+                    triangles.push(if faces_in { triangle::Triangle::new(a, b, c) } else { triangle::Triangle::new(a, c, b) });
+                }
+            }
+        }
+
+        volume_tree::VolumeTree::new(vec![Box::new(mesh_volume::MeshVolume::new(0, mesh::Mesh::new("box", triangles)))])
+    }
+
+    #[test]
+    fn color_at_texture_coordinates() {
+        let mut material = DiffuseMaterial::from_color("Half", &color::Color::new(0.5, 0.5, 0.5));
+
+        let hit = hit::Hit {
+            uv: uv::Uv::new(0.75, 0.5),
+            ..Default::default()
+        };
+
+        assert_eq!(material.color_at(&hit).red, 0.5);
+
+        material.set_texture(texture::Texture::new(2, 1, vec![color::Color::new(0.0, 1.0, 0.0), color::Color::new(1.0, 0.0, 0.0)]));
+
+        let color = material.color_at(&hit);
+        assert_eq!(color.red, 0.5);
+        assert_eq!(color.green, 0.0);
+    }
+
+    #[test]
+    fn color_at_procedural_texture() {
+        let mut material = DiffuseMaterial::new("Checker");
+        material.set_texture(texture_node::Checker::new(color::Color::new(1.0, 1.0, 1.0), color::Color::new(0.25, 0.25, 0.25)));
+
+        let hit_at = |u: f64| hit::Hit {
+            uv: uv::Uv::new(u, 0.5),
+            ..Default::default()
+        };
+
+        assert_eq!(material.color_at(&hit_at(0.5)).red, 1.0);
+        assert_eq!(material.color_at(&hit_at(1.5)).red, 0.25);
+    }
+
+    #[test]
+    fn lambertian_white_furnace() {
+        // photons bouncing around the inside of a closed white box keep all of their energy forever, and in a
+        // grey box lose the same fraction at every bounce
+        let volumes = closed_box();
+        let renderer = renderer::Renderer::new();
+        let mut rg = random_generator::RandomGenerator::from_seed(19);
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+
+        for albedo in [1.0f64, 0.5] {
+            let material = lambertian(color::Color::new(albedo, albedo, albedo));
+            let mut total = 0.0;
+
+            for _ in 0..200 {
+                let mut photon = photon::Photon {
+                    ray: ray::Ray::new(vector3::Vector3::default(), vector3::Vector3::random_sphere(&mut rg, 1.0)),
+                    color: color::Color::new(1.0, 1.0, 1.0),
+                    ..Default::default()
+                };
+
+                for _ in 0..10 {
+                    let photon_hit = renderer.process_photon(&photon, &mut cast_buffer, &volumes).unwrap();
+                    photon = material.bounce(&photon_hit, &mut rg);
+                }
+
+                assert_approx_eq!(photon.color.red, albedo.powi(10), 1e-12f64);
+                total += photon.color.brightness();
+            }
+
+            assert_approx_eq!(total / 200.0, 3.0 * albedo.powi(10), 1e-9f64);
+        }
+    }
+
+    #[test]
+    fn lambertian_reflects_albedo() {
+        // the light a surface shows to every direction over the hemisphere, weighted by the cosine of the
+        // direction, adds up to the albedo, clamped to all of the light
+        let material = lambertian(color::Color::new(0.8, 2.0, 0.0));
+        let mut rg = random_generator::RandomGenerator::from_seed(23);
+
+        let photon_hit = photon::PhotonHit {
+            hit: hit::Hit::new(vector3::Vector3::default(), vector3::UNIT_Z, 1.0, 0),
+            photon: photon::Photon {
+                ray: ray::Ray::new(vector3::UNIT_Z, vector3::Vector3::new(0.6, 0.0, -0.8)),
+                color: color::Color::new(1.0, 1.0, 1.0),
+                ..Default::default()
+            },
+        };
+
+        let samples = 100_000;
+        let mut reflected = color::Color::default();
+
+        for _ in 0..samples {
+            let direction = vector3::Vector3::random_sphere(&mut rg, 1.0);
+            if direction.get_z() > 0.0 {
+                reflected += material.color_for_hit(&-direction, &photon_hit) * direction.get_z() * 4.0 * consts::PI;
+            }
+        }
+
+        assert_approx_eq!(reflected.red / samples as f64, 0.8, 0.01f64);
+        assert_approx_eq!(reflected.green / samples as f64, 1.0, 0.01f64);
+        assert_eq!(reflected.blue, 0.0);
+
+        // bounced photons carry the same albedo in directions spread with the cosine around the normal
+        let photon = material.bounce(&photon_hit, &mut rg);
+        assert_approx_eq!(photon.color.red, 0.8, 1e-12f64);
+        assert_approx_eq!(photon.color.green, 1.0, 1e-12f64);
+        assert!(photon.ray.direction.get_z() > 0.0);
+
+        // photons arriving from behind the surface aren't reflected
+        let mut behind = photon_hit;
+        behind.photon.ray.direction = -behind.photon.ray.direction;
+        assert_eq!(material.bounce(&behind, &mut rg).color.brightness(), 0.0);
+        assert_eq!(material.color_for_hit(&-vector3::UNIT_Z, &behind).brightness(), 0.0);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{color, material, photon, random_generator, ray, vector3};
+
+// EmissiveMaterial turns the meshes it is applied to into light sources, like light panels or neon signs,
+// that emit power per unit area from the side their normals face. It absorbs all of the light it receives.
+pub struct EmissiveMaterial {
+    name: String,
+    m_color: color::Color,
+    m_power: f64,
+}
+
+impl EmissiveMaterial {
+    pub fn new(name: &str, color: &color::Color, power: f64) -> EmissiveMaterial {
+        EmissiveMaterial {
+            name: name.to_string(),
+            m_color: *color,
+            m_power: power,
+        }
+    }
+}
+
+impl material::Material for EmissiveMaterial {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    // the light the surface emits is shown to the camera as it is emitted, by the Pipeline
+    fn color_for_hit(&self, _pixel_direction: &vector3::Vector3, _photon_hit: &photon::PhotonHit) -> color::Color {
+        color::Color::default()
+    }
+
+    fn bounce(&self, photon_hit: &photon::PhotonHit, _random_generator: &mut random_generator::RandomGenerator) -> photon::Photon {
+        photon::Photon {
+            bounces: photon_hit.photon.bounces + 1,
+            medium: photon_hit.photon.medium,
+            color: color::Color::default(),
+            ray: ray::Ray {
+                origin: photon_hit.hit.position,
+                direction: photon_hit.photon.ray.direction,
+            },
+        }
+    }
+
+    fn get_emission(&self) -> Option<(color::Color, f64)> {
+        (self.m_power > 0.0).then_some((self.m_color, self.m_power))
+    }
+}
@@ -0,0 +1,222 @@
+use std::f64::consts;
+
+use crate::{color, hit, library, material, photon, random_generator, ray, sampling, triangle, vector3, volume_tree};
+
+// EmittingTriangle is a triangle of a volume with an emissive material, in world space
+struct EmittingTriangle {
+    triangle: triangle::Triangle,
+    color: color::Color,
+    material_index: usize,
+}
+
+// Emitters are the triangles of the volumes whose materials emit light, which the Pipeline emits photons
+// from alongside the lights
+pub struct Emitters {
+    m_triangles: Vec<EmittingTriangle>,
+    // running total of the area times the power of the triangles, to pick them in proportion to the power
+    // they emit
+    m_cumulative_power: Vec<f64>,
+}
+
+impl Emitters {
+    pub fn new(volumes: &volume_tree::VolumeTree, material_library: &library::Library<Box<dyn material::Material>>) -> Self {
+        let mut triangles = Vec::<EmittingTriangle>::new();
+        let mut cumulative_power = Vec::<f64>::new();
+        let mut total_power = 0.0;
+
+        for volume in volumes.iter() {
+            let material_index = volume.get_material_index();
+            let Some((color, power)) = material_library.fetch_by_index(material_index).get_emission() else {
+                continue;
+            };
+
+            for triangle in volume.get_triangles() {
+                let triangle_power = triangle.area() * power;
+                if triangle_power <= 0.0 {
+                    continue;
+                }
+
+                total_power += triangle_power;
+                cumulative_power.push(total_power);
+                triangles.push(EmittingTriangle {
+                    triangle,
+                    color,
+                    material_index,
+                });
+            }
+        }
+
+        Emitters {
+            m_triangles: triangles,
+            m_cumulative_power: cumulative_power,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.m_triangles.is_empty()
+    }
+
+    // get_power returns the power emitted by all of the triangles
+    pub fn get_power(&self) -> f64 {
+        self.m_cumulative_power.last().copied().unwrap_or(0.0)
+    }
+
+    // emit starts a photon at a point of a triangle picked in proportion to its power, heading in a
+    // direction spread with the cosine around the triangle's normal, and returns the hit on the triangle
+    // that it starts from. The photon carries the power of all of the triangles scaled by photon_brightness,
+    // so that on average it carries the share of every triangle.
+    pub fn emit(&self, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator) -> hit::Hit {
+        assert!(!self.is_empty(), "Can't emit a photon without emitting triangles");
+
+        let target = random_generator.value(self.get_power());
+        let index = self.m_cumulative_power
+            .partition_point(|&power| power <= target)
+            .min(self.m_triangles.len() - 1);
+        let emitting = &self.m_triangles[index];
+
+        // a uniformly distributed point on the triangle
+        let r = random_generator.value(1.0).sqrt();
+        let s = random_generator.value(1.0);
+        let coords = vector3::Vector3::new(1.0 - r, r * (1.0 - s), r * s);
+
+        let normal = emitting.triangle.get_face_normal();
+        let emission_hit = hit::Hit::with_shading(
+            emitting.triangle.get_position(coords),
+            normal,
+            normal,
+            coords,
+            emitting.triangle.get_uv(coords),
+            0.0,
+            emitting.material_index,
+        );
+
+        let direction = sampling::to_world(&sampling::cosine_hemisphere(random_generator), &normal);
+
+        photon.ray = ray::Ray::new(emission_hit.offset_origin(&direction), direction);
+        photon.color = emitting.color * self.get_power() * photon_brightness;
+        photon.bounces = 0;
+        photon.medium = None;
+
+        emission_hit
+    }
+}
+
+// emitted_radiance is the color a camera sees in the direction of an emitted photon's origin. The surfaces
+// emit equally bright in every direction, like Lambertian surfaces reflect.
+pub fn emitted_radiance(photon: &photon::Photon) -> color::Color {
+    photon.color / consts::PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::{diffuse_material, emissive_material, mesh, mesh_volume, quaternion, volume};
+
+    // a right triangle in the plane z = 0 facing +z, with legs of length size
+    fn right_triangle(size: f64, x: f64) -> triangle::Triangle {
+        triangle::Triangle::new(
+            vector3::Vector3::new(x, 0.0, 0.0),
+            vector3::Vector3::new(x + size, 0.0, 0.0),
+            vector3::Vector3::new(x, size, 0.0),
+        )
+    }
+
+    fn material_library() -> library::Library<Box<dyn material::Material>> {
+        let mut material_library = library::Library::<Box<dyn material::Material>>::new();
+        material_library.add("Dim", Box::new(emissive_material::EmissiveMaterial::new("Dim", &color::Color::new(1.0, 0.0, 0.0), 1.0)));
+        material_library.add("Bright", Box::new(emissive_material::EmissiveMaterial::new("Bright", &color::Color::new(0.0, 1.0, 0.0), 3.0)));
+        material_library.add("Wall", Box::new(diffuse_material::DiffuseMaterial::new("Wall")));
+
+        material_library
+    }
+
+    #[test]
+    fn emitters_of_emissive_volumes() {
+        let material_library = material_library();
+
+        let mut moved = mesh_volume::MeshVolume::new(1, mesh::Mesh::new("bright", vec![right_triangle(2.0, 0.0)]));
+        moved.set_position(vector3::Vector3::new(0.0, 0.0, 5.0));
+        moved.set_rotation(quaternion::Quaternion::from_axis_angle(&vector3::UNIT_X, consts::PI));
+
+        let volumes = volume_tree::VolumeTree::new(vec![
+            Box::new(mesh_volume::MeshVolume::new(0, mesh::Mesh::new("dim", vec![right_triangle(1.0, 10.0)]))),
+            Box::new(moved),
+            Box::new(mesh_volume::MeshVolume::new(2, mesh::Mesh::new("wall", vec![right_triangle(100.0, 20.0)]))),
+        ]);
+
+        let emitters = Emitters::new(&volumes, &material_library);
+
+        // the wall doesn't emit, the dim triangle has an area of 0.5 and the bright one of 2
+        assert_eq!(emitters.m_triangles.len(), 2);
+        assert_approx_eq!(emitters.get_power(), 0.5 + 6.0, 1e-9f64);
+
+        let mut rg = random_generator::RandomGenerator::from_seed(29);
+        let mut bright = 0;
+        let samples = 10_000;
+        let mut photon = photon::Photon::default();
+
+        for _ in 0..samples {
+            let emission_hit = emitters.emit(&mut photon, 1.0, &mut rg);
+
+            if emission_hit.material_index == 1 {
+                bright += 1;
+
+                // the bright triangle was turned over and moved, so it faces -z from z = 5
+                assert_approx_eq!(emission_hit.position.get_z(), 5.0, 1e-9f64);
+                assert!(photon.ray.direction.get_z() < 0.0);
+                assert_approx_eq!(photon.color.green, 6.5, 1e-9f64);
+            } else {
+                assert_approx_eq!(emission_hit.position.get_z(), 0.0, 1e-9f64);
+                assert!(emission_hit.position.get_x() >= 10.0 && emission_hit.position.get_x() + emission_hit.position.get_y() <= 11.0);
+                assert!(photon.ray.direction.get_z() > 0.0);
+                assert_approx_eq!(photon.color.red, 6.5, 1e-9f64);
+            }
+        }
+
+        // triangles are picked in proportion to their area times their power
+        assert_approx_eq!(bright as f64 / samples as f64, 6.0 / 6.5, 0.01f64);
+    }
+
+    #[test]
+    fn emit_cosine_distribution() {
+        let material_library = material_library();
+        let volumes = volume_tree::VolumeTree::new(vec![
+            Box::new(mesh_volume::MeshVolume::new(0, mesh::Mesh::new("dim", vec![right_triangle(1.0, 0.0)]))) as Box<dyn volume::VolumePublicInterface>,
+        ]);
+        let emitters = Emitters::new(&volumes, &material_library);
+
+        let mut rg = random_generator::RandomGenerator::from_seed(31);
+        let mut photon = photon::Photon::default();
+        let samples = 20_000;
+        let mut mean_cos = 0.0;
+        let mut mean_position = vector3::Vector3::default();
+
+        for _ in 0..samples {
+            let emission_hit = emitters.emit(&mut photon, 0.5, &mut rg);
+
+            assert_approx_eq!(photon.color.red, 0.25, 1e-9f64);
+            assert_eq!(photon.bounces, 0);
+            mean_cos += photon.ray.direction.get_z() / samples as f64;
+            mean_position = mean_position + emission_hit.position / samples as f64;
+        }
+
+        // directions are spread with the cosine, and points uniformly so that they average to the centroid
+        assert_approx_eq!(mean_cos, 2.0 / 3.0, 0.01f64);
+        assert_approx_eq!(mean_position.get_x(), 1.0 / 3.0, 0.01f64);
+        assert_approx_eq!(mean_position.get_y(), 1.0 / 3.0, 0.01f64);
+    }
+
+    #[test]
+    fn no_emitters() {
+        let volumes = volume_tree::VolumeTree::new(vec![
+            Box::new(mesh_volume::MeshVolume::new(2, mesh::Mesh::new("wall", vec![right_triangle(1.0, 0.0)]))) as Box<dyn volume::VolumePublicInterface>,
+        ]);
+        let emitters = Emitters::new(&volumes, &material_library());
+
+        assert!(emitters.is_empty());
+        assert_eq!(emitters.get_power(), 0.0);
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::io;
+use std::path;
+
+use crate::{film, image_writer};
+
+const MAGIC_NUMBER: u32 = 20000630;
+// single-part scanline file, version 2
+const VERSION: u32 = 2;
+const PIXEL_TYPE_FLOAT: i32 = 2;
+// channels must be listed in alphabetical order, and the pixel data of a scanline follows that order
+const CHANNEL_NAMES: [&str; 3] = ["B", "G", "R"];
+
+// ExrWriter writes the radiance of a film to an uncompressed OpenEXR file with 32-bit float RGB channels
+pub struct ExrWriter {
+    m_path: path::PathBuf,
+}
+
+impl ExrWriter {
+    pub fn new(file: &path::Path) -> Self {
+        ExrWriter {
+            m_path: file.to_path_buf(),
+        }
+    }
+
+    fn write_to(&self, writer: &mut impl io::Write, film: &film::Film) -> io::Result<()> {
+        let width = film.get_width();
+        let height = film.get_height();
+
+        let mut header = Vec::<u8>::new();
+        header.extend_from_slice(&MAGIC_NUMBER.to_le_bytes());
+        header.extend_from_slice(&VERSION.to_le_bytes());
+
+        let mut channels = Vec::<u8>::new();
+        for name in CHANNEL_NAMES {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            channels.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+            // pLinear and three reserved bytes
+            channels.extend_from_slice(&[0, 0, 0, 0]);
+            // x and y sampling
+            channels.extend_from_slice(&1i32.to_le_bytes());
+            channels.extend_from_slice(&1i32.to_le_bytes());
+        }
+        channels.push(0);
+
+        let mut window = Vec::<u8>::new();
+        for value in [0, 0, width as i32 - 1, height as i32 - 1] {
+            window.extend_from_slice(&value.to_le_bytes());
+        }
+
+        write_attribute(&mut header, "channels", "chlist", &channels);
+        // no compression
+        write_attribute(&mut header, "compression", "compression", &[0]);
+        write_attribute(&mut header, "dataWindow", "box2i", &window);
+        write_attribute(&mut header, "displayWindow", "box2i", &window);
+        // increasing y
+        write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        write_attribute(&mut header, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+        write_attribute(&mut header, "screenWindowCenter", "v2f", &[0.0f32.to_le_bytes(), 0.0f32.to_le_bytes()].concat());
+        write_attribute(&mut header, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+        header.push(0);
+
+        writer.write_all(&header)?;
+
+        // every scanline is its own block of y, data size and the channels of the scanline one after another
+        let block_size = 8 + CHANNEL_NAMES.len() * width * 4;
+        let first_block = header.len() + height * 8;
+
+        for y in 0..height {
+            writer.write_all(&((first_block + y * block_size) as u64).to_le_bytes())?;
+        }
+
+        for y in 0..height {
+            writer.write_all(&(y as i32).to_le_bytes())?;
+            writer.write_all(&((block_size - 8) as i32).to_le_bytes())?;
+
+            for name in CHANNEL_NAMES {
+                for x in 0..width {
+                    let radiance = film.get_radiance(x, y);
+                    let value = match name {
+                        "R" => radiance.red,
+                        "G" => radiance.green,
+                        _ => radiance.blue,
+                    };
+
+                    writer.write_all(&(value as f32).to_le_bytes())?;
+                }
+            }
+        }
+
+        writer.flush()
+    }
+}
+
+impl image_writer::ImageWriter for ExrWriter {
+    fn write(&self, film: &film::Film) -> Result<(), image_writer::ImageWriterError> {
+        let file = fs::File::create(&self.m_path)
+            .map_err(|e| image_writer::ImageWriterError::Io(self.m_path.clone(), e))?;
+
+        self.write_to(&mut io::BufWriter::new(file), film)
+            .map_err(|e| image_writer::ImageWriterError::Io(self.m_path.clone(), e))
+    }
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, attribute_type: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(attribute_type.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::color;
+
+    fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn read_f32(buffer: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn write_film() {
+        let mut film = film::Film::new(2, 3);
+        film.add_photons(2);
+        film.add_sample(1, 2, color::Color::new(1000.0, 0.5, 0.25));
+
+        let mut buffer = Vec::<u8>::new();
+        ExrWriter::new(path::Path::new("unused.exr")).write_to(&mut buffer, &film).unwrap();
+
+        assert_eq!(read_u32(&buffer, 0), MAGIC_NUMBER);
+        assert_eq!(read_u32(&buffer, 4), VERSION);
+
+        // the header ends with an empty attribute name right before the offset table
+        let block_size = 8 + 3 * 2 * 4;
+        let table = buffer.len() - 3 * block_size - 3 * 8;
+        assert_eq!(buffer[table - 1], 0);
+
+        let last_block = u64::from_le_bytes(buffer[table + 16..table + 24].try_into().unwrap()) as usize;
+        assert_eq!(last_block, buffer.len() - block_size);
+        assert_eq!(read_u32(&buffer, last_block), 2);
+        assert_eq!(read_u32(&buffer, last_block + 4) as usize, block_size - 8);
+
+        // B, G and R of the scanline, two pixels each
+        let data = last_block + 8;
+        assert_eq!(read_f32(&buffer, data), 0.0);
+        assert_eq!(read_f32(&buffer, data + 4), 0.125);
+        assert_eq!(read_f32(&buffer, data + 12), 0.25);
+        assert_eq!(read_f32(&buffer, data + 20), 500.0);
+    }
+}
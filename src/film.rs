@@ -0,0 +1,145 @@
+use crate::color;
+
+#[derive(Clone, Copy, Default)]
+struct FilmPixel {
+    sum: color::Color,
+    sample_count: u32,
+}
+
+// Film accumulates the photon contributions to every pixel in floating point, so that the image
+// converges as more photons are traced instead of showing the last photon to reach each pixel
+pub struct Film {
+    m_width: usize,
+    m_height: usize,
+    m_pixels: Vec<FilmPixel>,
+    // number of photons emitted from the lights, which every contribution is divided by
+    m_photon_count: usize,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Film {
+            m_width: width,
+            m_height: height,
+            m_pixels: vec![FilmPixel::default(); width * height],
+            m_photon_count: 0,
+        }
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.m_width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.m_height
+    }
+
+    pub fn get_photon_count(&self) -> usize {
+        self.m_photon_count
+    }
+
+    pub fn add_photons(&mut self, count: usize) {
+        self.m_photon_count += count;
+    }
+
+    pub fn add_sample(&mut self, x: usize, y: usize, color: color::Color) {
+        if x >= self.m_width || y >= self.m_height {
+            panic!("Cannot add sample at ({}, {}), film is only {}x{}", x, y, self.m_width, self.m_height);
+        }
+
+        let film_pixel = &mut self.m_pixels[x + y * self.m_width];
+        film_pixel.sum += color;
+        film_pixel.sample_count += 1;
+    }
+
+    pub fn get_sample_count(&self, x: usize, y: usize) -> u32 {
+        if x >= self.m_width || y >= self.m_height {
+            panic!("Cannot get sample count at ({}, {}), film is only {}x{}", x, y, self.m_width, self.m_height);
+        }
+
+        self.m_pixels[x + y * self.m_width].sample_count
+    }
+
+    // get_radiance returns the sum of the contributions to a pixel divided by the number of emitted photons
+    pub fn get_radiance(&self, x: usize, y: usize) -> color::Color {
+        if x >= self.m_width || y >= self.m_height {
+            panic!("Cannot get radiance at ({}, {}), film is only {}x{}", x, y, self.m_width, self.m_height);
+        }
+
+        if self.m_photon_count == 0 {
+            return color::Color::default();
+        }
+
+        self.m_pixels[x + y * self.m_width].sum / self.m_photon_count as f64
+    }
+
+    // merge adds the samples and emitted photons of another film of the same size to this one
+    pub fn merge(&mut self, other: &Film) {
+        if self.m_width != other.m_width || self.m_height != other.m_height {
+            panic!("Cannot merge a {}x{} film into a {}x{} film", other.m_width, other.m_height, self.m_width, self.m_height);
+        }
+
+        for (film_pixel, other_pixel) in self.m_pixels.iter_mut().zip(&other.m_pixels) {
+            film_pixel.sum += other_pixel.sum;
+            film_pixel.sample_count += other_pixel.sample_count;
+        }
+
+        self.m_photon_count += other.m_photon_count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulate() {
+        let mut film = Film::new(2, 2);
+        film.add_photons(4);
+        film.add_sample(1, 0, color::Color::new(1.0, 2.0, 0.0));
+        film.add_sample(1, 0, color::Color::new(1.0, 0.0, 4.0));
+
+        let radiance = film.get_radiance(1, 0);
+
+        assert_eq!(radiance.red, 0.5);
+        assert_eq!(radiance.green, 0.5);
+        assert_eq!(radiance.blue, 1.0);
+        assert_eq!(film.get_sample_count(1, 0), 2);
+        assert_eq!(film.get_sample_count(0, 0), 0);
+        assert_eq!(film.get_radiance(0, 0).brightness(), 0.0);
+    }
+
+    #[test]
+    fn merge() {
+        let mut a = Film::new(2, 1);
+        a.add_photons(1);
+        a.add_sample(0, 0, color::Color::new(1.0, 1.0, 1.0));
+
+        let mut b = Film::new(2, 1);
+        b.add_photons(3);
+        b.add_sample(0, 0, color::Color::new(1.0, 1.0, 1.0));
+        b.add_sample(1, 0, color::Color::new(2.0, 2.0, 2.0));
+
+        a.merge(&b);
+
+        assert_eq!(a.get_photon_count(), 4);
+        assert_eq!(a.get_sample_count(0, 0), 2);
+        assert_eq!(a.get_radiance(0, 0).red, 0.5);
+        assert_eq!(a.get_radiance(1, 0).red, 0.5);
+    }
+
+    #[test]
+    fn converges_with_more_photons() {
+        // a pixel that receives a quarter of the photons keeps the same radiance as the budget grows
+        for photon_count in [4, 400, 40000] {
+            let mut film = Film::new(1, 1);
+            film.add_photons(photon_count);
+
+            for _ in 0..photon_count / 4 {
+                film.add_sample(0, 0, color::Color::new(2.0, 2.0, 2.0));
+            }
+
+            assert_eq!(film.get_radiance(0, 0).red, 0.5);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::color;
+
+// schlick approximates the fraction of light reflected by a surface whose reflectance at normal
+// incidence is f0, for light arriving at an angle whose cosine to the normal is cos_theta
+pub fn schlick(cos_theta: f64, f0: &color::Color) -> color::Color {
+    let weight = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+
+    color::Color::new(
+        f0.red + (1.0 - f0.red) * weight,
+        f0.green + (1.0 - f0.green) * weight,
+        f0.blue + (1.0 - f0.blue) * weight,
+    )
+}
+
+// dielectric returns the fraction of unpolarized light reflected where it passes from a medium with index
+// of refraction eta_incident into one with eta_transmitted, arriving at an angle whose cosine to the
+// normal is cos_incident. It is 1 past the critical angle, where all of the light is reflected.
+pub fn dielectric(cos_incident: f64, eta_incident: f64, eta_transmitted: f64) -> f64 {
+    let cos_incident = cos_incident.clamp(0.0, 1.0);
+    let sin_transmitted = eta_incident / eta_transmitted * (1.0 - cos_incident * cos_incident).sqrt();
+
+    if sin_transmitted >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_transmitted = (1.0 - sin_transmitted * sin_transmitted).sqrt();
+
+    let parallel = (eta_transmitted * cos_incident - eta_incident * cos_transmitted) /
+        (eta_transmitted * cos_incident + eta_incident * cos_transmitted);
+    let perpendicular = (eta_incident * cos_incident - eta_transmitted * cos_transmitted) /
+        (eta_incident * cos_incident + eta_transmitted * cos_transmitted);
+
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn schlick_limits() {
+        let f0 = color::Color::new(0.04, 0.5, 1.0);
+
+        // at normal incidence the reflectance is f0, and at grazing angles everything is reflected
+        let normal = schlick(1.0, &f0);
+        assert_approx_eq!(normal.red, 0.04, 1e-9f64);
+        assert_approx_eq!(normal.green, 0.5, 1e-9f64);
+
+        let grazing = schlick(0.0, &f0);
+        assert_approx_eq!(grazing.red, 1.0, 1e-9f64);
+        assert_approx_eq!(grazing.blue, 1.0, 1e-9f64);
+    }
+
+    #[test]
+    fn dielectric_limits() {
+        // glass reflects 4% of light at normal incidence from either side, and everything at grazing angles
+        assert_approx_eq!(dielectric(1.0, 1.0, 1.5), 0.04, 1e-9f64);
+        assert_approx_eq!(dielectric(1.0, 1.5, 1.0), 0.04, 1e-9f64);
+        assert_approx_eq!(dielectric(0.0, 1.0, 1.5), 1.0, 1e-9f64);
+
+        // inside the glass light beyond the critical angle of about 42 degrees is totally reflected
+        let critical = (1.0f64 / 1.5).asin();
+        assert!(dielectric((critical - 0.01).cos(), 1.5, 1.0) < 1.0);
+        assert_eq!(dielectric((critical + 0.01).cos(), 1.5, 1.0), 1.0);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{color, fresnel, material, photon, random_generator, ray, vector3};
+
+// GlassMaterial is a transparent dielectric, like glass or water, that either reflects or refracts every
+// photon with the probability given by the Fresnel equations. Photons that travel through it lose the
+// light it absorbs following the Beer-Lambert law.
+pub struct GlassMaterial {
+    name: String,
+    m_index_of_refraction: f64,
+    // fraction of each color lost per unit distance travelled inside the material
+    m_absorption: color::Color,
+}
+
+impl GlassMaterial {
+    pub fn new(name: &str, index_of_refraction: f64) -> GlassMaterial {
+        GlassMaterial {
+            name: name.to_string(),
+            m_index_of_refraction: index_of_refraction,
+            m_absorption: color::Color::default(),
+        }
+    }
+
+    // set_absorption tints the material so that white light has faded to color after travelling distance
+    // through it
+    pub fn set_absorption(&mut self, color: &color::Color, distance: f64) {
+        let coefficient = |component: f64| -component.max(f64::MIN_POSITIVE).ln() / distance;
+
+        self.m_absorption = color::Color::new(coefficient(color.red), coefficient(color.green), coefficient(color.blue));
+    }
+
+    // transmittance is the fraction of light left after travelling distance through the material
+    fn transmittance(&self, distance: f64) -> color::Color {
+        color::Color::new(
+            (-self.m_absorption.red * distance).exp(),
+            (-self.m_absorption.green * distance).exp(),
+            (-self.m_absorption.blue * distance).exp(),
+        )
+    }
+}
+
+impl material::Material for GlassMaterial {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    // like a mirror, glass only sends light in single directions, so it is only seen through the photons it
+    // reflects and refracts onto other surfaces
+    fn color_for_hit(&self, _pixel_direction: &vector3::Vector3, _photon_hit: &photon::PhotonHit) -> color::Color {
+        color::Color::default()
+    }
+
+    fn is_transmissive(&self) -> bool {
+        true
+    }
+
+    fn bounce(&self, photon_hit: &photon::PhotonHit, random_generator: &mut random_generator::RandomGenerator) -> photon::Photon {
+        let hit = &photon_hit.hit;
+        let incident = photon_hit.photon.ray.direction;
+
+        // photons arriving from behind the surface are leaving the material
+        let entering = hit.is_above(&-incident);
+        let (normal, eta_incident, eta_transmitted) = if entering {
+            (hit.normal, 1.0, self.m_index_of_refraction)
+        } else {
+            (-hit.normal, self.m_index_of_refraction, 1.0)
+        };
+
+        // the photon has travelled through this material since it last bounced
+        let mut color = photon_hit.photon.color;
+        if photon_hit.photon.medium == Some(hit.material_index) {
+            color = color * self.transmittance(hit.distance);
+        }
+
+        let cos_incident = vector3::Vector3::dot(&-incident, &normal).clamp(0.0, 1.0);
+        let reflectance = fresnel::dielectric(cos_incident, eta_incident, eta_transmitted);
+
+        // reflectance is 1 past the critical angle, so refraction is only ever picked when it is possible
+        let (direction, medium, transmitted) = if random_generator.value(1.0) < reflectance {
+            (vector3::Vector3::reflected(&incident, &normal), photon_hit.photon.medium, false)
+        } else {
+            let eta = eta_incident / eta_transmitted;
+            let cos_transmitted = (1.0 - eta * eta * (1.0 - cos_incident * cos_incident)).max(0.0).sqrt();
+            let refraction = incident * eta + normal * (eta * cos_incident - cos_transmitted);
+            let medium = if entering { Some(hit.material_index) } else { None };
+
+            (refraction, medium, true)
+        };
+
+        // the directions are around the shading normal, but a reflection has to stay on the side of the
+        // surface the photon came from and a refraction has to cross it
+        if hit.is_above(&direction) != (entering != transmitted) {
+            color = color::Color::default();
+        }
+
+        photon::Photon {
+            bounces: photon_hit.photon.bounces + 1,
+            color,
+            medium,
+            ray: ray::Ray {
+                origin: hit.offset_origin(&direction),
+                direction,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::{hit, mesh, mesh_volume, renderer, triangle, volume_tree};
+    use crate::material::Material;
+
+    fn photon_hit(direction: vector3::Vector3, medium: Option<usize>) -> photon::PhotonHit {
+        photon::PhotonHit {
+            hit: hit::Hit::new(vector3::Vector3::default(), vector3::UNIT_Z, 2.0, 3),
+            photon: photon::Photon {
+                ray: ray::Ray::new(-direction, direction),
+                color: color::Color::new(1.0, 1.0, 1.0),
+                bounces: 0,
+                medium,
+            },
+        }
+    }
+
+    #[test]
+    fn bounce_refracts_into_medium() {
+        let material = GlassMaterial::new("Glass", 1.5);
+        let mut rg = random_generator::RandomGenerator::from_seed(11);
+        let direction = vector3::Vector3::new(0.5, 0.0, -(0.75f64).sqrt());
+        let mut reflected = 0;
+
+        for _ in 0..1000 {
+            let photon = material.bounce(&photon_hit(direction, None), &mut rg);
+            assert_eq!(photon.color.red, 1.0);
+
+            if photon.ray.direction.get_z() > 0.0 {
+                reflected += 1;
+                assert_eq!(photon.medium, None);
+                assert_approx_eq!(photon.ray.direction.get_z(), -direction.get_z(), 1e-9f64);
+            } else {
+                // Snell's law bends the photon towards the normal as it enters the material
+                assert_eq!(photon.medium, Some(3));
+                assert_approx_eq!(photon.ray.direction.norm(), 1.0, 1e-9f64);
+                assert_approx_eq!(photon.ray.direction.get_x(), 0.5 / 1.5, 1e-9f64);
+                assert!(photon.ray.origin.get_z() < 0.0);
+            }
+        }
+
+        // about 4% of the photons are reflected at 30 degrees
+        assert!(reflected > 20 && reflected < 70, "{}", reflected);
+    }
+
+    #[test]
+    fn bounce_total_internal_reflection() {
+        let material = GlassMaterial::new("Glass", 1.5);
+        let mut rg = random_generator::RandomGenerator::from_seed(11);
+
+        // inside the material and at 60 degrees, beyond the critical angle
+        let direction = vector3::Vector3::new((0.75f64).sqrt(), 0.0, 0.5);
+
+        for _ in 0..100 {
+            let photon = material.bounce(&photon_hit(direction, Some(3)), &mut rg);
+
+            assert_eq!(photon.medium, Some(3));
+            assert_approx_eq!(photon.ray.direction.get_z(), -0.5, 1e-9f64);
+            assert!(photon.color.red > 0.0);
+        }
+    }
+
+    #[test]
+    fn bounce_absorbs_inside_medium() {
+        let mut material = GlassMaterial::new("Glass", 1.0);
+        material.set_absorption(&color::Color::new(0.5, 1.0, 0.0), 1.0);
+        let mut rg = random_generator::RandomGenerator::from_seed(11);
+
+        // with an index of refraction of 1 every photon passes straight through
+        let photon = material.bounce(&photon_hit(vector3::UNIT_Z, Some(3)), &mut rg);
+
+        assert_eq!(photon.medium, None);
+        assert_approx_eq!(photon.color.red, 0.25, 1e-9f64);
+        assert_approx_eq!(photon.color.green, 1.0, 1e-9f64);
+        assert_approx_eq!(photon.color.blue, 0.0, 1e-9f64);
+
+        // photons arriving from outside haven't travelled through the material
+        let photon = material.bounce(&photon_hit(-vector3::UNIT_Z, None), &mut rg);
+        assert_eq!(photon.color.red, 1.0);
+    }
+
+    #[test]
+    fn slab_preserves_direction() {
+        let mut material = GlassMaterial::new("Glass", 1.5);
+        material.set_absorption(&color::Color::new(0.5, 1.0, 1.0), 1.0);
+
+        // a slab between z = 1 and z = 2, with its normals facing out of it
+        let front = [vector3::Vector3::new(-10.0, -10.0, 1.0), vector3::Vector3::new(-10.0, 10.0, 1.0),
+                     vector3::Vector3::new(10.0, -10.0, 1.0), vector3::Vector3::new(10.0, 10.0, 1.0)];
+        let back = front.map(|v| v + vector3::UNIT_Z);
+        let triangles = vec![
+            triangle::Triangle::new(front[0], front[1], front[2]),
+            triangle::Triangle::new(front[3], front[2], front[1]),
+            triangle::Triangle::new(back[0], back[2], back[1]),
+            triangle::Triangle::new(back[3], back[1], back[2]),
+        ];
+        let mut slab = mesh_volume::MeshVolume::new(0, mesh::Mesh::new("slab", triangles));
+        slab.set_two_sided(true);
+        let volumes = volume_tree::VolumeTree::new(vec![Box::new(slab)]);
+
+        let renderer = renderer::Renderer::new();
+        let mut rg = random_generator::RandomGenerator::from_seed(13);
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let direction = vector3::Vector3::new(0.5, 0.0, (0.75f64).sqrt());
+        let mut transmitted = 0;
+
+        for _ in 0..1000 {
+            let mut photon = photon::Photon {
+                ray: ray::Ray::new(vector3::Vector3::default(), direction),
+                color: color::Color::new(1.0, 1.0, 1.0),
+                ..Default::default()
+            };
+
+            while let Some(photon_hit) = renderer.process_photon(&photon, &mut cast_buffer, &volumes) {
+                photon = material.bounce(&photon_hit, &mut rg);
+            }
+
+            assert_eq!(photon.medium, None);
+
+            // photons that pass straight through leave parallel to how they arrived, having travelled
+            // 1 / cos(asin(1 / 3)) through the slab
+            if photon.ray.direction.get_z() > 0.0 && photon.bounces == 2 {
+                transmitted += 1;
+                assert_approx_eq!(photon.ray.direction.get_x(), direction.get_x(), 1e-9f64);
+                assert_approx_eq!(photon.ray.direction.get_z(), direction.get_z(), 1e-9f64);
+                assert_approx_eq!(photon.color.red, 0.5f64.powf(1.0 / (8.0f64 / 9.0).sqrt()), 1e-6f64);
+            }
+        }
+
+        assert!(transmitted > 880 && transmitted < 960, "{}", transmitted);
+    }
+}
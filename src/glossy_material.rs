@@ -0,0 +1,266 @@
+use std::f64::consts;
+
+use crate::{color, fresnel, material, photon, random_generator, ray, sampling, vector3};
+
+// roughness below which the microfacet distribution is too narrow to evaluate reliably
+const MIN_ROUGHNESS: f64 = 1e-3;
+
+// GlossyMaterial is a rough specular surface made of tiny mirrors whose orientations follow the GGX
+// distribution, so reflections blur as the roughness goes from 0, a mirror, to 1. The color is the
+// reflectance at normal incidence.
+pub struct GlossyMaterial {
+    name: String,
+    m_color: color::Color,
+    m_roughness: f64,
+}
+
+impl GlossyMaterial {
+    pub fn new(name: &str, roughness: f64) -> GlossyMaterial {
+        GlossyMaterial::from_color(name, &color::Color::new(1.0, 1.0, 1.0), roughness)
+    }
+
+    pub fn from_color(name: &str, color: &color::Color, roughness: f64) -> GlossyMaterial {
+        let mut material = GlossyMaterial {
+            name: name.to_string(),
+            m_color: *color,
+            m_roughness: 0.0,
+        };
+        material.set_roughness(roughness);
+
+        material
+    }
+
+    pub fn set_roughness(&mut self, roughness: f64) {
+        self.m_roughness = roughness.clamp(MIN_ROUGHNESS, 1.0);
+    }
+
+    // the roughness is squared so that it looks perceptually linear
+    fn alpha(&self) -> f64 {
+        self.m_roughness * self.m_roughness
+    }
+
+    // distribution is the density of microfacets whose normal is at an angle to the surface normal with
+    // cosine n_dot_h
+    fn distribution(&self, n_dot_h: f64) -> f64 {
+        let alpha2 = self.alpha() * self.alpha();
+        let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+
+        alpha2 / (consts::PI * denominator * denominator)
+    }
+
+    // masking is the fraction of microfacets seen from a direction with cosine n_dot_x to the surface
+    // normal that aren't hidden behind other microfacets, using the Smith approximation
+    fn masking(&self, n_dot_x: f64) -> f64 {
+        let alpha2 = self.alpha() * self.alpha();
+
+        2.0 * n_dot_x / (n_dot_x + (alpha2 + (1.0 - alpha2) * n_dot_x * n_dot_x).sqrt())
+    }
+
+    // brdf returns the fraction of light arriving from the direction incoming that is reflected towards
+    // outgoing, both pointing away from the surface, per unit solid angle and projected area
+    pub fn brdf(&self, normal: &vector3::Vector3, incoming: &vector3::Vector3, outgoing: &vector3::Vector3) -> color::Color {
+        let n_dot_l = vector3::Vector3::dot(normal, incoming);
+        let n_dot_v = vector3::Vector3::dot(normal, outgoing);
+
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return color::Color::default();
+        }
+
+        let half = vector3::Vector3::normalized(&(*incoming + *outgoing));
+        let n_dot_h = vector3::Vector3::dot(normal, &half);
+        let v_dot_h = vector3::Vector3::dot(outgoing, &half);
+
+        let specular = self.distribution(n_dot_h) * self.masking(n_dot_l) * self.masking(n_dot_v) / (4.0 * n_dot_l * n_dot_v);
+
+        fresnel::schlick(v_dot_h, &self.m_color) * specular
+    }
+}
+
+impl material::Material for GlossyMaterial {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn color_for_hit(&self, pixel_direction: &vector3::Vector3, photon_hit: &photon::PhotonHit) -> color::Color {
+        let hit = &photon_hit.hit;
+        let incoming = -photon_hit.photon.ray.direction;
+        let outgoing = -pixel_direction;
+
+        if !hit.is_above(&incoming) {
+            return color::Color::default();
+        }
+
+        self.brdf(&hit.normal, &incoming, &outgoing) * photon_hit.photon.color
+    }
+
+    // bounce picks a microfacet normal in proportion to how much of the surface facing that way is visible
+    // from the photon, and mirrors the photon about it (Heitz, "Sampling the GGX Distribution of Visible
+    // Normals"). The masking of the reflection is the only term of the brdf left over to scale the
+    // photon by, so the color of a photon never grows.
+    fn bounce(&self, photon_hit: &photon::PhotonHit, random_generator: &mut random_generator::RandomGenerator) -> photon::Photon {
+        let hit = &photon_hit.hit;
+        let incident = photon_hit.photon.ray.direction;
+        let incoming = -incident;
+        let alpha = self.alpha();
+
+        // stretch the incoming direction so that the microfacets become a hemisphere
+        let local = sampling::to_local(&incoming, &hit.normal);
+        let stretched = vector3::Vector3::normalized(&vector3::Vector3::new(alpha * local.get_x(), alpha * local.get_y(), local.get_z()));
+
+        let length2 = stretched.get_x() * stretched.get_x() + stretched.get_y() * stretched.get_y();
+        let t1_axis = if length2 > 0.0 {
+            vector3::Vector3::new(-stretched.get_y(), stretched.get_x(), 0.0) / length2.sqrt()
+        } else {
+            vector3::UNIT_X
+        };
+        let t2_axis = vector3::Vector3::cross(&stretched, &t1_axis);
+
+        // pick a point on the disk that the hemisphere projects to, squashed onto the visible half of it
+        let r = random_generator.value(1.0).sqrt();
+        let phi = random_generator.value(2.0 * consts::PI);
+        let t1 = r * phi.cos();
+        let s = 0.5 * (1.0 + stretched.get_z());
+        let t2 = (1.0 - s) * (1.0 - t1 * t1).sqrt() + s * r * phi.sin();
+        let t3 = (1.0 - t1 * t1 - t2 * t2).max(0.0).sqrt();
+        let stretched_half = t1_axis * t1 + t2_axis * t2 + stretched * t3;
+
+        let local_half = vector3::Vector3::normalized(&vector3::Vector3::new(
+            alpha * stretched_half.get_x(),
+            alpha * stretched_half.get_y(),
+            stretched_half.get_z().max(0.0),
+        ));
+        let half = sampling::to_world(&local_half, &hit.normal);
+
+        let reflection = vector3::Vector3::reflected(&incident, &half);
+
+        let n_dot_l = vector3::Vector3::dot(&hit.normal, &incoming);
+        let n_dot_v = vector3::Vector3::dot(&hit.normal, &reflection);
+        let v_dot_h = vector3::Vector3::dot(&reflection, &half);
+
+        let color = if n_dot_l > 0.0 && n_dot_v > 0.0 && v_dot_h > 0.0 && hit.reflects_validly(&incoming, &reflection) {
+            fresnel::schlick(v_dot_h, &self.m_color) * photon_hit.photon.color * self.masking(n_dot_v)
+        } else {
+            color::Color::default()
+        };
+
+        photon::Photon {
+            bounces: photon_hit.photon.bounces + 1,
+            medium: photon_hit.photon.medium,
+            color,
+            ray: ray::Ray {
+                origin: hit.offset_origin(&reflection),
+                direction: reflection,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::hit;
+    use crate::material::Material;
+
+    fn photon_hit(direction: vector3::Vector3) -> photon::PhotonHit {
+        photon::PhotonHit {
+            hit: hit::Hit::new(vector3::Vector3::default(), vector3::UNIT_Z, 1.0, 0),
+            photon: photon::Photon {
+                ray: ray::Ray::new(-direction, direction),
+                color: color::Color::new(1.0, 1.0, 1.0),
+                bounces: 0,
+                medium: None,
+            },
+        }
+    }
+
+    #[test]
+    fn roughness_clamped() {
+        assert_eq!(GlossyMaterial::new("Glossy", -1.0).m_roughness, MIN_ROUGHNESS);
+        assert_eq!(GlossyMaterial::new("Glossy", 2.0).m_roughness, 1.0);
+    }
+
+    #[test]
+    fn brdf_reciprocal_and_peaks_at_mirror_direction() {
+        let material = GlossyMaterial::from_color("Glossy", &color::Color::new(0.9, 0.6, 0.3), 0.3);
+        let incoming = vector3::Vector3::normalized(&vector3::Vector3::new(1.0, 0.0, 1.0));
+        let mirror = vector3::Vector3::new(-incoming.get_x(), 0.0, incoming.get_z());
+        let other = vector3::Vector3::normalized(&vector3::Vector3::new(-0.2, 0.5, 1.0));
+
+        let forward = material.brdf(&vector3::UNIT_Z, &incoming, &other);
+        let backward = material.brdf(&vector3::UNIT_Z, &other, &incoming);
+        assert_approx_eq!(forward.red, backward.red, 1e-9f64);
+
+        assert!(material.brdf(&vector3::UNIT_Z, &incoming, &mirror).red > forward.red);
+
+        // no light is reflected to or from below the surface
+        assert_eq!(material.brdf(&vector3::UNIT_Z, &incoming, &-other).brightness(), 0.0);
+    }
+
+    #[test]
+    fn color_for_hit_towards_camera() {
+        let material = GlossyMaterial::new("Glossy", 0.2);
+        let direction = vector3::Vector3::normalized(&vector3::Vector3::new(1.0, 0.0, -1.0));
+        let photon_hit = photon_hit(direction);
+
+        // the camera looks along pixel_direction, so it sees the reflection when looking back against it
+        let in_reflection = material.color_for_hit(&vector3::Vector3::new(-direction.get_x(), 0.0, direction.get_z()), &photon_hit);
+        let off_reflection = material.color_for_hit(&vector3::Vector3::normalized(&vector3::Vector3::new(1.0, 0.0, -1.0)), &photon_hit);
+
+        assert!(in_reflection.red > 10.0 * off_reflection.red);
+    }
+
+    #[test]
+    fn bounce_leaves_surface_near_mirror_direction() {
+        let material = GlossyMaterial::new("Glossy", 0.1);
+        let mut rg = random_generator::RandomGenerator::from_seed(5);
+        let direction = vector3::Vector3::normalized(&vector3::Vector3::new(1.0, 0.0, -2.0));
+        let mirror = vector3::Vector3::new(direction.get_x(), 0.0, -direction.get_z());
+        let mut near_mirror = 0;
+
+        for _ in 0..1000 {
+            let photon = material.bounce(&photon_hit(direction), &mut rg);
+
+            if photon.color.brightness() > 0.0 {
+                assert!(photon.ray.direction.get_z() > 0.0);
+                assert!(photon.color.red <= 1.0);
+            }
+
+            if vector3::Vector3::dot(&photon.ray.direction, &mirror) > 0.99 {
+                near_mirror += 1;
+            }
+        }
+
+        // GGX has a long tail, but a smooth surface reflects most photons close to the mirror direction
+        assert!(near_mirror > 950, "{}", near_mirror);
+    }
+
+    #[test]
+    fn bounce_matches_brdf() {
+        // the average color of bounced photons is the integral of the brdf times the cosine of the outgoing
+        // direction over the hemisphere, which is estimated independently with uniformly spread directions
+        let material = GlossyMaterial::new("Glossy", 0.5);
+        let mut rg = random_generator::RandomGenerator::from_seed(7);
+        let incoming = vector3::Vector3::normalized(&vector3::Vector3::new(1.0, 0.0, 2.0));
+        let samples = 200_000;
+
+        let bounced: f64 = (0..samples)
+            .map(|_| material.bounce(&photon_hit(-incoming), &mut rg).color.red)
+            .sum::<f64>() / samples as f64;
+
+        let integrated: f64 = (0..samples)
+            .map(|_| {
+                let cos_theta = rg.value(1.0);
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let phi = rg.value(2.0 * consts::PI);
+                let outgoing = vector3::Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+                material.brdf(&vector3::UNIT_Z, &incoming, &outgoing).red * outgoing.get_z() * 2.0 * consts::PI
+            })
+            .sum::<f64>() / samples as f64;
+
+        assert!(bounced <= 1.0);
+        assert_approx_eq!(bounced, integrated, 0.02f64);
+    }
+}
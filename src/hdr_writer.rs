@@ -0,0 +1,131 @@
+use std::fs;
+use std::io;
+use std::path;
+
+use crate::{color, film, image_writer};
+
+// HdrWriter writes the radiance of a film to a Radiance RGBE (.hdr) file, with uncompressed scanlines
+pub struct HdrWriter {
+    m_path: path::PathBuf,
+}
+
+impl HdrWriter {
+    pub fn new(file: &path::Path) -> Self {
+        HdrWriter {
+            m_path: file.to_path_buf(),
+        }
+    }
+
+    fn write_to(&self, writer: &mut impl io::Write, film: &film::Film) -> io::Result<()> {
+        write!(writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", film.get_height(), film.get_width())?;
+
+        for y in 0..film.get_height() {
+            for x in 0..film.get_width() {
+                writer.write_all(&to_rgbe(&film.get_radiance(x, y)))?;
+            }
+        }
+
+        writer.flush()
+    }
+}
+
+impl image_writer::ImageWriter for HdrWriter {
+    fn write(&self, film: &film::Film) -> Result<(), image_writer::ImageWriterError> {
+        let file = fs::File::create(&self.m_path)
+            .map_err(|e| image_writer::ImageWriterError::Io(self.m_path.clone(), e))?;
+
+        self.write_to(&mut io::BufWriter::new(file), film)
+            .map_err(|e| image_writer::ImageWriterError::Io(self.m_path.clone(), e))
+    }
+}
+
+// to_rgbe stores the channels as 8-bit mantissas sharing the exponent of the largest channel
+fn to_rgbe(color: &color::Color) -> [u8; 4] {
+    let red = color.red.max(0.0);
+    let green = color.green.max(0.0);
+    let blue = color.blue.max(0.0);
+
+    let largest = red.max(green).max(blue);
+
+    if largest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // largest = mantissa * 2^exponent with mantissa in [0.5, 1)
+    let mut exponent = largest.log2().floor() as i32 + 1;
+    if largest / f64::powi(2.0, exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let exponent = exponent.clamp(-128, 127);
+
+    let scale = 256.0 / f64::powi(2.0, exponent);
+
+    [
+        (red * scale).min(255.0) as u8,
+        (green * scale).min(255.0) as u8,
+        (blue * scale).min(255.0) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    fn from_rgbe(rgbe: &[u8]) -> color::Color {
+        if rgbe[3] == 0 {
+            return color::Color::default();
+        }
+
+        let scale = f64::powi(2.0, rgbe[3] as i32 - 128 - 8);
+
+        color::Color::new(
+            (rgbe[0] as f64 + 0.5) * scale,
+            (rgbe[1] as f64 + 0.5) * scale,
+            (rgbe[2] as f64 + 0.5) * scale,
+        )
+    }
+
+    #[test]
+    fn rgbe_round_trip() {
+        for c in [
+            color::Color::new(1.0, 0.5, 0.25),
+            color::Color::new(1000.0, 3.0, 0.0),
+            color::Color::new(0.001, 0.002, 0.003),
+        ] {
+            let rgbe = to_rgbe(&c);
+            let decoded = from_rgbe(&rgbe);
+            let largest = c.red.max(c.green).max(c.blue);
+
+            assert_approx_eq!(decoded.red, c.red, largest / 128.0);
+            assert_approx_eq!(decoded.green, c.green, largest / 128.0);
+            assert_approx_eq!(decoded.blue, c.blue, largest / 128.0);
+        }
+
+        assert_eq!(to_rgbe(&color::Color::default()), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn write_film() {
+        let mut film = film::Film::new(2, 1);
+        film.add_photons(2);
+        film.add_sample(1, 0, color::Color::new(8.0, 4.0, 2.0));
+
+        let mut buffer = Vec::<u8>::new();
+        HdrWriter::new(path::Path::new("unused.hdr")).write_to(&mut buffer, &film).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n";
+        assert_eq!(&buffer[..header.len()], header);
+        assert_eq!(buffer.len(), header.len() + 8);
+
+        let pixels = &buffer[header.len()..];
+        assert_eq!(&pixels[0..4], &[0, 0, 0, 0]);
+
+        let lit = from_rgbe(&pixels[4..8]);
+        assert_approx_eq!(lit.red, 4.0, 0.05f64);
+        assert_approx_eq!(lit.green, 2.0, 0.05f64);
+        assert_approx_eq!(lit.blue, 1.0, 0.05f64);
+    }
+}
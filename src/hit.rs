@@ -1,22 +1,85 @@
+use crate::uv;
 use crate::vector3;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Hit {
     pub position: vector3::Vector3,
+    // shading normal, which is interpolated from the vertex normals of triangles
     pub normal: vector3::Vector3,
+    // normal of the surface itself, which decides which side of the surface a direction is on
+    pub geometric_normal: vector3::Vector3,
+    // weights of the vertices a, b and c of a triangle at the hit, zero for other surfaces
+    pub barycentrics: vector3::Vector3,
+    // texture coordinates at the hit
+    pub uv: uv::Uv,
     pub distance: f64,
     pub material_index: usize,
 }
 
+// distance that ray origins are pushed off a surface, relative to the magnitude of the hit position
+const SURFACE_OFFSET: f64 = 1e-9;
+
 impl Hit {
+    // new returns a hit on a flat surface, whose shading normal is its geometric normal
     pub fn new(position: vector3::Vector3, normal: vector3::Vector3, distance: f64, material_index: usize) -> Self {
         Hit {
             position,
             normal,
+            geometric_normal: normal,
+            barycentrics: vector3::Vector3::default(),
+            uv: uv::Uv::default(),
+            distance,
+            material_index,
+        }
+    }
+
+    pub fn with_shading(
+        position: vector3::Vector3,
+        normal: vector3::Vector3,
+        geometric_normal: vector3::Vector3,
+        barycentrics: vector3::Vector3,
+        uv: uv::Uv,
+        distance: f64,
+        material_index: usize,
+    ) -> Self {
+        Hit {
+            position,
+            normal,
+            geometric_normal,
+            barycentrics,
+            uv,
             distance,
             material_index,
         }
     }
+
+    // is_above returns whether a direction leaves the surface on the side the geometric normal faces
+    pub fn is_above(&self, direction: &vector3::Vector3) -> bool {
+        vector3::Vector3::dot(direction, &self.geometric_normal) > 0.0
+    }
+
+    // reflects_validly returns whether light arriving from incoming and reflected towards outgoing stays on the
+    // side of the surface it arrived at. Reflections are around the shading normal, which can send them through
+    // the geometric surface.
+    pub fn reflects_validly(&self, incoming: &vector3::Vector3, outgoing: &vector3::Vector3) -> bool {
+        self.is_above(incoming) && self.is_above(outgoing)
+    }
+
+    // offset_origin returns the origin of a ray that leaves the hit in direction, pushed off the surface
+    // along the geometric normal to the side the direction is on so that the ray doesn't hit the surface again
+    pub fn offset_origin(&self, direction: &vector3::Vector3) -> vector3::Vector3 {
+        let scale = self.position.get_x().abs()
+            .max(self.position.get_y().abs())
+            .max(self.position.get_z().abs())
+            .max(1.0);
+        let offset = self.geometric_normal * (SURFACE_OFFSET * scale);
+
+        if self.is_above(direction) {
+            self.position + offset
+        } else {
+            self.position - offset
+        }
+    }
 }
 
 impl Default for Hit {
@@ -24,6 +87,9 @@ impl Default for Hit {
         Hit {
             position: vector3::Vector3::default(),
             normal: vector3::Vector3::default(),
+            geometric_normal: vector3::Vector3::default(),
+            barycentrics: vector3::Vector3::default(),
+            uv: uv::Uv::default(),
             distance: 0.0,
             material_index: 0,
         }
@@ -45,7 +111,39 @@ mod tests {
 
         assert_eq!(hit.position, position);
         assert_eq!(hit.normal, normal);
+        assert_eq!(hit.geometric_normal, normal);
         assert_eq!(hit.distance, distance);
         assert_eq!(hit.material_index, material_index);
     }
+
+    #[test]
+    fn offset_origin() {
+        let hit = Hit::with_shading(
+            vector3::Vector3::new(0.0, 0.0, 1000.0),
+            vector3::Vector3::new(0.6, 0.0, -0.8),
+            vector3::Vector3::new(0.0, 0.0, -1.0),
+            vector3::Vector3::new(0.5, 0.25, 0.25),
+            uv::Uv::default(),
+            1.0,
+            0,
+        );
+
+        assert!(hit.is_above(&vector3::Vector3::new(0.0, 1.0, -0.1)));
+        assert!(!hit.is_above(&vector3::Vector3::new(0.0, 1.0, 0.1)));
+
+        // reflected around the shading normal, light arriving at a grazing angle leaves below the geometric surface
+        let incoming = vector3::Vector3::new(-1.0, 0.0, -0.1);
+        assert!(!hit.reflects_validly(&incoming, &vector3::Vector3::reflected(&-incoming, &hit.normal)));
+        assert!(hit.reflects_validly(&incoming, &vector3::Vector3::new(0.0, 1.0, -0.1)));
+        assert!(!hit.reflects_validly(&vector3::Vector3::new(0.0, 1.0, 0.1), &vector3::Vector3::new(0.0, 1.0, -0.1)));
+
+        // the origin moves along the geometric normal, not the shading normal, by an offset that grows with
+        // the distance from the origin
+        let above = hit.offset_origin(&vector3::Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(above.get_x(), 0.0);
+        assert!(above.get_z() < 1000.0 && above.get_z() > 1000.0 - 1e-5);
+
+        let below = hit.offset_origin(&vector3::Vector3::new(0.0, 0.0, 1.0));
+        assert!(below.get_z() > 1000.0 && below.get_z() < 1000.0 + 1e-5);
+    }
 }
@@ -1,5 +1,11 @@
 use crate::pixel;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
 pub struct Image {
     m_width: usize,
     m_height: usize,
@@ -44,13 +50,24 @@ impl Image {
         self.m_pixels[x + y * self.m_width] = pixel;
     }
 
+    // unpack returns the pixels as 8-bit RGBA
     pub fn unpack(&self) -> Vec<u8> {
+        self.unpack_with(BitDepth::Eight, true)
+    }
+
+    // unpack_with returns the pixels as RGB or RGBA, with 16-bit channels stored big-endian
+    pub fn unpack_with(&self, bit_depth: BitDepth, alpha: bool) -> Vec<u8> {
         let mut buffer = Vec::new();
         for pixel in &self.m_pixels {
-            buffer.push(pixel.red as u8);
-            buffer.push(pixel.green as u8);
-            buffer.push(pixel.blue as u8);
-            buffer.push(255);
+            let channels = [pixel.red, pixel.green, pixel.blue, pixel.alpha];
+            let channels = if alpha { &channels[..] } else { &channels[..3] };
+
+            for channel in channels {
+                match bit_depth {
+                    BitDepth::Eight => buffer.push(((*channel as u32 * 255 + 32767) / 65535) as u8),
+                    BitDepth::Sixteen => buffer.extend_from_slice(&channel.to_be_bytes()),
+                }
+            }
         }
 
         buffer
@@ -64,3 +81,31 @@ impl Image {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack() {
+        let mut image = Image::new(2, 1);
+        let mut pixel = pixel::Pixel::new(0x1234, 0xff00, 0x00ff);
+        pixel.alpha = 0;
+        image.set_pixel(1, 0, pixel);
+
+        assert_eq!(image.unpack(), vec![0, 0, 0, 255, 0x12, 0xfe, 0x01, 0]);
+        assert_eq!(image.unpack_with(BitDepth::Eight, false), vec![0, 0, 0, 0x12, 0xfe, 0x01]);
+        assert_eq!(
+            image.unpack_with(BitDepth::Sixteen, true),
+            vec![0, 0, 0, 0, 0, 0, 0xff, 0xff, 0x12, 0x34, 0xff, 0x00, 0x00, 0xff, 0, 0],
+        );
+    }
+
+    #[test]
+    fn unpack_rounds_to_eight_bits() {
+        let mut image = Image::new(1, 1);
+        image.set_pixel(0, 0, pixel::Pixel::new(0x7fff, 0x8000, 0x00ff));
+
+        assert_eq!(image.unpack(), vec![127, 128, 1, 255]);
+    }
+}
@@ -0,0 +1,128 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path;
+
+use crate::{exr_writer, film, hdr_writer, image, png_writer, tone_mapping};
+
+#[derive(Debug)]
+pub enum ImageWriterError {
+    Io(path::PathBuf, io::Error),
+    Png(path::PathBuf, png::EncodingError),
+    UnsupportedFormat(path::PathBuf),
+}
+
+impl fmt::Display for ImageWriterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageWriterError::Io(path, error) => {
+                write!(f, "Failed to write image {}: {}", path.display(), error)
+            },
+            ImageWriterError::Png(path, error) => {
+                write!(f, "Failed to encode PNG image {}: {}", path.display(), error)
+            },
+            ImageWriterError::UnsupportedFormat(path) => {
+                write!(f, "Cannot write image {}, the extension must be one of png, hdr or exr", path.display())
+            },
+        }
+    }
+}
+
+impl error::Error for ImageWriterError {}
+
+// ImageWriter encodes the radiance accumulated on a film to a file. Formats that store floating point
+// values write the radiance as is, display formats tone map it first.
+pub trait ImageWriter {
+    fn write(&self, film: &film::Film) -> Result<(), ImageWriterError>;
+
+    // add_metadata records a keyword and text in the file, formats without text metadata ignore it
+    fn add_metadata(&mut self, _keyword: &str, _text: &str) {}
+}
+
+// WriterSettings configures the display formats, the floating point formats ignore them
+#[derive(Clone, Copy, Debug)]
+pub struct WriterSettings {
+    pub tone_mapper: tone_mapping::ToneMapper,
+    pub bit_depth: image::BitDepth,
+    // write an alpha channel that is transparent where no photon reached the film
+    pub alpha: bool,
+}
+
+impl Default for WriterSettings {
+    fn default() -> Self {
+        WriterSettings {
+            tone_mapper: tone_mapping::ToneMapper::default(),
+            bit_depth: image::BitDepth::Eight,
+            alpha: false,
+        }
+    }
+}
+
+// for_path picks the writer for the extension of path, which is compared case-insensitively
+pub fn for_path(path: &path::Path, settings: &WriterSettings) -> Result<Box<dyn ImageWriter>, ImageWriterError> {
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("png") => {
+            let mut writer = png_writer::PngWriter::new(path, settings.tone_mapper);
+            writer.set_bit_depth(settings.bit_depth);
+            writer.set_alpha(settings.alpha);
+
+            Ok(Box::new(writer))
+        },
+        Some("hdr") => Ok(Box::new(hdr_writer::HdrWriter::new(path))),
+        Some("exr") => Ok(Box::new(exr_writer::ExrWriter::new(path))),
+        _ => Err(ImageWriterError::UnsupportedFormat(path.to_path_buf())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+
+    use crate::color;
+
+    #[test]
+    fn writer_for_extension() {
+        let directory = env::temp_dir().join("tdi_ray_tracer_image_writer");
+        fs::create_dir_all(&directory).unwrap();
+
+        let mut film = film::Film::new(3, 2);
+        film.add_photons(1);
+        film.add_sample(1, 1, color::Color::new(4.0, 2.0, 1.0));
+
+        for name in ["image.png", "image.HDR", "image.exr"] {
+            let path = directory.join(name);
+            let _ = fs::remove_file(&path);
+
+            for_path(&path, &WriterSettings::default()).unwrap().write(&film).unwrap();
+
+            assert!(fs::metadata(&path).unwrap().len() > 0);
+        }
+
+        assert!(matches!(
+            for_path(path::Path::new("image.jpg"), &WriterSettings::default()),
+            Err(ImageWriterError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            for_path(path::Path::new("image"), &WriterSettings::default()),
+            Err(ImageWriterError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn write_to_missing_directory() {
+        let path = env::temp_dir().join("tdi_ray_tracer_missing_directory").join("image.hdr");
+
+        let film = film::Film::new(1, 1);
+
+        let result = for_path(&path, &WriterSettings::default()).unwrap().write(&film);
+
+        assert!(matches!(result, Err(ImageWriterError::Io(_, _))));
+    }
+}
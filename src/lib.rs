@@ -3,12 +3,23 @@
 
 pub mod angle;
 pub mod angle_generator;
+pub mod area_light;
+mod binary;
 mod bounds;
 pub mod camera;
-mod color;
+pub mod color;
 mod diffuse_material;
+mod emissive_material;
+mod emitter;
+pub mod exr_writer;
+pub mod film;
+mod fresnel;
+mod glass_material;
+mod glossy_material;
+pub mod hdr_writer;
 pub mod hit;
 pub mod image;
+pub mod image_writer;
 pub mod library;
 mod light;
 mod light_queue;
@@ -18,8 +29,9 @@ mod material_library;
 mod math;
 pub mod mesh;
 pub mod mesh_volume;
+mod mirror_material;
 pub mod quaternion;
-mod obj_reader;
+pub mod obj_reader;
 mod object;
 pub mod parallel_light;
 pub mod photon;
@@ -27,15 +39,25 @@ pub mod pipeline;
 pub mod pixel;
 mod pixel_coords;
 mod plane;
-mod plane_volume;
+pub mod plane_volume;
 pub mod png_writer;
+pub mod point_light;
 mod pyramid;
 pub mod random_generator;
-mod ray;
+pub mod ray;
+pub mod render_settings;
 pub mod renderer;
+mod sampling;
 pub mod scene;
+pub mod scene_reader;
+pub mod spot_light;
+pub mod texture;
+pub mod texture_node;
+pub mod tone_mapping;
 mod transform;
-mod tree;
+pub mod tree;
 pub mod triangle;
+pub mod uv;
 pub mod vector3;
 pub mod volume;
+pub mod volume_tree;
@@ -30,6 +30,10 @@ impl<T> Library<T> {
         *self.m_index_map.get(name).unwrap()
     }
 
+    pub fn find_index_for_name(&self, name: &str) -> Option<usize> {
+        self.m_index_map.get(name).copied()
+    }
+
     fn fetch(&self, name: &str) -> &T {
         &self.m_contents[self.index_for_name(name)]
     }
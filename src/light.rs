@@ -3,6 +3,15 @@ use crate::{color, object, photon, quaternion, random_generator, vector3};
 pub trait LightStrategy {
     // fn update_parameters(&mut self); // TODO this one doesn't need to be overridden? just access, so it's in the LightProtectedInterface but not here?
     fn emit(&self, base: &Light<Self>, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator);
+    // lumens returns the total power emitted by a light of the given brightness and area, which by default
+    // is the brightness per unit area, or the brightness itself for lights without an area
+    fn lumens(&self, brightness: f64, area: f64) -> f64 {
+        if area > 0.0 {
+            brightness * area
+        } else {
+            brightness
+        }
+    }
 }
 
 pub trait LightPublicInterface {
@@ -80,11 +89,7 @@ impl<T: LightStrategy> LightPublicInterface for Light<T> {
 
 impl<T: LightStrategy> LightProtectedInterface for Light<T> {
     fn update_parameters(&mut self) {
-        if self.m_area > 0.0 {
-            self.m_lumens = self.m_brightness * self.m_area;
-        } else {
-            self.m_lumens = self.m_brightness;
-        }
+        self.m_lumens = self.specialization.lumens(self.m_brightness, self.m_area);
     }
 
     fn object(&self) -> &object::Object {
@@ -1,6 +1,6 @@
 use std::ops;
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct Limits {
     pub min: f64,
     pub max: f64,
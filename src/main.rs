@@ -1,46 +1,141 @@
-use tdi_ray_tracer::{angle, camera, hit, image, library, mesh, mesh_volume, parallel_light,
-                     parallel_light::LightPublicInterface, photon, pipeline, pixel, png_writer,
-                     quaternion, random_generator, renderer, scene, triangle, vector3};
-
-fn main() {
-    let material_library = library::Library::build_material_library();
-
-    let image_width = 100;
-    let image_height = 100;
-    let camera = camera::Camera::new(
-        image_width,
-        image_height,
-        &angle::Angle::from_degrees(90.0),
-    );
+use std::num;
+use std::path;
+use std::process;
+use std::thread;
+use std::time;
+
+use clap::Parser;
+use clap::builder::TypedValueParser;
+
+use tdi_ray_tracer::{image, image_writer, pipeline, render_settings, renderer, scene_reader, tone_mapping};
+
+/// Renders a scene description file by tracing photons from its lights
+#[derive(Parser)]
+#[command(version)]
+struct Arguments {
+    /// Scene description file
+    scene: path::PathBuf,
+
+    /// Path of the rendered image, the extension selects PNG, Radiance HDR (.hdr) or OpenEXR (.exr)
+    #[arg(short, long, default_value = "render.png")]
+    output: path::PathBuf,
+
+    /// Overrides the camera width from the scene, in pixels
+    #[arg(long)]
+    width: Option<num::NonZeroUsize>,
+
+    /// Overrides the camera height from the scene, in pixels
+    #[arg(long)]
+    height: Option<num::NonZeroUsize>,
+
+    /// Number of photons emitted from the lights
+    #[arg(short, long, default_value_t = 10000)]
+    photons: usize,
+
+    /// Number of times a photon may bounce before it is discarded
+    #[arg(short, long, default_value_t = 1)]
+    bounces: u32,
+
+    /// Number of worker threads, defaults to the available parallelism
+    #[arg(short, long)]
+    threads: Option<num::NonZeroUsize>,
+
+    /// Seed of the random photon paths, renders with the same seed and thread count are identical
+    #[arg(short, long)]
+    seed: Option<u64>,
+
+    /// Tone mapping operator applied to the radiance of PNG output: clamp, reinhard or aces
+    #[arg(long, default_value = "clamp")]
+    tone_map: tone_mapping::ToneMapOperator,
+
+    /// Exposure compensation in stops
+    #[arg(short, long, default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: f64,
+
+    /// Bits per channel of PNG output
+    #[arg(long, default_value_t = 8, value_parser = clap::builder::PossibleValuesParser::new(["8", "16"]).map(|b| b.parse::<u8>().unwrap()))]
+    bit_depth: u8,
+
+    /// Adds an alpha channel to PNG output that is transparent where no photon reached the camera
+    #[arg(long)]
+    alpha: bool,
+}
 
-    let triangles = vec![
-        triangle::Triangle::new(
-            vector3::Vector3::new(-1.0, -1.0, 3.0),
-            vector3::Vector3::new(-1.0, 1.0, 3.0),
-            vector3::Vector3::new(1.0, 0.0, 3.0),
-        ),
-    ];
-
-    let mesh_volume = Box::new(mesh_volume::MeshVolume::new(material_library.index_for_name("Cyan"), mesh::Mesh::new("test_mesh", triangles)));
-
-    let mut light = parallel_light::ParallelLight::new();
-    light.set_radius(1.0);
-    light.set_brightness(1000.0);
-    light.set_position(vector3::Vector3::new(0.25, 0.25, 1.5));
-    light.set_rotation(quaternion::Quaternion::from_roll_pitch_yaw(0.0, 0.0, 0.0));
-
-    let s = scene::Scene {
-        camera,
-        volumes: vec![mesh_volume],
-        lights: vec![Box::new(light)],
-        material_library,
+fn main() -> process::ExitCode {
+    let arguments = Arguments::parse();
+
+    println!("Loading scene {}", arguments.scene.display());
+
+    let writer_settings = image_writer::WriterSettings {
+        tone_mapper: tone_mapping::ToneMapper::new(arguments.tone_map, arguments.exposure),
+        bit_depth: if arguments.bit_depth == 16 { image::BitDepth::Sixteen } else { image::BitDepth::Eight },
+        alpha: arguments.alpha,
+    };
+
+    let mut writer = match image_writer::for_path(&arguments.output, &writer_settings) {
+        Ok(writer) => writer,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return process::ExitCode::FAILURE;
+        },
+    };
+
+    let mut scene = match scene_reader::load_scene(&arguments.scene) {
+        Ok(scene) => scene,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return process::ExitCode::FAILURE;
+        },
+    };
+
+    if arguments.width.is_some() || arguments.height.is_some() {
+        let width = arguments.width.map_or(scene.camera.width(), |w| w.get());
+        let height = arguments.height.map_or(scene.camera.height(), |h| h.get());
+        scene.camera.set_from_render_configuration(width, height);
+    }
+
+    let thread_count = arguments.threads
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, |t| t.get());
+
+    let seed = arguments.seed.unwrap_or_else(rand::random);
+
+    let settings = render_settings::RenderSettings {
+        photon_count: arguments.photons,
+        bounce_threshold: arguments.bounces,
+        thread_count,
+        seed: Some(seed),
+        report_progress: true,
     };
 
-    let renderer = renderer::Renderer::new();
+    let p = pipeline::Pipeline::new(renderer::Renderer::new(), settings);
 
-    let p = pipeline::Pipeline::new(
-        renderer,
+    println!(
+        "Rendering {}x{} with {} photons, {} bounces, {} threads and seed {}",
+        scene.camera.width(),
+        scene.camera.height(),
+        arguments.photons,
+        arguments.bounces,
+        thread_count,
+        seed,
     );
 
-    p.render_scene(&s);
+    let start = time::Instant::now();
+
+    let film = p.render_scene(&scene);
+
+    writer.add_metadata("Software", concat!("tdi_ray_tracer ", env!("CARGO_PKG_VERSION")));
+    writer.add_metadata("Scene", &arguments.scene.display().to_string());
+    writer.add_metadata("Photons", &arguments.photons.to_string());
+    writer.add_metadata("Bounces", &arguments.bounces.to_string());
+    writer.add_metadata("Seed", &seed.to_string());
+
+    if let Err(error) = writer.write(&film) {
+        eprintln!("error: {}", error);
+        return process::ExitCode::FAILURE;
+    }
+
+    println!("Wrote {} in {:.2}s", arguments.output.display(), start.elapsed().as_secs_f64());
+
+    process::ExitCode::SUCCESS
 }
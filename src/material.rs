@@ -4,4 +4,14 @@ pub trait Material {
     fn get_name(&self) -> String;
     fn color_for_hit(&self, pixel_direction: &vector3::Vector3, photon_hit: &photon::PhotonHit) -> color::Color;
     fn bounce(&self, photon_hit: &photon::PhotonHit, generator: &mut random_generator::RandomGenerator) -> photon::Photon;
+    // get_emission returns the color and the power per unit area of the light emitted by surfaces of the
+    // material, or None if they don't emit light
+    fn get_emission(&self) -> Option<(color::Color, f64)> {
+        None
+    }
+    // is_transmissive returns whether photons pass through surfaces of the material, which makes the meshes
+    // it is applied to two sided so that photons inside of them can leave them again
+    fn is_transmissive(&self) -> bool {
+        false
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{color, diffuse_material, library, material};
+use crate::{color, diffuse_material, glass_material, glossy_material, library, material, mirror_material};
 
 impl library::Library<Box<dyn material::Material>> {
     pub fn build_material_library() -> library::Library<Box<dyn material::Material>> {
@@ -12,6 +12,9 @@ impl library::Library<Box<dyn material::Material>> {
         l.add("Cyan", Box::new(diffuse_material::DiffuseMaterial::from_color("Cyan", &color::Color::new(0.0, 1.0, 1.0))));
         l.add("Blue", Box::new(diffuse_material::DiffuseMaterial::from_color("Blue", &color::Color::new(0.0, 0.0, 1.0))));
         l.add("Magenta", Box::new(diffuse_material::DiffuseMaterial::from_color("Magenta", &color::Color::new(1.0, 0.0, 1.0))));
+        l.add("Mirror", Box::new(mirror_material::MirrorMaterial::new("Mirror")));
+        l.add("Glossy", Box::new(glossy_material::GlossyMaterial::new("Glossy", 0.3)));
+        l.add("Glass", Box::new(glass_material::GlassMaterial::new("Glass", 1.5)));
 
         l
     }
@@ -1,19 +1,354 @@
-use crate::{hit, ray, tree, triangle};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+
+use crate::{binary, bounds, hit, ray, tree, triangle};
+
+const CACHE_MAGIC: &[u8; 4] = b"TBVH";
+// incremented whenever the layout of the cache or of the trees it stores changes
+const CACHE_VERSION: u32 = 3;
+
+#[derive(Debug)]
+pub enum MeshCacheError {
+    Read(path::PathBuf, io::Error),
+    Write(path::PathBuf, io::Error),
+}
+
+impl fmt::Display for MeshCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshCacheError::Read(path, error) => {
+                write!(f, "Failed to read mesh cache {}: {}", path.display(), error)
+            },
+            MeshCacheError::Write(path, error) => {
+                write!(f, "Failed to write mesh cache {}: {}", path.display(), error)
+            },
+        }
+    }
+}
+
+impl error::Error for MeshCacheError {}
 
 pub struct Mesh {
     pub m_name: String,
-    m_tree: tree::Tree<triangle::Triangle>
+    m_tree: tree::Tree<triangle::Triangle>,
+    // whether the backs of the triangles are hit as well as their fronts
+    m_two_sided: bool,
 }
 
 impl Mesh {
+    // new builds the tree of the mesh with the default surface area heuristic settings
     pub fn new(name: &str, triangles: Vec<triangle::Triangle>) -> Self {
+        Mesh::new_with_builder(name, triangles, tree::TreeBuilder::Sah(tree::SahSettings::default()))
+    }
+
+    pub fn new_with_builder(name: &str, triangles: Vec<triangle::Triangle>, builder: tree::TreeBuilder) -> Self {
         Mesh {
             m_name: String::from(name),
-            m_tree: tree::Tree::new(triangles),
+            m_tree: tree::Tree::new_with_builder(triangles, builder),
+            m_two_sided: false,
+        }
+    }
+
+    pub fn tree_statistics(&self) -> tree::TreeStatistics {
+        self.m_tree.statistics()
+    }
+
+    pub fn triangles(&self) -> &[triangle::Triangle] {
+        self.m_tree.objects()
+    }
+
+    pub fn get_bounds(&self) -> bounds::Bounds {
+        self.m_tree.get_bounds()
+    }
+
+    // set_two_sided makes rays hit the backs of the triangles as well as their fronts, which closed meshes
+    // that photons travel through need in order to be left again
+    pub fn set_two_sided(&mut self, two_sided: bool) {
+        self.m_two_sided = two_sided;
+    }
+
+    pub fn cast_ray(&self, ray: &ray::Ray) -> Option<hit::Hit> {
+        self.m_tree.cast_ray_with_objects(ray, |triangles| {
+            triangle::Triangle::nearest_sided_intersection(triangles, ray, self.m_two_sided)
+        })
+    }
+
+    pub fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.m_tree.occluded_with_objects(ray, max_distance, |triangles| {
+            triangle::Triangle::sided_occluded_by(triangles, ray, max_distance, self.m_two_sided)
+        })
+    }
+}
+
+// CacheSource identifies the source file a cache was saved from. The size and modification time are cheap
+// to compare, so the hash of the contents only needs to be computed when they differ.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheSource {
+    pub size: u64,
+    // nanoseconds since the unix epoch, or 0 when the file system doesn't record it
+    pub modified: u64,
+    pub hash: u64,
+}
+
+// MeshCache holds everything that is needed to rebuild the meshes of a source file without reading it: the
+// built meshes, the material libraries the source names and the index of the material of every mesh in the
+// materials of those libraries
+pub struct MeshCache {
+    pub source: CacheSource,
+    pub material_libraries: Vec<String>,
+    pub meshes: Vec<Mesh>,
+    pub material_ids: Vec<Option<usize>>,
+}
+
+// stored in place of the material id of a mesh without a material
+const NO_MATERIAL: u32 = u32::MAX;
+
+impl MeshCache {
+    // save writes the cache to a binary file. The file is written next to its final path first and then
+    // renamed, so that an interrupted write doesn't leave a truncated cache behind.
+    pub fn save(&self, path: &path::Path) -> Result<(), MeshCacheError> {
+        let mut partial_name = path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".partial");
+        let partial_path = path.with_file_name(partial_name);
+
+        let write = || -> io::Result<()> {
+            let mut writer = io::BufWriter::new(fs::File::create(&partial_path)?);
+
+            io::Write::write_all(&mut writer, CACHE_MAGIC)?;
+            binary::write_u32(&mut writer, CACHE_VERSION)?;
+            binary::write_u64(&mut writer, self.source.size)?;
+            binary::write_u64(&mut writer, self.source.modified)?;
+            binary::write_u64(&mut writer, self.source.hash)?;
+
+            binary::write_u32(&mut writer, self.material_libraries.len() as u32)?;
+            for material_library in &self.material_libraries {
+                binary::write_string(&mut writer, material_library)?;
+            }
+
+            binary::write_u32(&mut writer, self.meshes.len() as u32)?;
+            for (mesh, material_id) in self.meshes.iter().zip(&self.material_ids) {
+                binary::write_string(&mut writer, &mesh.m_name)?;
+                binary::write_u32(&mut writer, material_id.map_or(NO_MATERIAL, |id| id as u32))?;
+                mesh.m_tree.write_to(&mut writer, |writer, triangle| triangle.write_to(writer))?;
+            }
+
+            io::Write::flush(&mut writer)?;
+            drop(writer);
+
+            fs::rename(&partial_path, path)
+        };
+
+        write().map_err(|e| MeshCacheError::Write(path.to_path_buf(), e))
+    }
+
+    // load reads a cache written by save, returning None when there is no cache or when it was written by
+    // another version. Whether it is still valid for its source is up to the caller.
+    pub fn load(path: &path::Path) -> Result<Option<MeshCache>, MeshCacheError> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(MeshCacheError::Read(path.to_path_buf(), e)),
+        };
+
+        let read = || -> io::Result<Option<MeshCache>> {
+            let mut reader = io::BufReader::new(file);
+
+            let mut magic = [0; 4];
+            io::Read::read_exact(&mut reader, &mut magic)?;
+            if &magic != CACHE_MAGIC {
+                return Err(binary::invalid_data("not a mesh cache"));
+            }
+
+            if binary::read_u32(&mut reader)? != CACHE_VERSION {
+                return Ok(None);
+            }
+
+            let source = CacheSource {
+                size: binary::read_u64(&mut reader)?,
+                modified: binary::read_u64(&mut reader)?,
+                hash: binary::read_u64(&mut reader)?,
+            };
+
+            let material_library_count = binary::read_u32(&mut reader)?;
+            let mut material_libraries = Vec::<String>::new();
+            for _ in 0..material_library_count {
+                material_libraries.push(binary::read_string(&mut reader)?);
+            }
+
+            let mesh_count = binary::read_u32(&mut reader)?;
+            let mut meshes = Vec::<Mesh>::new();
+            let mut material_ids = Vec::<Option<usize>>::new();
+
+            for _ in 0..mesh_count {
+                let name = binary::read_string(&mut reader)?;
+                let material_id = binary::read_u32(&mut reader)?;
+
+                meshes.push(Mesh {
+                    m_name: name,
+                    m_tree: tree::Tree::read_from(&mut reader, triangle::Triangle::read_from)?,
+                    m_two_sided: false,
+                });
+                material_ids.push((material_id != NO_MATERIAL).then_some(material_id as usize));
+            }
+
+            Ok(Some(MeshCache {
+                source,
+                material_libraries,
+                meshes,
+                material_ids,
+            }))
+        };
+
+        read().map_err(|e| MeshCacheError::Read(path.to_path_buf(), e))
+    }
+}
+
+// source_hash is the 64-bit FNV-1a hash of the contents of a source file, which a cache records to
+// detect that the source has changed since it was written
+pub fn source_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use crate::{random_generator, vector3};
+
+    fn random_triangles(count: usize, rg: &mut random_generator::RandomGenerator) -> Vec<triangle::Triangle> {
+        (0..count)
+            .map(|_| {
+                let a = vector3::Vector3::new(rg.value(10.0), rg.value(10.0), rg.value(10.0));
+
+                triangle::Triangle::with_normals(
+                    a,
+                    a + vector3::Vector3::new(rg.value(1.0), 0.0, 0.0),
+                    a + vector3::Vector3::new(0.0, rg.value(1.0), 0.0),
+                    vector3::Vector3::new(0.0, 0.0, -1.0),
+                    vector3::Vector3::new(rg.value(1.0), 0.0, -1.0),
+                    vector3::Vector3::new(0.0, rg.value(1.0), -1.0),
+                )
+            })
+            .collect()
+    }
+
+    fn components(vector: &vector3::Vector3) -> (f64, f64, f64) {
+        (vector.get_x(), vector.get_y(), vector.get_z())
+    }
+
+    #[test]
+    fn new_builds_sah_tree() {
+        let mut rg = random_generator::RandomGenerator::from_seed(18);
+        let triangles = random_triangles(200, &mut rg);
+
+        let mesh = Mesh::new("mesh", triangles.clone());
+        let sah = Mesh::new_with_builder("sah", triangles, tree::TreeBuilder::Sah(tree::SahSettings::default()));
+
+        assert_eq!(mesh.tree_statistics(), sah.tree_statistics());
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let path = env::temp_dir().join("tdi_ray_tracer_mesh_cache_round_trip.bvh");
+        let mut rg = random_generator::RandomGenerator::from_seed(17);
+
+        let meshes = vec![
+            Mesh::new_with_builder("median", random_triangles(200, &mut rg), tree::TreeBuilder::Median { page_size: 1 }),
+            Mesh::new("sah", random_triangles(200, &mut rg)),
+        ];
+
+        let source = CacheSource {
+            size: 1,
+            modified: 2,
+            hash: 42,
+        };
+        let cache = MeshCache {
+            source,
+            material_libraries: vec![String::from("first.mtl"), String::from("second.mtl")],
+            meshes,
+            material_ids: vec![Some(3), None],
+        };
+
+        cache.save(&path).unwrap();
+        let loaded = MeshCache::load(&path).unwrap().unwrap();
+
+        assert_eq!(loaded.source, source);
+        assert_eq!(loaded.material_libraries, cache.material_libraries);
+        assert_eq!(loaded.material_ids, cache.material_ids);
+        assert_eq!(loaded.meshes.len(), 2);
+
+        for (mesh, loaded) in cache.meshes.iter().zip(&loaded.meshes) {
+            assert_eq!(loaded.m_name, mesh.m_name);
+            assert_eq!(loaded.tree_statistics(), mesh.tree_statistics());
+            assert_eq!(components(&loaded.get_bounds().minimum()), components(&mesh.get_bounds().minimum()));
+            assert_eq!(components(&loaded.get_bounds().maximum()), components(&mesh.get_bounds().maximum()));
+
+            // the loaded trees give bit for bit the same hits, including the interpolated normals
+            for _ in 0..200 {
+                let ray = ray::Ray::new(
+                    vector3::Vector3::new(rg.value(10.0), rg.value(10.0), -1.0),
+                    vector3::Vector3::new(rg.value(0.2) - 0.1, rg.value(0.2) - 0.1, 1.0),
+                );
+
+                let expected = mesh.cast_ray(&ray);
+                let actual = loaded.cast_ray(&ray);
+
+                assert_eq!(actual.map(|hit| hit.distance), expected.map(|hit| hit.distance));
+                assert_eq!(actual.map(|hit| components(&hit.normal)), expected.map(|hit| components(&hit.normal)));
+                assert_eq!(loaded.occluded(&ray, 5.0), mesh.occluded(&ray, 5.0));
+            }
+        }
+    }
+
+    fn cache_of(meshes: Vec<Mesh>) -> MeshCache {
+        MeshCache {
+            source: CacheSource::default(),
+            material_libraries: Vec::new(),
+            material_ids: vec![None; meshes.len()],
+            meshes,
         }
     }
 
-    pub fn cast_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
-        self.m_tree.cast_ray(ray, cast_buffer)
+    #[test]
+    fn other_version_cache() {
+        let path = env::temp_dir().join("tdi_ray_tracer_mesh_cache_other_version.bvh");
+
+        cache_of(vec![Mesh::new("empty", Vec::new())]).save(&path).unwrap();
+        assert!(MeshCache::load(&path).unwrap().is_some());
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&(CACHE_VERSION + 1).to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(MeshCache::load(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn missing_and_invalid_cache() {
+        let directory = env::temp_dir();
+
+        assert!(MeshCache::load(&directory.join("tdi_ray_tracer_mesh_cache_missing.bvh")).unwrap().is_none());
+
+        let path = directory.join("tdi_ray_tracer_mesh_cache_invalid.bvh");
+        fs::write(&path, b"not a cache").unwrap();
+        assert!(matches!(MeshCache::load(&path), Err(MeshCacheError::Read(_, _))));
+
+        // a truncated cache is an error rather than a tree with missing triangles
+        let mut rg = random_generator::RandomGenerator::from_seed(17);
+        cache_of(vec![Mesh::new("truncated", random_triangles(10, &mut rg))]).save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(MeshCache::load(&path), Err(MeshCacheError::Read(_, _))));
+    }
+
+    #[test]
+    fn hash() {
+        assert_eq!(source_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(source_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(source_hash(b"ab"), source_hash(b"ba"));
     }
 }
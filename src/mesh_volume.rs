@@ -1,4 +1,4 @@
-use crate::{hit, mesh, ray, volume};
+use crate::{bounds, hit, mesh, quaternion, ray, tree, triangle, vector3, volume};
 
 struct MeshVolumeStrategy {
     m_mesh: mesh::Mesh,
@@ -7,8 +7,16 @@ struct MeshVolumeStrategy {
 // This was the old way that didn't work:
 // impl<T: volume::VolumeProtectedInterface> volume::VolumeStrategy<T> for MeshVolumeStrategy {
 impl volume::VolumeStrategy for MeshVolumeStrategy {
-    fn cast_transformed_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
-        self.m_mesh.cast_ray(ray, cast_buffer)
+    fn cast_transformed_ray(&self, ray: &ray::Ray, _cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
+        self.m_mesh.cast_ray(ray)
+    }
+
+    fn transformed_ray_occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.m_mesh.occluded(ray, max_distance)
+    }
+
+    fn get_transformed_bounds(&self) -> Option<bounds::Bounds> {
+        Some(self.m_mesh.get_bounds())
     }
 }
 
@@ -25,6 +33,22 @@ impl MeshVolume {
         }
     }
 
+    pub fn set_position(&mut self, position: vector3::Vector3) {
+        self.volume.object.transform.position = position;
+    }
+
+    pub fn set_rotation(&mut self, rotation: quaternion::Quaternion) {
+        self.volume.object.transform.rotation = rotation;
+    }
+
+    pub fn tree_statistics(&self) -> tree::TreeStatistics {
+        self.get_mesh().tree_statistics()
+    }
+
+    pub fn set_two_sided(&mut self, two_sided: bool) {
+        self.volume.specialization.m_mesh.set_two_sided(two_sided);
+    }
+
     fn get_mesh(&self) -> &mesh::Mesh {
         &self.volume.specialization.m_mesh
     }
@@ -46,4 +70,22 @@ impl volume::VolumePublicInterface for MeshVolume {
     fn cast_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
         self.volume.cast_ray(ray, cast_buffer)
     }
+
+    fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.volume.occluded(ray, max_distance)
+    }
+
+    fn get_bounds(&self) -> Option<bounds::Bounds> {
+        self.volume.get_bounds()
+    }
+
+    fn get_triangles(&self) -> Vec<triangle::Triangle> {
+        let to_world = |point: &vector3::Vector3| self.volume.object.position() + self.volume.object.rotation() * *point;
+
+        self.get_mesh()
+            .triangles()
+            .iter()
+            .map(|triangle| triangle::Triangle::new(to_world(&triangle.a), to_world(&triangle.b), to_world(&triangle.c)))
+            .collect()
+    }
 }
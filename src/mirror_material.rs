@@ -0,0 +1,121 @@
+use crate::{color, fresnel, material, photon, random_generator, ray, vector3};
+
+// MirrorMaterial reflects every photon in exactly the mirror direction, like polished metal. The color
+// is the reflectance at normal incidence, which rises towards white at grazing angles.
+pub struct MirrorMaterial {
+    name: String,
+    m_color: color::Color,
+}
+
+impl MirrorMaterial {
+    pub fn new(name: &str) -> MirrorMaterial {
+        MirrorMaterial {
+            name: name.to_string(),
+            m_color: color::Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn from_color(name: &str, color: &color::Color) -> MirrorMaterial {
+        MirrorMaterial {
+            name: name.to_string(),
+            m_color: *color,
+        }
+    }
+}
+
+impl material::Material for MirrorMaterial {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    // a mirror only reflects light in a single direction, which the direction to a pixel hits with zero
+    // probability, so it is only ever seen through the photons it reflects onto other surfaces
+    fn color_for_hit(&self, _pixel_direction: &vector3::Vector3, _photon_hit: &photon::PhotonHit) -> color::Color {
+        color::Color::default()
+    }
+
+    fn bounce(&self, photon_hit: &photon::PhotonHit, _random_generator: &mut random_generator::RandomGenerator) -> photon::Photon {
+        let hit = &photon_hit.hit;
+        let incident = photon_hit.photon.ray.direction;
+        let reflection = vector3::Vector3::reflected(&incident, &hit.normal);
+        let cos_theta = vector3::Vector3::dot(&-incident, &hit.normal);
+
+        let color = if hit.reflects_validly(&-incident, &reflection) {
+            fresnel::schlick(cos_theta, &self.m_color) * photon_hit.photon.color
+        } else {
+            color::Color::default()
+        };
+
+        photon::Photon {
+            bounces: photon_hit.photon.bounces + 1,
+            medium: photon_hit.photon.medium,
+            color,
+            ray: ray::Ray {
+                origin: hit.offset_origin(&reflection),
+                direction: reflection,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::hit;
+    use crate::material::Material;
+
+    fn photon_hit(direction: vector3::Vector3) -> photon::PhotonHit {
+        photon::PhotonHit {
+            hit: hit::Hit::new(vector3::Vector3::default(), vector3::UNIT_Y, 1.0, 0),
+            photon: photon::Photon {
+                ray: ray::Ray::new(-direction, direction),
+                color: color::Color::new(1.0, 1.0, 1.0),
+                bounces: 0,
+                medium: None,
+            },
+        }
+    }
+
+    #[test]
+    fn bounce_reflects() {
+        let material = MirrorMaterial::from_color("Gold", &color::Color::new(1.0, 0.8, 0.3));
+        let mut rg = random_generator::RandomGenerator::from_seed(1);
+
+        let direction = vector3::Vector3::new(1.0, -1.0, 0.0) / 2.0f64.sqrt();
+        let photon = material.bounce(&photon_hit(direction), &mut rg);
+
+        assert_approx_eq!(photon.ray.direction.get_x(), direction.get_x(), 1e-9f64);
+        assert_approx_eq!(photon.ray.direction.get_y(), -direction.get_y(), 1e-9f64);
+        assert!(photon.ray.origin.get_y() > 0.0);
+        assert_eq!(photon.bounces, 1);
+
+        // away from normal incidence the reflectance is between the color and white
+        assert_approx_eq!(photon.color.red, 1.0, 1e-9f64);
+        assert!(photon.color.blue > 0.3 && photon.color.blue < 1.0);
+    }
+
+    #[test]
+    fn bounce_normal_incidence() {
+        let material = MirrorMaterial::from_color("Gold", &color::Color::new(1.0, 0.8, 0.3));
+        let mut rg = random_generator::RandomGenerator::from_seed(1);
+
+        let photon = material.bounce(&photon_hit(-vector3::UNIT_Y), &mut rg);
+
+        assert_approx_eq!(photon.ray.direction.get_y(), 1.0, 1e-9f64);
+        assert_approx_eq!(photon.color.green, 0.8, 1e-9f64);
+        assert_approx_eq!(photon.color.blue, 0.3, 1e-9f64);
+    }
+
+    #[test]
+    fn bounce_from_behind() {
+        let material = MirrorMaterial::new("Mirror");
+        let mut rg = random_generator::RandomGenerator::from_seed(1);
+
+        let photon = material.bounce(&photon_hit(vector3::UNIT_Y), &mut rg);
+
+        assert_eq!(photon.color.brightness(), 0.0);
+    }
+}
@@ -1,22 +1,718 @@
+use std::cell;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
 use std::path;
+use std::time;
 
-use crate::{mesh, triangle};
+use crate::{color, diffuse_material, library, material, mesh, mesh_volume, texture, triangle, uv, vector3};
 
-pub fn load_meshes(path: &path::Path) -> Vec<mesh::Mesh> {
-    let (models, _) =
-        tobj::load_obj(
-            &path,
-            &tobj::LoadOptions::default()
+const DEFAULT_MATERIAL_NAME: &str = "default";
+
+#[derive(Debug)]
+pub enum ObjReaderError {
+    Io(path::PathBuf, io::Error),
+    Obj(path::PathBuf, tobj::LoadError),
+    Mtl(path::PathBuf, tobj::LoadError),
+    MissingMaterial(String, usize),
+    MissingVertex(String, usize),
+    Texture(texture::TextureError),
+}
+
+impl fmt::Display for ObjReaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjReaderError::Io(path, error) => {
+                write!(f, "Failed to read OBJ file {}: {}", path.display(), error)
+            },
+            ObjReaderError::Obj(path, error) => {
+                write!(f, "Failed to load OBJ file {}: {}", path.display(), error)
+            },
+            ObjReaderError::Mtl(path, error) => {
+                write!(f, "Failed to load materials for OBJ file {}: {}", path.display(), error)
+            },
+            ObjReaderError::MissingMaterial(model, index) => {
+                write!(f, "Model {} references material {}, which is not defined in its MTL file", model, index)
+            },
+            ObjReaderError::MissingVertex(model, index) => {
+                write!(f, "Model {} references vertex {}, which does not exist", model, index)
+            },
+            ObjReaderError::Texture(error) => {
+                write!(f, "{}", error)
+            },
+        }
+    }
+}
+
+impl error::Error for ObjReaderError {}
+
+// load_meshes reads every model in an OBJ file into a MeshVolume, registering the diffuse color and
+// diffuse texture of each MTL material in material_library under library_name(path, name). Materials
+// registered by an earlier load of the same file are reused.
+pub fn load_meshes(
+    path: &path::Path,
+    material_library: &mut library::Library<Box<dyn material::Material>>,
+) -> Result<Vec<mesh_volume::MeshVolume>, ObjReaderError> {
+    load(path, material_library, false)
+}
+
+// load_meshes_with_cache is load_meshes, except that the meshes, their trees and the materials they use are
+// read from the cache at cache_path(path) when it was saved from the same OBJ file, so that the OBJ file
+// isn't parsed, and are saved there after they are built when it wasn't. The materials themselves are always
+// read from the MTL files, so editing them doesn't invalidate the cache.
+pub fn load_meshes_with_cache(
+    path: &path::Path,
+    material_library: &mut library::Library<Box<dyn material::Material>>,
+) -> Result<Vec<mesh_volume::MeshVolume>, ObjReaderError> {
+    load(path, material_library, true)
+}
+
+// cache_path is the path of the mesh cache of an OBJ file, which is the OBJ file's path with .bvh appended
+pub fn cache_path(path: &path::Path) -> path::PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".bvh");
+
+    path.with_file_name(file_name)
+}
+
+fn load(
+    path: &path::Path,
+    material_library: &mut library::Library<Box<dyn material::Material>>,
+    use_cache: bool,
+) -> Result<Vec<mesh_volume::MeshVolume>, ObjReaderError> {
+    if !use_cache {
+        let source = read_source(path)?;
+        let obj = parse_obj(path, &source)?;
+        let (meshes, material_ids) = build_meshes(&obj.models)?;
+
+        return build_volumes(path, meshes, &material_ids, &obj.materials, material_library);
+    }
+
+    let cache_path = cache_path(path);
+    let metadata = fs::metadata(path).map_err(|e| ObjReaderError::Io(path.to_path_buf(), e))?;
+    let size = metadata.len();
+    let modified = modified_time(&metadata);
+
+    // a cache that can't be read is rebuilt
+    let cache = mesh::MeshCache::load(&cache_path).ok().flatten();
+
+    // the file is only read when its size or modification time differ from the ones the cache was saved with,
+    // and only parsed when its contents differ as well, so a valid cache only needs the MTL files to be read
+    let cache = match cache {
+        Some(cache) if cache.source.size == size && modified.is_some_and(|modified| modified == cache.source.modified) => cache,
+        cache => {
+            let source = read_source(path)?;
+            let cache_source = mesh::CacheSource {
+                size,
+                modified: modified.unwrap_or(0),
+                hash: mesh::source_hash(&source),
+            };
+
+            let cache = match cache.filter(|cache| cache.source.hash == cache_source.hash) {
+                Some(cache) => mesh::MeshCache {
+                    source: cache_source,
+                    ..cache
+                },
+                None => {
+                    let obj = parse_obj(path, &source)?;
+                    let (meshes, material_ids) = build_meshes(&obj.models)?;
+
+                    mesh::MeshCache {
+                        source: cache_source,
+                        material_libraries: obj.material_libraries,
+                        meshes,
+                        material_ids,
+                    }
+                },
+            };
+
+            // the cache only speeds up later loads, so failing to save it doesn't fail this one
+            let _ = cache.save(&cache_path);
+
+            cache
+        },
+    };
+
+    let materials = load_material_libraries(path, &cache.material_libraries)?;
+
+    build_volumes(path, cache.meshes, &cache.material_ids, &materials, material_library)
+}
+
+fn read_source(path: &path::Path) -> Result<Vec<u8>, ObjReaderError> {
+    fs::read(path).map_err(|e| ObjReaderError::Io(path.to_path_buf(), e))
+}
+
+// modified_time returns the modification time of a file in nanoseconds since the unix epoch, if the file
+// system records it
+fn modified_time(metadata: &fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?.duration_since(time::UNIX_EPOCH).ok()?;
+
+    Some(modified.as_nanos() as u64).filter(|&nanos| nanos != 0)
+}
+
+// ParsedObj is an OBJ file's models, the materials of its MTL files and the names of those files in the order
+// the materials are in
+struct ParsedObj {
+    models: Vec<tobj::Model>,
+    materials: Vec<tobj::Material>,
+    material_libraries: Vec<String>,
+}
+
+fn parse_obj(path: &path::Path, source: &[u8]) -> Result<ParsedObj, ObjReaderError> {
+    let directory = path.parent().unwrap_or(path::Path::new(""));
+    let material_libraries = cell::RefCell::new(Vec::<String>::new());
+    let mut reader = source;
+
+    let (models, materials) =
+        tobj::load_obj_buf(
+            &mut reader,
+            &tobj::LoadOptions {
+                single_index: true,
+                triangulate: true,
+                ..Default::default()
+            },
+            |material_library| {
+                material_libraries.borrow_mut().push(material_library.to_string_lossy().into_owned());
+                tobj::load_mtl(directory.join(material_library))
+            },
         )
-            .expect("Failed to OBJ load file");
+            .map_err(|e| ObjReaderError::Obj(path.to_path_buf(), e))?;
+
+    let materials = materials.map_err(|e| ObjReaderError::Mtl(path.to_path_buf(), e))?;
+
+    Ok(ParsedObj {
+        models,
+        materials,
+        material_libraries: material_libraries.into_inner(),
+    })
+}
 
-    let mut meshes = Vec::<mesh::Mesh>::new();
+// load_material_libraries reads the materials of the MTL files an OBJ file names, relative to the OBJ file, in
+// the same order as parse_obj
+fn load_material_libraries(path: &path::Path, material_libraries: &[String]) -> Result<Vec<tobj::Material>, ObjReaderError> {
+    let directory = path.parent().unwrap_or(path::Path::new(""));
+    let mut materials = Vec::<tobj::Material>::new();
+
+    for material_library in material_libraries {
+        let (mut library_materials, _) =
+            tobj::load_mtl(directory.join(material_library)).map_err(|e| ObjReaderError::Mtl(path.to_path_buf(), e))?;
+        materials.append(&mut library_materials);
+    }
+
+    Ok(materials)
+}
+
+// build_meshes builds a mesh for every model, returning them with the index of every model's material in the
+// materials of its MTL files
+fn build_meshes(models: &[tobj::Model]) -> Result<(Vec<mesh::Mesh>, Vec<Option<usize>>), ObjReaderError> {
+    let mut meshes = Vec::<mesh::Mesh>::with_capacity(models.len());
 
     for model in models {
-        let mut triangles = Vec::<triangle::Triangle>::new();
-        // TODO(cdelguercio): implement
-        meshes.push(mesh::Mesh::new(&model.name, triangles));
+        meshes.push(mesh::Mesh::new(&model.name, build_triangles(model)?));
+    }
+
+    Ok((meshes, models.iter().map(|model| model.mesh.material_id).collect()))
+}
+
+// build_volumes registers the materials in the library and wraps every mesh in a MeshVolume with its material,
+// or with the default material when it has none
+fn build_volumes(
+    path: &path::Path,
+    meshes: Vec<mesh::Mesh>,
+    material_ids: &[Option<usize>],
+    materials: &[tobj::Material],
+    material_library: &mut library::Library<Box<dyn material::Material>>,
+) -> Result<Vec<mesh_volume::MeshVolume>, ObjReaderError> {
+    let directory = path.parent().unwrap_or(path::Path::new(""));
+
+    let material_indices = materials
+        .iter()
+        .map(|m| register_material(m, path, directory, material_library))
+        .collect::<Result<Vec<usize>, ObjReaderError>>()?;
+
+    let mut volumes = Vec::<mesh_volume::MeshVolume>::with_capacity(meshes.len());
+
+    for (mesh, material_id) in meshes.into_iter().zip(material_ids) {
+        let material_index = match *material_id {
+            Some(id) => *material_indices.get(id).ok_or(ObjReaderError::MissingMaterial(mesh.m_name.clone(), id))?,
+            None => default_material_index(material_library),
+        };
+
+        volumes.push(mesh_volume::MeshVolume::new(material_index, mesh));
+    }
+
+    Ok(volumes)
+}
+
+// library_name is the name an MTL material loaded for an OBJ file is registered under in the material
+// library, so that it doesn't clash with built in materials or with materials of the same name in other files
+fn library_name(path: &path::Path, material_name: &str) -> String {
+    format!("{}:{}", path.display(), material_name)
+}
+
+// register_material adds an MTL material of the OBJ file at path to the library, loading its diffuse texture
+// relative to directory
+fn register_material(
+    material: &tobj::Material,
+    path: &path::Path,
+    directory: &path::Path,
+    material_library: &mut library::Library<Box<dyn material::Material>>,
+) -> Result<usize, ObjReaderError> {
+    let name = library_name(path, &material.name);
+    if let Some(index) = material_library.find_index_for_name(&name) {
+        return Ok(index);
+    }
+
+    let color = match material.diffuse {
+        Some([red, green, blue]) => color::Color::new(red as f64, green as f64, blue as f64),
+        None => color::Color::new(1.0, 1.0, 1.0),
+    };
+
+    let mut diffuse_material = diffuse_material::DiffuseMaterial::from_color(&material.name, &color);
+
+    if let Some(texture_path) = &material.diffuse_texture {
+        let texture = texture::Texture::load(&directory.join(texture_path)).map_err(ObjReaderError::Texture)?;
+        diffuse_material.set_texture(texture);
+    }
+
+    material_library.add(&name, Box::new(diffuse_material));
+    Ok(material_library.index_for_name(&name))
+}
+
+fn default_material_index(material_library: &mut library::Library<Box<dyn material::Material>>) -> usize {
+    if let Some(index) = material_library.find_index_for_name(DEFAULT_MATERIAL_NAME) {
+        return index;
+    }
+
+    material_library.add(DEFAULT_MATERIAL_NAME, Box::new(diffuse_material::DiffuseMaterial::new(DEFAULT_MATERIAL_NAME)));
+    material_library.index_for_name(DEFAULT_MATERIAL_NAME)
+}
+
+fn build_triangles(model: &tobj::Model) -> Result<Vec<triangle::Triangle>, ObjReaderError> {
+    let mesh = &model.mesh;
+    let has_normals = !mesh.normals.is_empty();
+    let has_uvs = !mesh.texcoords.is_empty();
+
+    let vector_at = |values: &Vec<f32>, index: u32| -> Result<vector3::Vector3, ObjReaderError> {
+        let start = index as usize * 3;
+        match values.get(start..start + 3) {
+            Some(v) => Ok(vector3::Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64)),
+            None => Err(ObjReaderError::MissingVertex(model.name.clone(), index as usize)),
+        }
+    };
+
+    let uv_at = |values: &Vec<f32>, index: u32| -> Result<uv::Uv, ObjReaderError> {
+        let start = index as usize * 2;
+        match values.get(start..start + 2) {
+            Some(v) => Ok(uv::Uv::new(v[0] as f64, v[1] as f64)),
+            None => Err(ObjReaderError::MissingVertex(model.name.clone(), index as usize)),
+        }
+    };
+
+    let mut triangles = Vec::<triangle::Triangle>::with_capacity(mesh.indices.len() / 3);
+
+    for face in mesh.indices.chunks_exact(3) {
+        let a = vector_at(&mesh.positions, face[0])?;
+        let b = vector_at(&mesh.positions, face[1])?;
+        let c = vector_at(&mesh.positions, face[2])?;
+
+        let mut triangle = if has_normals {
+            triangle::Triangle::with_normals(
+                a,
+                b,
+                c,
+                vector_at(&mesh.normals, face[0])?,
+                vector_at(&mesh.normals, face[1])?,
+                vector_at(&mesh.normals, face[2])?,
+            )
+        } else {
+            triangle::Triangle::new(a, b, c)
+        };
+
+        if has_uvs {
+            triangle.set_uvs(
+                uv_at(&mesh.texcoords, face[0])?,
+                uv_at(&mesh.texcoords, face[1])?,
+                uv_at(&mesh.texcoords, face[2])?,
+            );
+        }
+
+        triangles.push(triangle);
     }
 
-    meshes
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use crate::{hit, photon, ray};
+    use crate::volume::VolumePublicInterface;
+
+    fn write_test_files(name: &str, obj: &str, mtl: Option<&str>) -> path::PathBuf {
+        let directory = std::env::temp_dir().join(format!("tdi_ray_tracer_obj_reader_{}", name));
+        fs::create_dir_all(&directory).unwrap();
+
+        if let Some(mtl) = mtl {
+            fs::write(directory.join("test.mtl"), mtl).unwrap();
+        }
+
+        let obj_path = directory.join("test.obj");
+        fs::write(&obj_path, obj).unwrap();
+
+        obj_path
+    }
+
+    #[test]
+    fn load_meshes_with_materials() {
+        let obj_path = write_test_files(
+            "materials",
+            "mtllib test.mtl\n\
+             o first\n\
+             v 0.0 0.0 3.0\n\
+             v 0.0 1.0 3.0\n\
+             v 1.0 0.0 3.0\n\
+             v 1.0 1.0 3.0\n\
+             usemtl Orange\n\
+             f 1 2 3\n\
+             f 2 4 3\n\
+             o second\n\
+             usemtl Cyan\n\
+             f 1 2 3\n",
+            Some("newmtl Orange\nKd 1.0 0.5 0.0\n\nnewmtl Cyan\nKd 0.0 1.0 1.0\n"),
+        );
+
+        let mut material_library = library::Library::build_material_library();
+        let mesh_volumes = load_meshes(&obj_path, &mut material_library).unwrap();
+
+        assert_eq!(mesh_volumes.len(), 2);
+
+        let orange_index = material_library.index_for_name(&library_name(&obj_path, "Orange"));
+        assert_eq!(mesh_volumes[0].get_material_index(), orange_index);
+        assert_eq!(material_library.fetch_by_index(orange_index).get_name(), "Orange");
+
+        // a material named like a built in one is registered separately rather than replaced by it
+        let cyan_index = material_library.index_for_name(&library_name(&obj_path, "Cyan"));
+        assert_eq!(mesh_volumes[1].get_material_index(), cyan_index);
+        assert_ne!(cyan_index, material_library.index_for_name("Cyan"));
+
+        // loading the same file again reuses its materials rather than registering them twice
+        let reloaded_volumes = load_meshes(&obj_path, &mut material_library).unwrap();
+        assert_eq!(reloaded_volumes[0].get_material_index(), orange_index);
+        assert_eq!(reloaded_volumes[1].get_material_index(), cyan_index);
+
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.75, 0.75, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+
+        let hit = mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap();
+        assert_eq!(hit.material_index, orange_index);
+        assert!((hit.distance - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn load_meshes_fills_leaves() {
+        // a 10 by 10 grid of quads, which the four wide intersection tests four triangles of at a time
+        let mut obj = String::new();
+        for y in 0..=10 {
+            for x in 0..=10 {
+                obj.push_str(&format!("v {} {} 3.0\n", x, y));
+            }
+        }
+        for y in 0..10 {
+            for x in 0..10 {
+                let corner = y * 11 + x + 1;
+                obj.push_str(&format!("f {} {} {}\nf {} {} {}\n", corner, corner + 11, corner + 1, corner + 1, corner + 11, corner + 12));
+            }
+        }
+        let obj_path = write_test_files("leaves", &obj, None);
+
+        let mut material_library = library::Library::build_material_library();
+        let mesh_volumes = load_meshes(&obj_path, &mut material_library).unwrap();
+
+        let statistics = mesh_volumes[0].tree_statistics();
+        assert_eq!(statistics.object_count, 200);
+        assert!(statistics.mean_leaf_objects > 1.0, "{:?}", statistics);
+        assert!(statistics.max_leaf_objects <= 4, "{:?}", statistics);
+    }
+
+    #[test]
+    fn load_meshes_with_normals() {
+        let obj_path = write_test_files(
+            "normals",
+            "v 0.0 0.0 3.0\n\
+             v 0.0 1.0 3.0\n\
+             v 1.0 0.0 3.0\n\
+             vn 0.0 0.0 -1.0\n\
+             vn 0.0 1.0 -1.0\n\
+             vn 1.0 0.0 -1.0\n\
+             f 1//1 2//2 3//3\n",
+            None,
+        );
+
+        let mut material_library = library::Library::build_material_library();
+        let mesh_volumes = load_meshes(&obj_path, &mut material_library).unwrap();
+
+        assert_eq!(mesh_volumes.len(), 1);
+        assert_eq!(mesh_volumes[0].get_material_index(), material_library.index_for_name("default"));
+
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 0.0, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+
+        // the hit is on vertex a, so the normal is the authored vertex normal
+        let hit = mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap();
+        assert!((hit.normal.get_z() + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn load_meshes_with_cache_reuses_valid_cache() {
+        let obj = "o triangle\n\
+                   v 0.0 0.0 3.0\n\
+                   v 0.0 1.0 3.0\n\
+                   v 1.0 0.0 3.0\n\
+                   f 1 2 3\n";
+        let obj_path = write_test_files("cache", obj, None);
+        let cache_path = cache_path(&obj_path);
+        assert_eq!(cache_path.file_name().unwrap(), "test.obj.bvh");
+        let _ = fs::remove_file(&cache_path);
+
+        let mut material_library = library::Library::build_material_library();
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.25, 0.25, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+
+        // the first load builds the tree and saves it
+        let mesh_volumes = load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+        assert!(cache_path.exists());
+        assert!((mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap().distance - 3.0).abs() < 1e-6);
+
+        let cache = mesh::MeshCache::load(&cache_path).unwrap().unwrap();
+        assert_eq!(cache.source.size, obj.len() as u64);
+        assert_eq!(cache.source.hash, mesh::source_hash(obj.as_bytes()));
+
+        // a cache saved for the same file is used instead of the file's triangles
+        let moved = triangle::Triangle::new(
+            vector3::Vector3::new(0.0, 0.0, 5.0),
+            vector3::Vector3::new(0.0, 1.0, 5.0),
+            vector3::Vector3::new(1.0, 0.0, 5.0),
+        );
+        mesh::MeshCache {
+            meshes: vec![mesh::Mesh::new("triangle", vec![moved])],
+            ..cache
+        }
+            .save(&cache_path)
+            .unwrap();
+
+        let mesh_volumes = load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+        assert_eq!(mesh_volumes[0].get_material_index(), material_library.index_for_name("default"));
+        assert!((mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap().distance - 5.0).abs() < 1e-6);
+
+        // while the size and modification time match the file isn't even parsed, so garbage of the same size
+        // still loads the cached meshes
+        let modified = fs::metadata(&obj_path).unwrap().modified().unwrap();
+        let garbage = obj.replace(|c: char| c.is_ascii_digit(), "x");
+        fs::write(&obj_path, &garbage).unwrap();
+        fs::File::options().write(true).open(&obj_path).unwrap().set_modified(modified).unwrap();
+
+        assert!(matches!(load_meshes(&obj_path, &mut material_library), Err(ObjReaderError::Obj(_, _))));
+        let mesh_volumes = load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+        assert!((mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap().distance - 5.0).abs() < 1e-6);
+
+        // a file that only has a new modification time is hashed, still matches and the cache is saved again
+        // with the new time
+        fs::write(&obj_path, obj).unwrap();
+        let touched = modified + time::Duration::from_secs(1);
+        fs::File::options().write(true).open(&obj_path).unwrap().set_modified(touched).unwrap();
+
+        let mesh_volumes = load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+        assert!((mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap().distance - 5.0).abs() < 1e-6);
+        assert_eq!(mesh::MeshCache::load(&cache_path).unwrap().unwrap().source.modified, modified_time(&fs::metadata(&obj_path).unwrap()).unwrap());
+
+        // once the file changes the cache is stale, and is rebuilt from the file
+        fs::write(&obj_path, obj.replace("3.0", "4.0")).unwrap();
+
+        let mesh_volumes = load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+        assert!((mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap().distance - 4.0).abs() < 1e-6);
+
+        let hash = mesh::source_hash(&fs::read(&obj_path).unwrap());
+        assert_eq!(mesh::MeshCache::load(&cache_path).unwrap().unwrap().source.hash, hash);
+    }
+
+    #[test]
+    fn load_meshes_with_cache_reads_materials() {
+        let obj_path = write_test_files(
+            "cache_materials",
+            "mtllib test.mtl\n\
+             o first\n\
+             v 0.0 0.0 3.0\n\
+             v 0.0 1.0 3.0\n\
+             v 1.0 0.0 3.0\n\
+             usemtl Cyan\n\
+             f 1 2 3\n\
+             o second\n\
+             usemtl Orange\n\
+             f 1 2 3\n",
+            Some("newmtl Orange\nKd 1.0 0.5 0.0\n\nnewmtl Cyan\nKd 0.0 1.0 1.0\n"),
+        );
+        let cache_path = cache_path(&obj_path);
+        let _ = fs::remove_file(&cache_path);
+
+        let mut material_library = library::Library::build_material_library();
+        load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+
+        let cache = mesh::MeshCache::load(&cache_path).unwrap().unwrap();
+        assert_eq!(cache.material_libraries, vec![String::from("test.mtl")]);
+        assert_eq!(cache.material_ids, vec![Some(1), Some(0)]);
+
+        // the materials of the cached meshes are read from the MTL file
+        let mut material_library = library::Library::build_material_library();
+        let mesh_volumes = load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+        assert_eq!(mesh_volumes[0].get_material_index(), material_library.index_for_name(&library_name(&obj_path, "Cyan")));
+        assert_eq!(mesh_volumes[1].get_material_index(), material_library.index_for_name(&library_name(&obj_path, "Orange")));
+    }
+
+
+    #[test]
+    fn load_meshes_with_cache_rebuilds_unreadable_cache() {
+        let obj_path = write_test_files(
+            "unreadable_cache",
+            "o triangle\n\
+             v 0.0 0.0 3.0\n\
+             v 0.0 1.0 3.0\n\
+             v 1.0 0.0 3.0\n\
+             f 1 2 3\n",
+            None,
+        );
+        let cache_path = cache_path(&obj_path);
+        fs::write(&cache_path, b"not a mesh cache").unwrap();
+
+        let mut material_library = library::Library::build_material_library();
+        let mesh_volumes = load_meshes_with_cache(&obj_path, &mut material_library).unwrap();
+
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.25, 0.25, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+        assert!((mesh_volumes[0].cast_ray(&ray, &mut Vec::new()).unwrap().distance - 3.0).abs() < 1e-6);
+
+        // the garbage is replaced by a cache of the rebuilt meshes
+        let hash = mesh::source_hash(&fs::read(&obj_path).unwrap());
+        assert_eq!(mesh::MeshCache::load(&cache_path).unwrap().unwrap().source.hash, hash);
+    }
+
+    #[test]
+    fn load_meshes_with_texture_coordinates() {
+        let obj_path = write_test_files(
+            "texture_coordinates",
+            "mtllib test.mtl\n\
+             v 0.0 0.0 3.0\n\
+             v 0.0 1.0 3.0\n\
+             v 1.0 0.0 3.0\n\
+             vt 0.0 0.0\n\
+             vt 0.0 1.0\n\
+             vt 1.0 0.0\n\
+             usemtl Checker\n\
+             f 1/1 2/2 3/3\n",
+            Some("newmtl Checker\nKd 1.0 1.0 1.0\nmap_Kd checker.png\n"),
+        );
+
+        let mut encoder = png::Encoder::new(fs::File::create(obj_path.with_file_name("checker.png")).unwrap(), 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 255, 255, 0, 0, 0]).unwrap();
+        writer.finish().unwrap();
+
+        let mut material_library = library::Library::build_material_library();
+        let mesh_volumes = load_meshes(&obj_path, &mut material_library).unwrap();
+
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.25, 0.5, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+
+        // the texture coordinates follow the positions, which line up with them
+        let hit = mesh_volumes[0].cast_ray(&ray, &mut cast_buffer).unwrap();
+        assert!((hit.uv.u - 0.25).abs() < 1e-6);
+        assert!((hit.uv.v - 0.5).abs() < 1e-6);
+
+        let photon_hit = photon::PhotonHit {
+            hit,
+            photon: photon::Photon {
+                ray,
+                color: color::Color::new(1.0, 1.0, 1.0),
+                bounces: 0,
+                medium: None,
+            },
+        };
+
+        // the left texel of the texture is white, the right one black
+        let material = material_library.fetch_by_index(hit.material_index);
+        let color = material.color_for_hit(&vector3::Vector3::new(0.0, 0.0, 1.0), &photon_hit);
+        assert!(color.red > 0.0);
+
+        let mut right = photon_hit;
+        right.hit.uv = uv::Uv::new(0.75, 0.5);
+        assert_eq!(material.color_for_hit(&vector3::Vector3::new(0.0, 0.0, 1.0), &right).red, 0.0);
+    }
+
+    #[test]
+    fn load_meshes_missing_texture() {
+        let obj_path = write_test_files(
+            "missing_texture",
+            "mtllib test.mtl\n\
+             v 0.0 0.0 3.0\n\
+             v 0.0 1.0 3.0\n\
+             v 1.0 0.0 3.0\n\
+             usemtl Missing\n\
+             f 1 2 3\n",
+            Some("newmtl Missing\nmap_Kd missing.png\n"),
+        );
+
+        let mut material_library = library::Library::build_material_library();
+        let result = load_meshes(&obj_path, &mut material_library);
+
+        assert!(matches!(result, Err(ObjReaderError::Texture(texture::TextureError::Io(_, _)))));
+    }
+
+    #[test]
+    fn load_meshes_missing_file() {
+        let mut material_library = library::Library::build_material_library();
+        let result = load_meshes(path::Path::new("does_not_exist.obj"), &mut material_library);
+
+        assert!(matches!(result, Err(ObjReaderError::Io(_, ref e)) if e.kind() == io::ErrorKind::NotFound));
+
+        let result = load_meshes_with_cache(path::Path::new("does_not_exist.obj"), &mut material_library);
+
+        assert!(matches!(result, Err(ObjReaderError::Io(_, ref e)) if e.kind() == io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn load_meshes_missing_mtl() {
+        let obj_path = write_test_files(
+            "missing_mtl",
+            "mtllib missing.mtl\n\
+             v 0.0 0.0 3.0\n\
+             v 0.0 1.0 3.0\n\
+             v 1.0 0.0 3.0\n\
+             f 1 2 3\n",
+            None,
+        );
+
+        let mut material_library = library::Library::build_material_library();
+        let result = load_meshes(&obj_path, &mut material_library);
+
+        assert!(matches!(result, Err(ObjReaderError::Mtl(_, _))));
+    }
 }
@@ -9,6 +9,8 @@ pub struct Photon {
     pub ray: ray::Ray,
     pub color: color::Color,
     pub bounces: u32,
+    // material index of the transparent volume the photon is travelling through, None outside of them
+    pub medium: Option<usize>,
 }
 
 impl Default for Photon {
@@ -17,6 +19,7 @@ impl Default for Photon {
             ray: ray::Ray::default(),
             color: color::Color::default(),
             bounces: 0,
+            medium: None,
         }
     }
 }
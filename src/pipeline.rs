@@ -1,115 +1,143 @@
-use std::path;
-use std::thread;
+use std::sync::atomic;
 
 use crossbeam;
-use kanal;
 
-use crate::{renderer, photon, scene, pixel, png_writer, hit, image, random_generator};
+use crate::{emitter, renderer, photon, scene, hit, film, random_generator, render_settings};
 
 pub struct Pipeline {
     renderer: renderer::Renderer,
+    settings: render_settings::RenderSettings,
 }
 
 impl Pipeline {
-    pub fn new(renderer: renderer::Renderer) -> Self {
+    pub fn new(mut renderer: renderer::Renderer, settings: render_settings::RenderSettings) -> Self {
+        renderer.set_bounce_threshold(settings.bounce_threshold);
+
         Pipeline {
             renderer,
+            settings,
         }
     }
 
-    pub fn render_scene(&self, scene: &scene::Scene) {
-        let (photon_sender, photon_receiver): (kanal::Sender<photon::Photon>, kanal::Receiver<photon::Photon>) = kanal::unbounded();
-        let (hit_sender, hit_receiver): (kanal::Sender<photon::PhotonHit>, kanal::Receiver<photon::PhotonHit>) = kanal::unbounded();
-        let (final_hit_sender, final_hit_receiver): (kanal::Sender<photon::PhotonHit>, kanal::Receiver<photon::PhotonHit>) = kanal::unbounded();
-        let photon_receiver_checker = photon_receiver.clone();
-        let hit_receiver_checker = hit_receiver.clone();
-        let final_hit_receiver_checker = final_hit_receiver.clone();
+    // render_scene traces the photons of every light in the scene and returns the film they were
+    // accumulated on
+    //
+    // Each worker thread traces a contiguous range of the photons from emission to the camera onto its
+    // own film with its own random stream derived from the seed, and the films are merged in order, so
+    // the result only depends on the seed and the thread count
+    pub fn render_scene(&self, scene: &scene::Scene) -> film::Film {
+        let thread_count = self.settings.thread_count.max(1);
+        let photon_count = self.settings.photon_count;
+        let seed = self.settings.seed.unwrap_or_else(rand::random);
 
-        let mut image = image::Image::new(scene.camera.width(), scene.camera.height());
+        let traced_photons = atomic::AtomicUsize::new(0);
+        let emitters = emitter::Emitters::new(&scene.volumes, &scene.material_library);
 
         let result = crossbeam::scope(|s| {
-            let process_lights_handle = s.spawn(|_| {
-                let mut rg = random_generator::RandomGenerator::new();
-                for _ in 0..10000 {
-
-
-                    let mut photon = photon::Photon::default();
-
-                    println!("photon = {:?}", photon);
-
-                    for light in &scene.lights {
-                        self.renderer.process_light(light.as_ref(), &mut photon, 1.0, &mut rg);
-                    }
-
-                    photon_sender.send(photon).expect("Photon Send Failed");
-                }
-            });
+            let handles: Vec<_> = random_generator::RandomGenerator::streams(seed, thread_count)
+                .into_iter()
+                .enumerate()
+                .map(|(i, rg)| {
+                    let first = photon_count * i / thread_count;
+                    let last = photon_count * (i + 1) / thread_count;
+                    let traced_photons = &traced_photons;
+                    let emitters = &emitters;
+
+                    s.spawn(move |_| self.trace_photons(scene, emitters, last - first, rg, traced_photons))
+                })
+                .collect();
+
+            handles.into_iter()
+                .map(|handle| handle.join().expect("Photon Worker Failed"))
+                .collect::<Vec<_>>()
+        });
 
-            s.spawn(|_| {
-                let mut cast_buffer = Vec::<hit::Hit>::new();
+        let worker_films = result.unwrap();
 
-                let mut volume_hit_buffer = Vec::<photon::PhotonHit>::new();
+        if self.settings.report_progress {
+            eprintln!();
+        }
 
-                for photon in photon_receiver {
-                    let photon_hit = self.renderer.process_photon(&photon, &mut cast_buffer, &mut volume_hit_buffer, &scene.volumes);
+        let mut film = film::Film::new(scene.camera.width(), scene.camera.height());
 
-                    println!("photon_hit = {:?}", photon_hit);
+        for worker_film in &worker_films {
+            film.merge(worker_film);
+        }
 
-                    let Some(photon_hit) = photon_hit else {
-                        continue;
-                    };
+        film
+    }
 
-                    hit_sender.send(photon_hit).expect("Photon Hit Send Failed");
+    // trace_photons emits photon_count photons from the lights, and as many from the emissive volumes, at
+    // full brightness and follows each of them and its bounces, accumulating every hit seen by the camera
+    // on a film
+    fn trace_photons(
+        &self,
+        scene: &scene::Scene,
+        emitters: &emitter::Emitters,
+        photon_count: usize,
+        mut rg: random_generator::RandomGenerator,
+        traced_photons: &atomic::AtomicUsize,
+    ) -> film::Film {
+        let mut film = film::Film::new(scene.camera.width(), scene.camera.height());
+        film.add_photons(photon_count);
+
+        let total_photon_count = self.settings.photon_count;
+        let progress_step = (total_photon_count / 100).max(1);
+
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let mut photons = Vec::<photon::Photon>::new();
+
+        for _ in 0..photon_count {
+            // every light emits a photon of its own, carrying all of its power
+            for light in &scene.lights {
+                let mut photon = photon::Photon::default();
+                self.renderer.process_light(light.as_ref(), &mut photon, 1.0, &mut rg);
+
+                photons.push(photon);
+            }
+
+            if !emitters.is_empty() {
+                let mut photon = photon::Photon::default();
+                let emission_hit = emitters.emit(&mut photon, 1.0, &mut rg);
+                let photon_hit = photon::PhotonHit {
+                    hit: emission_hit,
+                    photon,
+                };
+
+                // the camera sees the emitting surface where the photon leaves it
+                if self.renderer.process_hit(&photon_hit, &scene.camera, &scene.volumes) {
+                    if let Some(pc) = scene.camera.coord_for_point(&emission_hit.position) {
+                        film.add_sample(pc.x, pc.y, emitter::emitted_radiance(&photon));
+                    }
                 }
-            });
 
-            s.spawn(|_| {
-                let mut rg = random_generator::RandomGenerator::new();
+                photons.push(photon);
+            }
 
-                let mut cast_buffer = Vec::<hit::Hit>::new();
+            while let Some(photon) = photons.pop() {
+                let photon_hit = self.renderer.process_photon(&photon, &mut cast_buffer, &scene.volumes);
 
-                for photon_hit in hit_receiver {
-                    let photon = self.renderer.bounce_photon_hit(&photon_hit, &mut rg, &scene.material_library);
-                    if let Some(photon) = photon {
-                        photon_sender.send(photon).expect("Photon Send Failed");
-                    };
+                let Some(photon_hit) = photon_hit else {
+                    continue;
+                };
 
-                    let photon_hit_valid = self.renderer.process_hit(&photon_hit, &mut cast_buffer, &scene.camera, &scene.volumes);
-                    if photon_hit_valid {
-                        final_hit_sender.send(photon_hit).expect("Final Photon Hit Send Failed");
-                    };
+                if let Some(photon) = self.renderer.bounce_photon_hit(&photon_hit, &mut rg, &scene.material_library) {
+                    photons.push(photon);
                 }
-            });
-
-            s.spawn(|_| {
-                for photon_hit in final_hit_receiver {
-                    let result = self.renderer.process_final_hit(&photon_hit, &scene.camera, &scene.material_library);
 
-                    if let Some((pc, c)) = result {
-                        image.set_pixel(pc.x, pc.y, pixel::Pixel::from_color(&c));
+                if self.renderer.process_hit(&photon_hit, &scene.camera, &scene.volumes) {
+                    if let Some((pc, c)) = self.renderer.process_final_hit(&photon_hit, &scene.camera, &scene.material_library) {
+                        film.add_sample(pc.x, pc.y, c);
                     }
                 }
-            });
+            }
 
-            process_lights_handle.join().expect("Process Lights Failed");
-
-            s.spawn(|_| {
-                loop {
-                    if photon_receiver_checker.is_empty() && hit_receiver_checker.is_empty() && final_hit_receiver_checker.is_empty() {
-                        photon_receiver_checker.close();
-                        hit_receiver_checker.close();
-                        final_hit_receiver_checker.close();
-                        break;
-                    }
-                    thread::sleep(std::time::Duration::from_millis(1000));
-                }
-            });
-        });
-
-        result.unwrap();
-
-        let png_w = png_writer::PngWriter::new(scene.camera.width() as u32, scene.camera.height() as u32, path::Path::new("test.png"));
+            let traced = traced_photons.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+            if self.settings.report_progress && (traced.is_multiple_of(progress_step) || traced == total_photon_count) {
+                eprint!("\rTraced {}/{} photons", traced, total_photon_count);
+            }
+        }
 
-        png_w.write(&image);
+        film
     }
-}
\ No newline at end of file
+}
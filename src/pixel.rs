@@ -5,22 +5,47 @@ pub struct Pixel {
     pub red: u16,
     pub green: u16,
     pub blue: u16,
+    pub alpha: u16,
 }
 
 impl Pixel {
+    pub const MAX: u16 = u16::MAX;
+
+    // new creates an opaque pixel, channels use the full 16-bit range
     pub fn new(red: u16, green: u16, blue: u16) -> Self {
         Pixel {
             red,
             green,
             blue,
+            alpha: Pixel::MAX,
         }
     }
 
+    // from_color quantizes a display color in [0, 1], clamping anything outside of that range
     pub fn from_color(color: &color::Color) -> Self {
-        Pixel {
-            red: (color.red * 255.0) as u16,
-            green: (color.green * 255.0) as u16,
-            blue: (color.blue * 255.0) as u16,
-        }
+        Pixel::new(
+            Pixel::quantize(color.red),
+            Pixel::quantize(color.green),
+            Pixel::quantize(color.blue),
+        )
+    }
+
+    fn quantize(value: f64) -> u16 {
+        (value.clamp(0.0, 1.0) * Pixel::MAX as f64).round() as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_color() {
+        let pixel = Pixel::from_color(&color::Color::new(0.5, 2.0, -1.0));
+
+        assert_eq!(pixel.red, 32768);
+        assert_eq!(pixel.green, 65535);
+        assert_eq!(pixel.blue, 0);
+        assert_eq!(pixel.alpha, 65535);
     }
 }
@@ -21,7 +21,7 @@ impl Plane {
         vector3::Vector3::dot(&self.normal, point) > self.dot
     }
 
-    fn ray_intersects(&self, ray: &ray::Ray) -> Option<hit::Hit> {
+    pub fn ray_intersects(&self, ray: &ray::Ray) -> Option<hit::Hit> {
         let dot = vector3::Vector3::dot(&ray.direction, &self.normal);
 
         if dot.abs() < f64::EPSILON {
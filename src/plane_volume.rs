@@ -1,17 +1,55 @@
-use crate::{hit, plane, ray, vector3, volume};
+use crate::{bounds, hit, plane, quaternion, ray, uv, vector3, volume};
 
 pub struct PlaneVolumeStrategy {
     m_plane: plane::Plane,
+    // width along the local X axis and depth along the local Z axis, or None for an infinite plane
+    m_size: Option<(f64, f64)>,
 }
 
 // This was the old way that didn't work:
 // impl<T: volume::VolumeProtectedInterface> volume::VolumeStrategy<T> for PlaneVolumeStrategy {
 impl volume::VolumeStrategy for PlaneVolumeStrategy {
-    fn cast_transformed_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
-        None // TODO(cdelguercio): Is this usable like this or not?
+    fn cast_transformed_ray(&self, ray: &ray::Ray, _cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
+        let mut hit = self.m_plane.ray_intersects(ray)?;
+
+        // the texture coordinates of bounded planes span the plane, and repeat every unit on infinite ones
+        match self.m_size {
+            Some((width, depth)) => {
+                if hit.position.get_x().abs() > width / 2.0 || hit.position.get_z().abs() > depth / 2.0 {
+                    return None;
+                }
+
+                hit.uv = uv::Uv::new(hit.position.get_x() / width + 0.5, hit.position.get_z() / depth + 0.5);
+            },
+            None => hit.uv = uv::Uv::new(hit.position.get_x(), hit.position.get_z()),
+        }
+
+        // Planes are two-sided, so the normal always faces the incoming ray
+        if vector3::Vector3::dot(&ray.direction, &hit.normal) > 0.0 {
+            hit.normal = -hit.normal;
+            hit.geometric_normal = -hit.geometric_normal;
+        }
+
+        Some(hit)
+    }
+
+    fn transformed_ray_occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.cast_transformed_ray(ray, &mut Vec::new())
+            .is_some_and(|hit| hit.distance > 0.0 && hit.distance < max_distance)
+    }
+
+    fn get_transformed_bounds(&self) -> Option<bounds::Bounds> {
+        let (width, depth) = self.m_size?;
+
+        Some(bounds::Bounds::from_vectors(
+            vector3::Vector3::new(-width / 2.0, 0.0, -depth / 2.0),
+            vector3::Vector3::new(width / 2.0, 0.0, depth / 2.0),
+        ))
     }
 }
 
+// PlaneVolume is a plane through its position with a local +Y normal, so an unrotated PlaneVolume is
+// a ground plane
 pub struct PlaneVolume {
     volume: volume::Volume<PlaneVolumeStrategy>
 }
@@ -21,9 +59,27 @@ impl PlaneVolume {
         PlaneVolume {
             volume: volume::Volume::<PlaneVolumeStrategy>::new(material_index, PlaneVolumeStrategy {
                 m_plane: plane::Plane::new(vector3::Vector3::default(), vector3::Vector3::new(0.0, 0.0, 1.0), vector3::Vector3::new(1.0, 0.0, 0.0)),
+                m_size: None,
             }),
         }
     }
+
+    pub fn set_position(&mut self, position: vector3::Vector3) {
+        self.volume.object.transform.position = position;
+    }
+
+    pub fn set_rotation(&mut self, rotation: quaternion::Quaternion) {
+        self.volume.object.transform.rotation = rotation;
+    }
+
+    // set_size bounds the plane to a width x depth rectangle centered on its position
+    pub fn set_size(&mut self, width: f64, depth: f64) {
+        self.volume.specialization.m_size = Some((width, depth));
+    }
+
+    pub fn set_infinite(&mut self) {
+        self.volume.specialization.m_size = None;
+    }
 }
 
 impl volume::VolumePublicInterface for PlaneVolume {
@@ -38,4 +94,148 @@ impl volume::VolumePublicInterface for PlaneVolume {
     fn cast_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
         self.volume.cast_ray(ray, cast_buffer)
     }
+
+    fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.volume.occluded(ray, max_distance)
+    }
+
+    fn get_bounds(&self) -> Option<bounds::Bounds> {
+        self.volume.get_bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f64::consts;
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::volume::VolumePublicInterface;
+
+    #[test]
+    fn cast_ray_infinite() {
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let mut plane_volume = PlaneVolume::new(3);
+        plane_volume.set_position(vector3::Vector3::new(0.0, -1.0, 0.0));
+
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(100.0, 1.0, 50.0),
+            vector3::Vector3::new(0.0, -1.0, 0.0),
+        );
+
+        let hit = plane_volume.cast_ray(&ray, &mut cast_buffer).unwrap();
+
+        assert_approx_eq!(hit.distance, 2.0, 1e-6f64);
+        assert_approx_eq!(hit.position.get_x(), 100.0, 1e-6f64);
+        assert_approx_eq!(hit.position.get_y(), -1.0, 1e-6f64);
+        assert_approx_eq!(hit.position.get_z(), 50.0, 1e-6f64);
+        assert_approx_eq!(hit.normal.get_y(), 1.0, 1e-6f64);
+        assert_eq!(hit.material_index, 3);
+
+        // rays parallel to or pointing away from the plane miss
+        let parallel_ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 1.0, 0.0),
+            vector3::Vector3::new(1.0, 0.0, 0.0),
+        );
+        let away_ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 1.0, 0.0),
+            vector3::Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        assert!(plane_volume.cast_ray(&parallel_ray, &mut cast_buffer).is_none());
+        assert!(plane_volume.cast_ray(&away_ray, &mut cast_buffer).is_none());
+    }
+
+    #[test]
+    fn cast_ray_from_below() {
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let plane_volume = PlaneVolume::new(0);
+
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, -2.0, 0.0),
+            vector3::Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let hit = plane_volume.cast_ray(&ray, &mut cast_buffer).unwrap();
+
+        assert_approx_eq!(hit.distance, 2.0, 1e-6f64);
+        assert_approx_eq!(hit.normal.get_y(), -1.0, 1e-6f64);
+    }
+
+    #[test]
+    fn cast_ray_bounded() {
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let mut plane_volume = PlaneVolume::new(0);
+        plane_volume.set_size(2.0, 4.0);
+
+        let inside_ray = ray::Ray::new(
+            vector3::Vector3::new(0.9, 1.0, 1.9),
+            vector3::Vector3::new(0.0, -1.0, 0.0),
+        );
+        let outside_width_ray = ray::Ray::new(
+            vector3::Vector3::new(1.1, 1.0, 0.0),
+            vector3::Vector3::new(0.0, -1.0, 0.0),
+        );
+        let outside_depth_ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 1.0, 2.1),
+            vector3::Vector3::new(0.0, -1.0, 0.0),
+        );
+
+        assert!(plane_volume.cast_ray(&inside_ray, &mut cast_buffer).is_some());
+        assert!(plane_volume.cast_ray(&outside_width_ray, &mut cast_buffer).is_none());
+        assert!(plane_volume.cast_ray(&outside_depth_ray, &mut cast_buffer).is_none());
+
+        plane_volume.set_infinite();
+
+        assert!(plane_volume.cast_ray(&outside_width_ray, &mut cast_buffer).is_some());
+    }
+
+    #[test]
+    fn cast_ray_rotated() {
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let mut plane_volume = PlaneVolume::new(0);
+        plane_volume.set_size(2.0, 2.0);
+        plane_volume.set_position(vector3::Vector3::new(0.0, 0.0, 5.0));
+        // rotate the ground plane into a wall facing the origin
+        plane_volume.set_rotation(quaternion::Quaternion::from_axis_angle(&vector3::UNIT_X, -consts::FRAC_PI_2));
+
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.5, 0.5, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+        let miss_ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 1.5, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+
+        let hit = plane_volume.cast_ray(&ray, &mut cast_buffer).unwrap();
+
+        assert_approx_eq!(hit.distance, 5.0, 1e-6f64);
+        assert_approx_eq!(hit.position.get_x(), 0.5, 1e-6f64);
+        assert_approx_eq!(hit.position.get_y(), 0.5, 1e-6f64);
+        assert_approx_eq!(hit.position.get_z(), 5.0, 1e-6f64);
+        assert_approx_eq!(hit.normal.get_z(), -1.0, 1e-6f64);
+
+        // the texture coordinates span the plane, with the local X and Z axes as U and V
+        assert_approx_eq!(hit.uv.u, 0.75, 1e-6f64);
+        assert_approx_eq!(hit.uv.v, 0.75, 1e-6f64);
+
+        assert!(plane_volume.cast_ray(&miss_ray, &mut cast_buffer).is_none());
+    }
+
+    #[test]
+    fn occluded() {
+        let mut plane_volume = PlaneVolume::new(0);
+        plane_volume.set_position(vector3::Vector3::new(0.0, -1.0, 0.0));
+
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 1.0, 0.0),
+            vector3::Vector3::new(0.0, -1.0, 0.0),
+        );
+
+        assert!(plane_volume.occluded(&ray, 3.0));
+        assert!(!plane_volume.occluded(&ray, 2.0));
+
+    }
 }
@@ -4,27 +4,69 @@ use std::io;
 
 use png;
 
-use crate::image;
+use crate::{film, image, image_writer, tone_mapping};
 
-pub struct PngWriter<'a> {
-    encoder: png::Encoder<'a, io::BufWriter<fs::File>>,
+pub struct PngWriter {
+    m_path: path::PathBuf,
+    m_tone_mapper: tone_mapping::ToneMapper,
+    m_bit_depth: image::BitDepth,
+    // write an alpha channel, which marks the pixels no photon reached as transparent
+    m_alpha: bool,
+    // keyword and text of the tEXt chunks
+    m_text: Vec<(String, String)>,
 }
 
-impl PngWriter<'static> {
-    pub fn new(width: u32, height: u32, file: &path::Path) -> Self {
-        let file = fs::File::create(file).unwrap();
-        let mut writer = io::BufWriter::new(file);
-        let mut encoder = png::Encoder::new(writer, width, height);
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
+impl PngWriter {
+    pub fn new(file: &path::Path, tone_mapper: tone_mapping::ToneMapper) -> Self {
         PngWriter {
-            encoder,
+            m_path: file.to_path_buf(),
+            m_tone_mapper: tone_mapper,
+            m_bit_depth: image::BitDepth::Eight,
+            m_alpha: false,
+            m_text: Vec::new(),
         }
     }
 
-    pub fn write(self, data: &image::Image) {
-        let mut writer = self.encoder.write_header().unwrap();
-        writer.write_image_data(&data.unpack()).unwrap();
+    pub fn set_bit_depth(&mut self, bit_depth: image::BitDepth) {
+        self.m_bit_depth = bit_depth;
+    }
+
+    pub fn set_alpha(&mut self, alpha: bool) {
+        self.m_alpha = alpha;
+    }
+
+    // write_image encodes an image that is already sRGB encoded, which is recorded in the sRGB and gAMA chunks
+    pub fn write_image(&self, data: &image::Image) -> Result<(), image_writer::ImageWriterError> {
+        let file = fs::File::create(&self.m_path)
+            .map_err(|e| image_writer::ImageWriterError::Io(self.m_path.clone(), e))?;
+        let writer = io::BufWriter::new(file);
+        let mut encoder = png::Encoder::new(writer, data.get_width() as u32, data.get_height() as u32);
+        encoder.set_color(if self.m_alpha { png::ColorType::Rgba } else { png::ColorType::Rgb });
+        encoder.set_depth(match self.m_bit_depth {
+            image::BitDepth::Eight => png::BitDepth::Eight,
+            image::BitDepth::Sixteen => png::BitDepth::Sixteen,
+        });
+        encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+
+        for (keyword, text) in &self.m_text {
+            encoder.add_text_chunk(keyword.clone(), text.clone())
+                .map_err(|e| image_writer::ImageWriterError::Png(self.m_path.clone(), e))?;
+        }
+
+        let mut writer = encoder.write_header()
+            .map_err(|e| image_writer::ImageWriterError::Png(self.m_path.clone(), e))?;
+        writer.write_image_data(&data.unpack_with(self.m_bit_depth, self.m_alpha))
+            .map_err(|e| image_writer::ImageWriterError::Png(self.m_path.clone(), e))
+    }
+}
+
+impl image_writer::ImageWriter for PngWriter {
+    fn write(&self, film: &film::Film) -> Result<(), image_writer::ImageWriterError> {
+        self.write_image(&self.m_tone_mapper.to_image(film))
+    }
+
+    fn add_metadata(&mut self, keyword: &str, text: &str) {
+        self.m_text.push((keyword.to_string(), text.to_string()));
     }
 }
 
@@ -32,13 +74,58 @@ impl PngWriter<'static> {
 mod tests {
     use super::*;
 
-    use crate::pixel;
+    use std::env;
+
+    use crate::{color, pixel};
+    use crate::image_writer::ImageWriter;
 
     #[test]
     fn test_write() {
         let mut image = image::Image::new(2, 2);
-        image.set_pixel(0, 0, pixel::Pixel::new(255, 0, 0));
-        let writer = PngWriter::new(2, 2, &path::Path::new("test.png"));
-        writer.write(&image);
+        image.set_pixel(0, 0, pixel::Pixel::new(pixel::Pixel::MAX, 0, 0));
+        let writer = PngWriter::new(path::Path::new("test.png"), tone_mapping::ToneMapper::default());
+        writer.write_image(&image).unwrap();
+    }
+
+    #[test]
+    fn write_sixteen_bit_with_alpha_and_text() {
+        let path = env::temp_dir().join("tdi_ray_tracer_png_writer_sixteen_bit.png");
+
+        let mut film = film::Film::new(2, 1);
+        film.add_photons(1);
+        film.add_sample(0, 0, color::Color::new(0.5, 1.0, 0.0));
+
+        let mut writer = PngWriter::new(&path, tone_mapping::ToneMapper::default());
+        writer.set_bit_depth(image::BitDepth::Sixteen);
+        writer.set_alpha(true);
+        writer.add_metadata("Photons", "1");
+        writer.write(&film).unwrap();
+
+        let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+
+        let info = reader.info();
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        assert_eq!(info.srgb, Some(png::SrgbRenderingIntent::Perceptual));
+        assert!(info.source_gamma.is_some());
+        assert_eq!(info.uncompressed_latin1_text[0].keyword, "Photons");
+        assert_eq!(info.uncompressed_latin1_text[0].text, "1");
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer).unwrap();
+
+        // the covered pixel is opaque with a full green channel, the other one is transparent
+        assert_eq!(&buffer[2..8], &[0xff, 0xff, 0, 0, 0xff, 0xff]);
+        assert_eq!(&buffer[14..16], &[0, 0]);
+    }
+
+    #[test]
+    fn write_to_missing_directory() {
+        let path = env::temp_dir().join("tdi_ray_tracer_missing_directory").join("image.png");
+
+        let writer = PngWriter::new(&path, tone_mapping::ToneMapper::default());
+
+        assert!(matches!(writer.write_image(&image::Image::new(1, 1)), Err(image_writer::ImageWriterError::Io(_, _))));
     }
 }
@@ -0,0 +1,117 @@
+use std::f64::consts;
+
+use crate::{color, light, photon, quaternion, random_generator, ray, vector3};
+
+pub use crate::light::LightPublicInterface;
+
+struct PointLightStrategy {}
+
+impl light::LightStrategy for PointLightStrategy {
+    fn emit(&self, base: &light::Light<PointLightStrategy>, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator) {
+        let direction = vector3::Vector3::random_sphere(random_generator, 1.0);
+
+        photon.ray = ray::Ray::new(base.object.position(), direction);
+        photon.color = base.m_color * base.m_lumens * photon_brightness;
+        photon.bounces = 0;
+    }
+
+    // the brightness is the intensity in every direction, per unit solid angle
+    fn lumens(&self, brightness: f64, _area: f64) -> f64 {
+        4.0 * consts::PI * brightness
+    }
+}
+
+// PointLight emits the same amount of light in every direction from a single point, like a bare bulb
+pub struct PointLight {
+    light: light::Light<PointLightStrategy>,
+}
+
+impl PointLight {
+    pub fn new() -> PointLight {
+        PointLight {
+            light: light::Light::<PointLightStrategy>::new(PointLightStrategy {}),
+        }
+    }
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        PointLight::new()
+    }
+}
+
+impl light::LightPublicInterface for PointLight {
+    fn get_color(&self) -> color::Color {
+        self.light.get_color()
+    }
+
+    fn set_color(&mut self, color: color::Color) {
+        self.light.set_color(color)
+    }
+
+    fn get_brightness(&self) -> f64 {
+        self.light.get_brightness()
+    }
+
+    fn set_brightness(&mut self, brightness: f64) {
+        self.light.set_brightness(brightness)
+    }
+
+    fn emit(&self, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator) {
+        self.light.emit(photon, photon_brightness, random_generator)
+    }
+
+    fn set_position(&mut self, position: vector3::Vector3) {
+        self.light.set_position(position)
+    }
+
+    fn set_rotation(&mut self, rotation: quaternion::Quaternion) {
+        self.light.set_rotation(rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn lumens() {
+        let mut light = PointLight::new();
+        light.set_brightness(10.0);
+
+        assert_approx_eq!(light.light.m_lumens, 40.0 * consts::PI, 1e-9f64);
+    }
+
+    #[test]
+    fn emit_isotropic() {
+        let mut light = PointLight::new();
+        light.set_brightness(1.0);
+        light.set_position(vector3::Vector3::new(1.0, 2.0, 3.0));
+
+        let mut rg = random_generator::RandomGenerator::from_seed(37);
+        let mut photon = photon::Photon::default();
+        let samples = 100_000;
+        let mut mean = vector3::Vector3::default();
+        let mut within_60 = 0;
+
+        for _ in 0..samples {
+            light.emit(&mut photon, 0.5, &mut rg);
+
+            assert_eq!(photon.ray.origin, vector3::Vector3::new(1.0, 2.0, 3.0));
+            assert_approx_eq!(photon.ray.direction.norm(), 1.0, 1e-9f64);
+            assert_approx_eq!(photon.color.red, 2.0 * consts::PI, 1e-9f64);
+
+            mean = mean + photon.ray.direction / samples as f64;
+            if photon.ray.direction.get_x() > 0.5 {
+                within_60 += 1;
+            }
+        }
+
+        // directions are uniform over the sphere, so they average out and a cap within 60 degrees of any axis
+        // holds a quarter of them
+        assert!(mean.norm() < 0.01, "{:?}", mean);
+        assert_approx_eq!(within_60 as f64 / samples as f64, 0.25, 0.01f64);
+    }
+}
@@ -1,21 +1,46 @@
-use rand::Rng;
-use rand::rngs;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
 
+#[derive(Clone)]
 pub struct RandomGenerator {
-    rng: rngs::ThreadRng,
+    rng: Xoshiro256PlusPlus,
 }
 
 impl RandomGenerator {
+    // new seeds the generator from the thread RNG, so its sequence differs on every run
     pub fn new() -> Self {
+        RandomGenerator::from_seed(rand::thread_rng().gen())
+    }
+
+    pub fn from_seed(seed: u64) -> Self {
         RandomGenerator {
-            rng: rand::thread_rng(),
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
         }
     }
+
+    // streams derives count non-overlapping generators from a master seed, each one 2^128 values
+    // ahead of the previous, so stream i is the same for a given seed regardless of count
+    pub fn streams(seed: u64, count: usize) -> Vec<RandomGenerator> {
+        let mut master = RandomGenerator::from_seed(seed);
+
+        (0..count).map(|_| {
+            let stream = master.clone();
+            master.rng.jump();
+            stream
+        }).collect()
+    }
+
     pub fn value(&mut self, scale: f64) -> f64 {
         self.rng.gen_range(0.0..scale)
     }
 }
 
+impl Default for RandomGenerator {
+    fn default() -> Self {
+        RandomGenerator::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -26,4 +51,31 @@ mod tests {
         let v = g.value(1.0);
         assert!(v >= 0.0 && v < 1.0);
     }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = RandomGenerator::from_seed(42);
+        let mut b = RandomGenerator::from_seed(42);
+        let mut c = RandomGenerator::from_seed(43);
+
+        let a_values: Vec<f64> = (0..16).map(|_| a.value(1.0)).collect();
+        let b_values: Vec<f64> = (0..16).map(|_| b.value(1.0)).collect();
+        let c_values: Vec<f64> = (0..16).map(|_| c.value(1.0)).collect();
+
+        assert_eq!(a_values, b_values);
+        assert_ne!(a_values, c_values);
+    }
+
+    #[test]
+    fn streams_are_independent_and_stable() {
+        let mut two = RandomGenerator::streams(7, 2);
+        let mut three = RandomGenerator::streams(7, 3);
+
+        let first: Vec<f64> = (0..16).map(|_| two[0].value(1.0)).collect();
+        let second: Vec<f64> = (0..16).map(|_| two[1].value(1.0)).collect();
+
+        assert_ne!(first, second);
+        assert_eq!(first, (0..16).map(|_| three[0].value(1.0)).collect::<Vec<f64>>());
+        assert_eq!(second, (0..16).map(|_| three[1].value(1.0)).collect::<Vec<f64>>());
+    }
 }
@@ -0,0 +1,23 @@
+pub struct RenderSettings {
+    pub photon_count: usize,
+    pub bounce_threshold: u32,
+    // worker threads that each trace an equal share of the photons
+    pub thread_count: usize,
+    // master seed of the random streams, so renders with the same seed and thread count are identical,
+    // or None to draw a new seed for every render
+    pub seed: Option<u64>,
+    // print the number of traced photons to stderr while rendering
+    pub report_progress: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            photon_count: 10000,
+            bounce_threshold: 1,
+            thread_count: 1,
+            seed: None,
+            report_progress: false,
+        }
+    }
+}
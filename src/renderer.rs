@@ -1,4 +1,4 @@
-use crate::{camera, color, hit, library, light, material, photon, pixel_coords, random_generator, ray, vector3, volume};
+use crate::{camera, color, hit, library, light, material, photon, pixel_coords, random_generator, ray, volume_tree};
 
 const SELF_HIT_THRESHOLD: f64 = f64::EPSILON;
 
@@ -21,8 +21,13 @@ impl Renderer {
         }
     }
 
+    pub fn set_bounce_threshold(&mut self, bounce_threshold: u32) {
+        self.m_bounce_threshold = bounce_threshold;
+    }
+
     // process_light generates a photon from a particular light source
-    // the photon_brightness should be derived from 1.0 / total_number_of_photons from the light source
+    // the photon_brightness scales the lumens of the light carried by the photon, the Pipeline emits at
+    // full brightness and normalizes by the number of emitted photons on the film instead
     // TODO(cdelguercio): maybe we would rather return a photon::Photon instead of passing in a mutable reference
     pub fn process_light(
         &self,
@@ -34,47 +39,23 @@ impl Renderer {
         light.emit(photon, photon_brightness, random_generator);
     }
 
-    // process_photon
+    // process_photon returns the nearest hit of the photon's ray with the volumes
     pub fn process_photon(
         &self,
         photon: &photon::Photon,
         cast_buffer: &mut Vec<hit::Hit>,
-        volume_hit_buffer: &mut Vec::<photon::PhotonHit>,
-        volumes: &Vec<Box<dyn volume::VolumePublicInterface>>,
+        volumes: &volume_tree::VolumeTree,
     ) -> Option<photon::PhotonHit> {
         if photon.color.brightness() < f64::EPSILON {
             return None;
         }
 
-        volume_hit_buffer.clear();
-
-        for volume in volumes {
-            let hit = volume.cast_ray(&photon.ray, cast_buffer);
-
-            match hit {
-                Some(hit) => {
-                    volume_hit_buffer.push(photon::PhotonHit {
-                        hit,
-                        photon: *photon,
-                    });
-                },
-                None => {},
-            }
-        }
+        let hit = volumes.cast_ray(&photon.ray, SELF_HIT_THRESHOLD, cast_buffer)?;
 
-        if volume_hit_buffer.is_empty() {
-            return None;
-        }
-
-        // return the shortest valid hit from the photon
-        return if let Some(hit) = volume_hit_buffer
-            .iter()
-            .filter(|photon_hit| photon_hit.hit.distance > SELF_HIT_THRESHOLD)
-            .reduce(|a, b| return if a.hit.distance < b.hit.distance { a } else { b }) {
-            Some(*hit)
-        } else {
-            None
-        };
+        Some(photon::PhotonHit {
+            hit,
+            photon: *photon,
+        })
     }
 
     // bounce_photon_hit bounces a photon hit, and then optionally generates another photon
@@ -97,9 +78,8 @@ impl Renderer {
     pub fn process_hit(
         &self,
         photon_hit: &photon::PhotonHit,
-        cast_buffer: &mut Vec<hit::Hit>,
         camera: &camera::Camera,
-        volumes: &Vec<Box<dyn volume::VolumePublicInterface>>,
+        volumes: &volume_tree::VolumeTree,
     ) -> bool {
         let camera_position = camera.position();
         // let camera_normal = camera.forward(); // TODO(cdelguercio): this is in the original C++ code, but is never used
@@ -112,10 +92,10 @@ impl Renderer {
         };
 
         let pixel_direction = camera.pixel_direction(&coord);
-        let dot = vector3::Vector3::dot(&pixel_direction, &photon_hit.hit.normal);
 
-        // Not facing the pixel, skip
-        if dot >= 0.0 {
+        // Not facing the pixel, skip. Smooth shading doesn't change which side of the surface the camera is on,
+        // so this is decided by the geometric normal.
+        if !photon_hit.hit.is_above(&-pixel_direction) {
             return false;
         }
 
@@ -126,29 +106,11 @@ impl Renderer {
             return false;
         }
 
-        let ray = ray::Ray::new(photon_hit.hit.position, path / camera_distance);
-
-        let mut closest_hit: Option<hit::Hit> = None;
-
-        // Do any volumes obscure this hit?
-        for volume in volumes {
-            let hit = volume.cast_ray(&ray, cast_buffer);
-
-            let Some(hit) = hit else {
-                continue;
-            };
-
-            if hit.distance > SELF_HIT_THRESHOLD && (closest_hit.is_none() || hit.distance < closest_hit.unwrap().distance) {
-                closest_hit = Some(hit);
-            }
-        }
-
-        // If no object was hit, or the closest hit object is behind the camera, the hit is valid
-        if closest_hit.is_none() || closest_hit.unwrap().distance > camera_distance {
-            return true;
-        }
+        let direction = path / camera_distance;
+        let ray = ray::Ray::new(photon_hit.hit.offset_origin(&direction), direction);
 
-        false
+        // The hit is valid if no volume obscures it between the hit and the camera
+        !volumes.occluded(&ray, camera_distance)
     }
 
     // process_final_hit generates a color at a particular pixel coordinate from a photon hit
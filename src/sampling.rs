@@ -0,0 +1,121 @@
+use std::f64::consts;
+
+use crate::{random_generator, vector3};
+
+// orthonormal_basis returns two unit tangents that together with the unit normal form a right handed
+// basis, without a branch on which axis the normal is closest to (Duff et al., "Building an
+// Orthonormal Basis, Revisited")
+pub fn orthonormal_basis(normal: &vector3::Vector3) -> (vector3::Vector3, vector3::Vector3) {
+    let sign = 1.0f64.copysign(normal.get_z());
+    let a = -1.0 / (sign + normal.get_z());
+    let b = normal.get_x() * normal.get_y() * a;
+
+    let tangent = vector3::Vector3::new(
+        1.0 + sign * normal.get_x() * normal.get_x() * a,
+        sign * b,
+        -sign * normal.get_x(),
+    );
+    let bitangent = vector3::Vector3::new(
+        b,
+        sign + normal.get_y() * normal.get_y() * a,
+        -normal.get_y(),
+    );
+
+    (tangent, bitangent)
+}
+
+// to_world turns a direction given in the frame of a unit normal, where the normal is the z axis, into
+// world space
+pub fn to_world(local: &vector3::Vector3, normal: &vector3::Vector3) -> vector3::Vector3 {
+    let (tangent, bitangent) = orthonormal_basis(normal);
+
+    tangent * local.get_x() + bitangent * local.get_y() + *normal * local.get_z()
+}
+
+// to_local is the inverse of to_world
+pub fn to_local(world: &vector3::Vector3, normal: &vector3::Vector3) -> vector3::Vector3 {
+    let (tangent, bitangent) = orthonormal_basis(normal);
+
+    vector3::Vector3::new(
+        vector3::Vector3::dot(world, &tangent),
+        vector3::Vector3::dot(world, &bitangent),
+        vector3::Vector3::dot(world, normal),
+    )
+}
+
+// cosine_hemisphere returns a direction in the hemisphere around the z axis, picked with a density
+// proportional to the cosine of its angle to the axis by projecting a uniform point on the unit disk up
+// onto the hemisphere
+pub fn cosine_hemisphere(random_generator: &mut random_generator::RandomGenerator) -> vector3::Vector3 {
+    let r = random_generator.value(1.0).sqrt();
+    let phi = random_generator.value(2.0 * consts::PI);
+
+    vector3::Vector3::new(r * phi.cos(), r * phi.sin(), (1.0 - r * r).max(0.0).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+
+    #[test]
+    fn orthonormal_basis_is_orthonormal() {
+        let mut rg = random_generator::RandomGenerator::from_seed(3);
+
+        let mut normals: Vec<vector3::Vector3> = (0..100).map(|_| vector3::Vector3::random_sphere(&mut rg, 1.0)).collect();
+        normals.extend([vector3::UNIT_Z, -vector3::UNIT_Z, vector3::UNIT_X]);
+
+        for normal in normals {
+            let (tangent, bitangent) = orthonormal_basis(&normal);
+
+            assert_approx_eq!(tangent.norm(), 1.0, 1e-9f64);
+            assert_approx_eq!(bitangent.norm(), 1.0, 1e-9f64);
+            assert_approx_eq!(vector3::Vector3::dot(&tangent, &normal), 0.0, 1e-9f64);
+            assert_approx_eq!(vector3::Vector3::dot(&bitangent, &normal), 0.0, 1e-9f64);
+            assert_approx_eq!(vector3::Vector3::dot(&tangent, &bitangent), 0.0, 1e-9f64);
+            assert_approx_eq!(vector3::Vector3::dot(&vector3::Vector3::cross(&tangent, &bitangent), &normal), 1.0, 1e-9f64);
+        }
+    }
+
+    #[test]
+    fn to_world_maps_z_to_normal() {
+        let normal = vector3::Vector3::new(1.0, 2.0, -2.0) / 3.0;
+        let world = to_world(&vector3::UNIT_Z, &normal);
+
+        assert_approx_eq!(world.get_x(), normal.get_x(), 1e-9f64);
+        assert_approx_eq!(world.get_y(), normal.get_y(), 1e-9f64);
+        assert_approx_eq!(world.get_z(), normal.get_z(), 1e-9f64);
+
+        let local = vector3::Vector3::new(0.6, 0.0, 0.8);
+        let round_trip = to_local(&to_world(&local, &normal), &normal);
+        assert_approx_eq!(round_trip.get_x(), 0.6, 1e-9f64);
+        assert_approx_eq!(round_trip.get_y(), 0.0, 1e-9f64);
+        assert_approx_eq!(round_trip.get_z(), 0.8, 1e-9f64);
+    }
+
+    #[test]
+    fn cosine_hemisphere_distribution() {
+        let mut rg = random_generator::RandomGenerator::from_seed(3);
+        let samples = 100_000;
+
+        let directions: Vec<vector3::Vector3> = (0..samples).map(|_| cosine_hemisphere(&mut rg)).collect();
+
+        for direction in &directions {
+            assert_approx_eq!(direction.norm(), 1.0, 1e-9f64);
+            assert!(direction.get_z() >= 0.0);
+        }
+
+        // the fraction of directions within an angle theta of the axis is sin^2(theta), so the cosine of the
+        // angle averages 2/3 and half of the directions are within 45 degrees
+        let mean_cos = directions.iter().map(|d| d.get_z()).sum::<f64>() / samples as f64;
+        assert_approx_eq!(mean_cos, 2.0 / 3.0, 0.005f64);
+
+        let within_45 = directions.iter().filter(|d| d.get_z() > consts::FRAC_1_SQRT_2).count() as f64 / samples as f64;
+        assert_approx_eq!(within_45, 0.5, 0.01f64);
+
+        let mean_x = directions.iter().map(|d| d.get_x()).sum::<f64>() / samples as f64;
+        assert_approx_eq!(mean_x, 0.0, 0.01f64);
+    }
+}
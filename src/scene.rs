@@ -1,12 +1,18 @@
-use crate::{camera, volume, light, library, material};
+use crate::{camera, volume_tree, light, library, material, ray};
 
 pub struct Scene {
     pub camera: camera::Camera,
-    pub volumes: Vec<Box<dyn volume::VolumePublicInterface>>,
+    pub volumes: volume_tree::VolumeTree,
     pub lights: Vec<Box<dyn light::LightPublicInterface>>,
     pub material_library: library::Library<Box<dyn material::Material>>,
 }
 
+impl Scene {
+    // occluded returns whether any volume of the scene is hit at a distance in (0, max_distance) along the ray
+    pub fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.volumes.occluded(ray, max_distance)
+    }
+}
 
 unsafe impl Sync for Scene {}
 
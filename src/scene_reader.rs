@@ -0,0 +1,779 @@
+// Scenes are described in TOML. Every table except [camera] is optional:
+//
+//   [camera]
+//   width = 100
+//   height = 100
+//   vertical_fov = 90.0           # degrees
+//   position = [0.0, 0.0, 0.0]
+//   rotation = [0.0, 0.0, 0.0]    # roll, pitch, yaw in degrees
+//
+//   [[materials]]                 # added to the built in material library
+//   name = "Orange"
+//   type = "diffuse"              # optional, one of diffuse, mirror, glossy, glass or emissive
+//   color = [1.0, 0.5, 0.0]       # optional, defaults to white, the reflectance at normal incidence of
+//                                 # mirror and glossy materials, and the color white light fades to after
+//                                 # passing through a distance of 1 of glass, or the emitted color
+//   mode = "lambertian"           # optional, diffuse only, reflection_cone or the energy conserving lambertian
+//   texture = "textures/wood.png" # optional, diffuse only, multiplies the color, relative to the scene file
+//   wrap = "repeat"               # optional, texture only, one of repeat, mirror or clamp
+//   roughness = 0.5               # optional, glossy only, from 0 for a mirror to 1
+//   ior = 1.5                     # optional, glass only, the index of refraction
+//   power = 100.0                 # optional, emissive only, the power emitted per unit area, defaults to 1
+//
+//   [[parallel_lights]]
+//   radius = 1.0
+//   brightness = 1000.0
+//   color = [1.0, 1.0, 1.0]
+//   position = [0.25, 0.25, 1.5]
+//
+//   [[point_lights]]              # emits in every direction
+//   brightness = 100.0            # intensity per unit solid angle
+//   color = [1.0, 1.0, 1.0]
+//
+//   [[spot_lights]]               # emits into a cone around the forward direction
+//   brightness = 100.0            # intensity per unit solid angle in the forward direction
+//   color = [1.0, 1.0, 1.0]
+//   angle = 45.0                  # optional, degrees between the forward direction and the edge of the cone
+//   falloff = 0.0                 # optional, exponent of the cosine the intensity fades towards the edge with
+//
+//   [[area_lights]]               # emits from the side of a surface facing the forward direction
+//   brightness = 100.0            # power per unit area
+//   color = [1.0, 1.0, 1.0]
+//   size = [1.0, 1.0]             # optional, width and height of a rectangle
+//   radius = 0.5                  # optional, instead of size for a disk
+//
+//   [[meshes]]                    # inline triangles, only hit from the front unless their material is glass
+//   material = "Cyan"
+//   triangles = [[[-1.0, -1.0, 3.0], [-1.0, 1.0, 3.0], [1.0, 0.0, 3.0]]]
+//   uvs = [[[0.0, 0.0], [0.0, 1.0], [1.0, 0.5]]]     # optional, texture coordinates of every triangle
+//
+//   [[obj_files]]                 # path is relative to the scene file
+//   path = "models/teapot.obj"
+//   material = "White"            # optional, overrides the MTL materials
+//   cache = true                  # optional, saves the built trees to models/teapot.obj.bvh and reuses
+//                                 # them while the OBJ file is unchanged
+//
+//   [[planes]]
+//   material = "White"
+//   size = [10.0, 10.0]           # optional width and depth, planes are infinite without it
+//
+// Lights and volumes all accept position and rotation.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+
+use serde::Deserialize;
+
+use crate::{angle, area_light, camera, color, diffuse_material, emissive_material, glass_material, glossy_material, library, light, material, mesh,
+            mesh_volume, mirror_material, obj_reader, parallel_light, plane_volume, point_light, quaternion, scene, spot_light,
+            texture, triangle, uv, vector3, volume, volume_tree};
+
+use crate::light::LightPublicInterface;
+use crate::volume::VolumePublicInterface;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: CameraDescription,
+    #[serde(default)]
+    materials: Vec<MaterialDescription>,
+    #[serde(default)]
+    parallel_lights: Vec<ParallelLightDescription>,
+    #[serde(default)]
+    point_lights: Vec<PointLightDescription>,
+    #[serde(default)]
+    spot_lights: Vec<SpotLightDescription>,
+    #[serde(default)]
+    area_lights: Vec<AreaLightDescription>,
+    #[serde(default)]
+    meshes: Vec<MeshDescription>,
+    #[serde(default)]
+    obj_files: Vec<ObjFileDescription>,
+    #[serde(default)]
+    planes: Vec<PlaneDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    width: usize,
+    height: usize,
+    vertical_fov: f64,
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDescription {
+    name: toml::Spanned<String>,
+    #[serde(rename = "type", default)]
+    kind: MaterialKind,
+    #[serde(default = "white")]
+    color: [f64; 3],
+    mode: Option<diffuse_material::DiffuseMode>,
+    texture: Option<String>,
+    wrap: Option<texture::WrapMode>,
+    roughness: Option<f64>,
+    ior: Option<f64>,
+    power: Option<f64>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MaterialKind {
+    #[default]
+    Diffuse,
+    Mirror,
+    Glossy,
+    Glass,
+    Emissive,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParallelLightDescription {
+    #[serde(default)]
+    radius: f64,
+    brightness: f64,
+    #[serde(default = "white")]
+    color: [f64; 3],
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PointLightDescription {
+    brightness: f64,
+    #[serde(default = "white")]
+    color: [f64; 3],
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpotLightDescription {
+    brightness: f64,
+    #[serde(default = "white")]
+    color: [f64; 3],
+    #[serde(default = "spot_angle")]
+    angle: f64,
+    #[serde(default)]
+    falloff: f64,
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AreaLightDescription {
+    brightness: f64,
+    #[serde(default = "white")]
+    color: [f64; 3],
+    size: Option<[f64; 2]>,
+    radius: Option<f64>,
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDescription {
+    #[serde(default)]
+    name: String,
+    material: toml::Spanned<String>,
+    triangles: Vec<[[f64; 3]; 3]>,
+    #[serde(default)]
+    uvs: Vec<[[f64; 2]; 3]>,
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjFileDescription {
+    path: String,
+    material: Option<toml::Spanned<String>>,
+    #[serde(default)]
+    cache: bool,
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaneDescription {
+    material: toml::Spanned<String>,
+    size: Option<[f64; 2]>,
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+fn white() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn spot_angle() -> f64 {
+    45.0
+}
+
+#[derive(Debug)]
+pub enum SceneReaderError {
+    Io(path::PathBuf, io::Error),
+    Parse(toml::de::Error),
+    // name, line, column
+    UnknownMaterial(String, usize, usize),
+    DuplicateMaterial(String, usize, usize),
+    // material name, parameter, line, column
+    UnsupportedParameter(String, &'static str, usize, usize),
+    Obj(obj_reader::ObjReaderError),
+    Texture(texture::TextureError),
+    // mesh name, number of triangles, number of texture coordinate triples
+    UvCount(String, usize, usize),
+    // index of the area light
+    AreaLightShape(usize),
+}
+
+impl fmt::Display for SceneReaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneReaderError::Io(path, error) => {
+                write!(f, "Failed to read scene file {}: {}", path.display(), error)
+            },
+            SceneReaderError::Parse(error) => {
+                write!(f, "Failed to parse scene: {}", error)
+            },
+            SceneReaderError::UnknownMaterial(name, line, column) => {
+                write!(f, "Unknown material {} at line {}, column {}", name, line, column)
+            },
+            SceneReaderError::DuplicateMaterial(name, line, column) => {
+                write!(f, "Material {} at line {}, column {} is already defined", name, line, column)
+            },
+            SceneReaderError::UnsupportedParameter(name, parameter, line, column) => {
+                write!(f, "Material {} at line {}, column {} doesn't support {}", name, line, column, parameter)
+            },
+            SceneReaderError::Obj(error) => {
+                write!(f, "{}", error)
+            },
+            SceneReaderError::Texture(error) => {
+                write!(f, "{}", error)
+            },
+            SceneReaderError::UvCount(name, triangles, uvs) => {
+                write!(f, "Mesh {} has {} triangles, but texture coordinates for {}", name, triangles, uvs)
+            },
+            SceneReaderError::AreaLightShape(index) => {
+                write!(f, "Area light {} has both a size and a radius", index)
+            },
+        }
+    }
+}
+
+impl error::Error for SceneReaderError {}
+
+impl From<obj_reader::ObjReaderError> for SceneReaderError {
+    fn from(error: obj_reader::ObjReaderError) -> Self {
+        SceneReaderError::Obj(error)
+    }
+}
+
+// load_scene reads a scene file, resolving OBJ and texture paths relative to the directory containing it
+pub fn load_scene(path: &path::Path) -> Result<scene::Scene, SceneReaderError> {
+    let source = fs::read_to_string(path).map_err(|e| SceneReaderError::Io(path.to_path_buf(), e))?;
+    let directory = path.parent().unwrap_or(path::Path::new(""));
+
+    parse_scene(&source, directory)
+}
+
+pub fn parse_scene(source: &str, directory: &path::Path) -> Result<scene::Scene, SceneReaderError> {
+    let description: SceneDescription = toml::from_str(source).map_err(SceneReaderError::Parse)?;
+
+    let mut material_library = library::Library::build_material_library();
+
+    for material in &description.materials {
+        if material_library.find_index_for_name(material.name.get_ref()).is_some() {
+            let (line, column) = line_column(source, material.name.span().start);
+            return Err(SceneReaderError::DuplicateMaterial(material.name.get_ref().clone(), line, column));
+        }
+
+        let name = material.name.get_ref();
+        let color = to_color(&material.color);
+
+        let unsupported = |parameter| {
+            let (line, column) = line_column(source, material.name.span().start);
+            SceneReaderError::UnsupportedParameter(name.clone(), parameter, line, column)
+        };
+
+        if material.mode.is_some() && !matches!(material.kind, MaterialKind::Diffuse) {
+            return Err(unsupported("mode"));
+        }
+        if material.texture.is_some() && !matches!(material.kind, MaterialKind::Diffuse) {
+            return Err(unsupported("texture"));
+        }
+        if material.wrap.is_some() && material.texture.is_none() {
+            return Err(unsupported("wrap"));
+        }
+        if material.roughness.is_some() && !matches!(material.kind, MaterialKind::Glossy) {
+            return Err(unsupported("roughness"));
+        }
+        if material.ior.is_some() && !matches!(material.kind, MaterialKind::Glass) {
+            return Err(unsupported("ior"));
+        }
+        if material.power.is_some() && !matches!(material.kind, MaterialKind::Emissive) {
+            return Err(unsupported("power"));
+        }
+
+        let built_material: Box<dyn material::Material> = match material.kind {
+            MaterialKind::Diffuse => {
+                let mut diffuse_material = diffuse_material::DiffuseMaterial::from_color(name, &color);
+                if let Some(mode) = material.mode {
+                    diffuse_material.set_mode(mode);
+                }
+
+                if let Some(texture_path) = &material.texture {
+                    let mut texture = texture::Texture::load(&directory.join(texture_path)).map_err(SceneReaderError::Texture)?;
+                    texture.set_wrap(material.wrap.unwrap_or(texture::WrapMode::Repeat));
+                    diffuse_material.set_texture(texture);
+                }
+
+                Box::new(diffuse_material)
+            },
+            MaterialKind::Mirror => Box::new(mirror_material::MirrorMaterial::from_color(name, &color)),
+            MaterialKind::Glossy => {
+                Box::new(glossy_material::GlossyMaterial::from_color(name, &color, material.roughness.unwrap_or(0.5)))
+            },
+            MaterialKind::Glass => {
+                let mut glass_material = glass_material::GlassMaterial::new(name, material.ior.unwrap_or(1.5));
+                glass_material.set_absorption(&color, 1.0);
+
+                Box::new(glass_material)
+            },
+            MaterialKind::Emissive => {
+                Box::new(emissive_material::EmissiveMaterial::new(name, &color, material.power.unwrap_or(1.0)))
+            },
+        };
+
+        material_library.add(name, built_material);
+    }
+
+    let mut camera = camera::Camera::new(
+        description.camera.width,
+        description.camera.height,
+        &angle::Angle::from_degrees(description.camera.vertical_fov),
+    );
+    camera.set_position(to_vector(&description.camera.position));
+    camera.set_rotation(to_rotation(&description.camera.rotation));
+
+    let mut lights = Vec::<Box<dyn light::LightPublicInterface>>::new();
+
+    for light_description in &description.parallel_lights {
+        let mut light = parallel_light::ParallelLight::new();
+        light.set_radius(light_description.radius);
+        light.set_brightness(light_description.brightness);
+        light.set_color(to_color(&light_description.color));
+        light.set_position(to_vector(&light_description.position));
+        light.set_rotation(to_rotation(&light_description.rotation));
+
+        lights.push(Box::new(light));
+    }
+
+    for light_description in &description.point_lights {
+        let mut light = point_light::PointLight::new();
+        light.set_brightness(light_description.brightness);
+        light.set_color(to_color(&light_description.color));
+        light.set_position(to_vector(&light_description.position));
+        light.set_rotation(to_rotation(&light_description.rotation));
+
+        lights.push(Box::new(light));
+    }
+
+    for light_description in &description.spot_lights {
+        let mut light = spot_light::SpotLight::new();
+        light.set_angle(angle::Angle::from_degrees(light_description.angle));
+        light.set_falloff(light_description.falloff);
+        light.set_brightness(light_description.brightness);
+        light.set_color(to_color(&light_description.color));
+        light.set_position(to_vector(&light_description.position));
+        light.set_rotation(to_rotation(&light_description.rotation));
+
+        lights.push(Box::new(light));
+    }
+
+    for (index, light_description) in description.area_lights.iter().enumerate() {
+        let mut light = area_light::AreaLight::new();
+        match (light_description.size, light_description.radius) {
+            (Some(_), Some(_)) => return Err(SceneReaderError::AreaLightShape(index)),
+            (Some([width, height]), None) => light.set_rectangle(width, height),
+            (None, Some(radius)) => light.set_disk(radius),
+            (None, None) => {},
+        }
+        light.set_brightness(light_description.brightness);
+        light.set_color(to_color(&light_description.color));
+        light.set_position(to_vector(&light_description.position));
+        light.set_rotation(to_rotation(&light_description.rotation));
+
+        lights.push(Box::new(light));
+    }
+
+    let mut volumes = Vec::<Box<dyn volume::VolumePublicInterface>>::new();
+
+    for mesh_description in &description.meshes {
+        let material_index = material_index(source, &material_library, &mesh_description.material)?;
+
+        if !mesh_description.uvs.is_empty() && mesh_description.uvs.len() != mesh_description.triangles.len() {
+            return Err(SceneReaderError::UvCount(
+                mesh_description.name.clone(),
+                mesh_description.triangles.len(),
+                mesh_description.uvs.len(),
+            ));
+        }
+
+        let mut triangles: Vec<triangle::Triangle> = mesh_description.triangles
+            .iter()
+            .map(|[a, b, c]| triangle::Triangle::new(to_vector(a), to_vector(b), to_vector(c)))
+            .collect();
+
+        for (triangle, [a, b, c]) in triangles.iter_mut().zip(&mesh_description.uvs) {
+            triangle.set_uvs(to_uv(a), to_uv(b), to_uv(c));
+        }
+
+        let mut mesh_volume = mesh_volume::MeshVolume::new(material_index, mesh::Mesh::new(&mesh_description.name, triangles));
+        mesh_volume.set_two_sided(material_library.fetch_by_index(material_index).is_transmissive());
+        mesh_volume.set_position(to_vector(&mesh_description.position));
+        mesh_volume.set_rotation(to_rotation(&mesh_description.rotation));
+
+        volumes.push(Box::new(mesh_volume));
+    }
+
+    for obj_description in &description.obj_files {
+        let override_index = match &obj_description.material {
+            Some(material) => Some(material_index(source, &material_library, material)?),
+            None => None,
+        };
+
+        let obj_path = directory.join(&obj_description.path);
+        let mesh_volumes = if obj_description.cache {
+            obj_reader::load_meshes_with_cache(&obj_path, &mut material_library)?
+        } else {
+            obj_reader::load_meshes(&obj_path, &mut material_library)?
+        };
+
+        for mut mesh_volume in mesh_volumes {
+            if let Some(index) = override_index {
+                mesh_volume.set_material_index(index);
+            }
+            mesh_volume.set_two_sided(material_library.fetch_by_index(mesh_volume.get_material_index()).is_transmissive());
+            mesh_volume.set_position(to_vector(&obj_description.position));
+            mesh_volume.set_rotation(to_rotation(&obj_description.rotation));
+
+            volumes.push(Box::new(mesh_volume));
+        }
+    }
+
+    for plane_description in &description.planes {
+        let material_index = material_index(source, &material_library, &plane_description.material)?;
+
+        let mut plane = plane_volume::PlaneVolume::new(material_index);
+        if let Some([width, depth]) = plane_description.size {
+            plane.set_size(width, depth);
+        }
+        plane.set_position(to_vector(&plane_description.position));
+        plane.set_rotation(to_rotation(&plane_description.rotation));
+
+        volumes.push(Box::new(plane));
+    }
+
+    Ok(scene::Scene {
+        camera,
+        volumes: volume_tree::VolumeTree::new(volumes),
+        lights,
+        material_library,
+    })
+}
+
+fn material_index(
+    source: &str,
+    material_library: &library::Library<Box<dyn material::Material>>,
+    name: &toml::Spanned<String>,
+) -> Result<usize, SceneReaderError> {
+    material_library.find_index_for_name(name.get_ref()).ok_or_else(|| {
+        let (line, column) = line_column(source, name.span().start);
+        SceneReaderError::UnknownMaterial(name.get_ref().clone(), line, column)
+    })
+}
+
+// line_column converts a byte offset into source to a 1-based line and column
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = offset - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+
+    (line, column)
+}
+
+fn to_vector(v: &[f64; 3]) -> vector3::Vector3 {
+    vector3::Vector3::new(v[0], v[1], v[2])
+}
+
+fn to_uv(v: &[f64; 2]) -> uv::Uv {
+    uv::Uv::new(v[0], v[1])
+}
+
+fn to_color(c: &[f64; 3]) -> color::Color {
+    color::Color::new(c[0], c[1], c[2])
+}
+
+fn to_rotation(degrees: &[f64; 3]) -> quaternion::Quaternion {
+    quaternion::Quaternion::from_roll_pitch_yaw(
+        angle::Angle::from_degrees(degrees[0]).get_radians(),
+        angle::Angle::from_degrees(degrees[1]).get_radians(),
+        angle::Angle::from_degrees(degrees[2]).get_radians(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{hit, ray};
+
+    const SCENE: &str = r#"
+[camera]
+width = 64
+height = 32
+vertical_fov = 90.0
+
+[[materials]]
+name = "Orange"
+color = [1.0, 0.5, 0.0]
+
+[[parallel_lights]]
+radius = 1.0
+brightness = 1000.0
+position = [0.25, 0.25, 1.5]
+
+[[meshes]]
+name = "triangle"
+material = "Orange"
+triangles = [[[-1.0, -1.0, 3.0], [-1.0, 1.0, 3.0], [1.0, 0.0, 3.0]]]
+
+[[planes]]
+material = "White"
+size = [10.0, 10.0]
+position = [0.0, -1.0, 0.0]
+"#;
+
+    #[test]
+    fn parse_scene_description() {
+        let scene = parse_scene(SCENE, path::Path::new("")).unwrap();
+
+        assert_eq!(scene.camera.width(), 64);
+        assert_eq!(scene.camera.height(), 32);
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.lights[0].get_brightness(), 1000.0);
+        assert_eq!(scene.volumes.len(), 2);
+        assert_eq!(scene.volumes[0].get_material_index(), scene.material_library.index_for_name("Orange"));
+        assert_eq!(scene.volumes[1].get_material_index(), scene.material_library.index_for_name("White"));
+
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 0.0, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+
+        assert!(scene.volumes[0].cast_ray(&ray, &mut cast_buffer).is_some());
+    }
+
+    #[test]
+    fn parse_scene_glass_meshes_are_two_sided() {
+        let behind = ray::Ray::new(
+            vector3::Vector3::new(0.0, 0.0, 6.0),
+            vector3::Vector3::new(0.0, 0.0, -1.0),
+        );
+
+        let scene = parse_scene(SCENE, path::Path::new("")).unwrap();
+        assert!(scene.volumes[0].cast_ray(&behind, &mut Vec::new()).is_none());
+        assert!(!scene.volumes[0].occluded(&behind, 10.0));
+
+        let scene = parse_scene(&SCENE.replace("material = \"Orange\"", "material = \"Glass\""), path::Path::new("")).unwrap();
+        assert!((scene.volumes[0].cast_ray(&behind, &mut Vec::new()).unwrap().distance - 3.0).abs() < 1e-9);
+        assert!(scene.volumes[0].occluded(&behind, 10.0));
+    }
+
+    #[test]
+    fn parse_scene_texture_coordinates() {
+        let source = SCENE.replace(
+            "triangles = [[[-1.0, -1.0, 3.0], [-1.0, 1.0, 3.0], [1.0, 0.0, 3.0]]]",
+            "triangles = [[[-1.0, -1.0, 3.0], [-1.0, 1.0, 3.0], [1.0, 0.0, 3.0]]]\nuvs = [[[0.0, 0.0], [0.0, 1.0], [1.0, 0.5]]]",
+        );
+
+        let scene = parse_scene(&source, path::Path::new("")).unwrap();
+
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.0, 0.0, 0.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+        let hit = scene.volumes[0].cast_ray(&ray, &mut Vec::new()).unwrap();
+
+        assert!((hit.uv.u - 0.5).abs() < 1e-9);
+        assert!((hit.uv.v - 0.5).abs() < 1e-9);
+
+        let error = parse_scene(&source.replace("uvs = [[", "uvs = [[[0.0, 0.0], [0.0, 1.0], [1.0, 0.5]], ["), path::Path::new(""))
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, SceneReaderError::UvCount(ref name, 1, 2) if name == "triangle"), "{}", error);
+    }
+
+    #[test]
+    fn parse_scene_missing_texture() {
+        let source = SCENE.replace("color = [1.0, 0.5, 0.0]", "texture = \"missing.png\"\nwrap = \"clamp\"");
+
+        let error = parse_scene(&source, path::Path::new("")).err().unwrap();
+
+        assert!(matches!(error, SceneReaderError::Texture(texture::TextureError::Io(_, _))), "{}", error);
+    }
+
+    #[test]
+    fn parse_scene_wrap_without_texture() {
+        let source = SCENE.replace("color = [1.0, 0.5, 0.0]", "color = [1.0, 0.5, 0.0]\nwrap = \"clamp\"");
+
+        let error = parse_scene(&source, path::Path::new("")).err().unwrap();
+
+        assert!(matches!(error, SceneReaderError::UnsupportedParameter(ref name, "wrap", 8, 8) if name == "Orange"), "{}", error);
+    }
+
+    #[test]
+    fn parse_scene_unknown_material() {
+        let source = SCENE.replace("material = \"White\"", "material = \"Mauve\"");
+
+        let error = parse_scene(&source, path::Path::new("")).err().unwrap();
+
+        assert!(matches!(error, SceneReaderError::UnknownMaterial(ref name, 22, 12) if name == "Mauve"), "{}", error);
+    }
+
+    #[test]
+    fn parse_scene_duplicate_material() {
+        let source = SCENE.replace("name = \"Orange\"", "name = \"Cyan\"");
+
+        let error = parse_scene(&source, path::Path::new("")).err().unwrap();
+
+        assert!(matches!(error, SceneReaderError::DuplicateMaterial(ref name, 8, 8) if name == "Cyan"), "{}", error);
+    }
+
+    #[test]
+    fn parse_scene_material_types() {
+        let source = SCENE.replace(
+            "color = [1.0, 0.5, 0.0]",
+            "color = [1.0, 0.5, 0.0]\ntype = \"glossy\"\nroughness = 0.2\n\n[[materials]]\nname = \"Chrome\"\ntype = \"mirror\"\n\n[[materials]]\nname = \"Crystal\"\ntype = \"glass\"\nior = 2.0\n\n[[materials]]\nname = \"Chalk\"\nmode = \"lambertian\"",
+        );
+
+        let scene = parse_scene(&source, path::Path::new("")).unwrap();
+
+        assert_eq!(scene.material_library.fetch_by_index(scene.material_library.index_for_name("Orange")).get_name(), "Orange");
+        assert_eq!(scene.material_library.fetch_by_index(scene.material_library.index_for_name("Chrome")).get_name(), "Chrome");
+        assert_eq!(scene.material_library.fetch_by_index(scene.material_library.index_for_name("Crystal")).get_name(), "Crystal");
+        assert_eq!(scene.material_library.fetch_by_index(scene.material_library.index_for_name("Chalk")).get_name(), "Chalk");
+
+        let error = parse_scene(&source.replace("type = \"mirror\"", "type = \"mirror\"\nroughness = 0.1"), path::Path::new(""))
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, SceneReaderError::UnsupportedParameter(ref name, "roughness", 14, 8) if name == "Chrome"), "{}", error);
+
+        let error = parse_scene(&source.replace("type = \"mirror\"", "type = \"metal\""), path::Path::new("")).err().unwrap();
+
+        assert!(matches!(error, SceneReaderError::Parse(_)));
+    }
+
+    #[test]
+    fn parse_scene_light_types() {
+        let source = format!(
+            "{}\n[[point_lights]]\nbrightness = 2.0\n\n[[spot_lights]]\nbrightness = 3.0\nangle = 30.0\nfalloff = 2.0\n\n[[area_lights]]\nbrightness = 4.0\nradius = 0.5\n",
+            SCENE,
+        );
+
+        let scene = parse_scene(&source, path::Path::new("")).unwrap();
+
+        let brightnesses: Vec<f64> = scene.lights.iter().map(|light| light.get_brightness()).collect();
+        assert_eq!(brightnesses, vec![1000.0, 2.0, 3.0, 4.0]);
+
+        let error = parse_scene(&source.replace("radius = 0.5", "radius = 0.5\nsize = [1.0, 2.0]"), path::Path::new("")).err().unwrap();
+
+        assert!(matches!(error, SceneReaderError::AreaLightShape(0)), "{}", error);
+    }
+
+    #[test]
+    fn parse_scene_syntax_error() {
+        let error = parse_scene("[camera]\nwidth = \n", path::Path::new("")).err().unwrap();
+
+        assert!(matches!(error, SceneReaderError::Parse(_)));
+    }
+
+    #[test]
+    fn load_scene_with_obj_file() {
+        let directory = std::env::temp_dir().join("tdi_ray_tracer_scene_reader_obj");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("triangle.obj"),
+            "v -1.0 -1.0 3.0\nv -1.0 1.0 3.0\nv 1.0 0.0 3.0\nf 1 2 3\n",
+        ).unwrap();
+        fs::write(
+            directory.join("scene.toml"),
+            "[camera]\nwidth = 10\nheight = 10\nvertical_fov = 90.0\n\n[[obj_files]]\npath = \"triangle.obj\"\nmaterial = \"Red\"\n",
+        ).unwrap();
+
+        let scene = load_scene(&directory.join("scene.toml")).unwrap();
+
+        assert_eq!(scene.volumes.len(), 1);
+        assert_eq!(scene.volumes[0].get_material_index(), scene.material_library.index_for_name("Red"));
+    }
+
+    #[test]
+    fn load_scene_with_cached_obj_file() {
+        let directory = std::env::temp_dir().join("tdi_ray_tracer_scene_reader_cached_obj");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("triangle.obj"),
+            "v -1.0 -1.0 3.0\nv -1.0 1.0 3.0\nv 1.0 0.0 3.0\nf 1 2 3\n",
+        ).unwrap();
+        fs::write(
+            directory.join("scene.toml"),
+            "[camera]\nwidth = 10\nheight = 10\nvertical_fov = 90.0\n\n[[obj_files]]\npath = \"triangle.obj\"\ncache = true\n",
+        ).unwrap();
+        let _ = fs::remove_file(directory.join("triangle.obj.bvh"));
+
+        let scene = load_scene(&directory.join("scene.toml")).unwrap();
+
+        assert_eq!(scene.volumes.len(), 1);
+        assert!(directory.join("triangle.obj.bvh").exists());
+    }
+
+    #[test]
+    fn line_column_offsets() {
+        assert_eq!(line_column("abc\ndef", 0), (1, 1));
+        assert_eq!(line_column("abc\ndef", 2), (1, 3));
+        assert_eq!(line_column("abc\ndef", 4), (2, 1));
+        assert_eq!(line_column("abc\ndef", 6), (2, 3));
+    }
+}
@@ -0,0 +1,175 @@
+use std::f64::consts;
+
+use crate::{angle, color, light, photon, quaternion, random_generator, ray, sampling, vector3};
+
+use crate::light::LightProtectedInterface;
+pub use crate::light::LightPublicInterface;
+
+struct SpotLightStrategy {
+    // angle between the forward direction and the edge of the cone
+    m_angle: f64,
+    // exponent of the cosine of the angle to the forward direction that the intensity falls off with
+    m_falloff: f64,
+}
+
+impl SpotLightStrategy {
+    // edge is the cosine of the edge angle raised to the power of the falloff plus one, which bounds the
+    // integral of the intensity over the cone
+    fn edge(&self) -> f64 {
+        self.m_angle.cos().max(0.0).powf(self.m_falloff + 1.0)
+    }
+}
+
+impl light::LightStrategy for SpotLightStrategy {
+    // emit picks the cosine of the angle to the forward direction with a density that follows the intensity,
+    // by inverting its integral over the cone
+    fn emit(&self, base: &light::Light<SpotLightStrategy>, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator) {
+        let edge = self.edge();
+        let cos_theta = (edge + random_generator.value(1.0) * (1.0 - edge)).powf(1.0 / (self.m_falloff + 1.0));
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = random_generator.value(2.0 * consts::PI);
+
+        let local = vector3::Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let direction = sampling::to_world(&local, &base.object.forward());
+
+        photon.ray = ray::Ray::new(base.object.position(), direction);
+        photon.color = base.m_color * base.m_lumens * photon_brightness;
+        photon.bounces = 0;
+    }
+
+    // the brightness is the intensity in the forward direction, per unit solid angle
+    fn lumens(&self, brightness: f64, _area: f64) -> f64 {
+        brightness * 2.0 * consts::PI * (1.0 - self.edge()) / (self.m_falloff + 1.0)
+    }
+}
+
+// SpotLight emits light from a single point into a cone around its forward direction, fading from the
+// middle of the cone towards its edge
+pub struct SpotLight {
+    light: light::Light<SpotLightStrategy>,
+}
+
+impl SpotLight {
+    pub fn new() -> SpotLight {
+        SpotLight {
+            light: light::Light::<SpotLightStrategy>::new(SpotLightStrategy {
+                m_angle: consts::FRAC_PI_4,
+                m_falloff: 0.0,
+            }),
+        }
+    }
+
+    // set_angle sets the angle between the forward direction and the edge of the cone, up to 90 degrees
+    pub fn set_angle(&mut self, angle: angle::Angle) {
+        self.light.specialization.m_angle = angle.get_radians().clamp(0.0, consts::FRAC_PI_2);
+        self.light.update_parameters();
+    }
+
+    // set_falloff sets how quickly the light fades away from the forward direction, as the exponent of the
+    // cosine of the angle to it, so 0 is evenly bright across the cone
+    pub fn set_falloff(&mut self, falloff: f64) {
+        self.light.specialization.m_falloff = falloff.max(0.0);
+        self.light.update_parameters();
+    }
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        SpotLight::new()
+    }
+}
+
+impl light::LightPublicInterface for SpotLight {
+    fn get_color(&self) -> color::Color {
+        self.light.get_color()
+    }
+
+    fn set_color(&mut self, color: color::Color) {
+        self.light.set_color(color)
+    }
+
+    fn get_brightness(&self) -> f64 {
+        self.light.get_brightness()
+    }
+
+    fn set_brightness(&mut self, brightness: f64) {
+        self.light.set_brightness(brightness)
+    }
+
+    fn emit(&self, photon: &mut photon::Photon, photon_brightness: f64, random_generator: &mut random_generator::RandomGenerator) {
+        self.light.emit(photon, photon_brightness, random_generator)
+    }
+
+    fn set_position(&mut self, position: vector3::Vector3) {
+        self.light.set_position(position)
+    }
+
+    fn set_rotation(&mut self, rotation: quaternion::Quaternion) {
+        self.light.set_rotation(rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn lumens() {
+        let mut light = SpotLight::new();
+        light.set_brightness(10.0);
+        light.set_angle(angle::Angle::from_degrees(60.0));
+
+        // an even cone holds the solid angle of its cap
+        assert_approx_eq!(light.light.m_lumens, 10.0 * 2.0 * consts::PI * 0.5, 1e-9f64);
+
+        // a cone of 90 degrees falling off with the cosine is a Lambertian emitter
+        light.set_angle(angle::Angle::from_degrees(90.0));
+        light.set_falloff(1.0);
+        assert_approx_eq!(light.light.m_lumens, 10.0 * consts::PI, 1e-9f64);
+    }
+
+    // mean_cos emits photons from a spot light pointing down -y and returns the mean cosine of their angle
+    // to it, checking that they all leave within the cone
+    fn mean_cos(angle: f64, falloff: f64) -> f64 {
+        let mut light = SpotLight::new();
+        light.set_brightness(1.0);
+        light.set_angle(angle::Angle::from_degrees(angle));
+        light.set_falloff(falloff);
+        light.set_rotation(quaternion::Quaternion::from_axis_angle(&vector3::UNIT_X, consts::FRAC_PI_2));
+
+        let forward = light.light.object.forward();
+        assert_approx_eq!(forward.get_y(), -1.0, 1e-9f64);
+
+        let mut rg = random_generator::RandomGenerator::from_seed(41);
+        let mut photon = photon::Photon::default();
+        let samples = 100_000;
+        let mut total = 0.0;
+
+        for _ in 0..samples {
+            light.emit(&mut photon, 1.0, &mut rg);
+
+            let cos = vector3::Vector3::dot(&photon.ray.direction, &forward);
+            assert!(cos >= angle.to_radians().cos() - 1e-9);
+            assert_approx_eq!(photon.ray.direction.norm(), 1.0, 1e-9f64);
+            total += cos;
+        }
+
+        total / samples as f64
+    }
+
+    #[test]
+    fn emit_within_cone() {
+        // without falloff directions are uniform over the cap of the cone, so the cosine averages halfway
+        // between 1 and the cosine of the edge
+        let edge = 30.0f64.to_radians().cos();
+        assert_approx_eq!(mean_cos(30.0, 0.0), (1.0 + edge) / 2.0, 1e-3f64);
+
+        // with a cosine falloff over the hemisphere the directions are cosine distributed
+        assert_approx_eq!(mean_cos(90.0, 1.0), 2.0 / 3.0, 5e-3f64);
+
+        // a sharper falloff concentrates the light towards the middle of the cone
+        assert!(mean_cos(60.0, 8.0) > mean_cos(60.0, 0.0));
+    }
+}
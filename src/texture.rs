@@ -0,0 +1,250 @@
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+
+use serde::Deserialize;
+use strum_macros::EnumString;
+
+use crate::{color, tone_mapping, uv};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum WrapMode {
+    // tiles the texture
+    Repeat,
+    // tiles the texture, flipping every other tile so that the edges meet
+    Mirror,
+    // extends the edge texels outwards
+    Clamp,
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(path::PathBuf, io::Error),
+    Png(path::PathBuf, png::DecodingError),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Io(path, error) => {
+                write!(f, "Failed to read texture {}: {}", path.display(), error)
+            },
+            TextureError::Png(path, error) => {
+                write!(f, "Failed to decode PNG texture {}: {}", path.display(), error)
+            },
+        }
+    }
+}
+
+impl error::Error for TextureError {}
+
+// Texture is an image of linear colors that is sampled at texture coordinates with bilinear filtering
+pub struct Texture {
+    m_width: usize,
+    m_height: usize,
+    // rows from the top of the image down
+    m_texels: Vec<color::Color>,
+    m_wrap: WrapMode,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, texels: Vec<color::Color>) -> Self {
+        assert!(width > 0 && height > 0, "Texture must have at least one texel, got {} x {}", width, height);
+        assert_eq!(texels.len(), width * height, "Texture of {} x {} texels", width, height);
+
+        Texture {
+            m_width: width,
+            m_height: height,
+            m_texels: texels,
+            m_wrap: WrapMode::Repeat,
+        }
+    }
+
+    // load decodes a PNG file, whose colors are taken to be sRGB encoded. Alpha channels are ignored.
+    pub fn load(path: &path::Path) -> Result<Self, TextureError> {
+        let file = fs::File::open(path).map_err(|e| TextureError::Io(path.to_path_buf(), e))?;
+
+        let mut decoder = png::Decoder::new(io::BufReader::new(file));
+        // palettes are expanded to RGB and low bit depths to 8 bits
+        decoder.set_transformations(png::Transformations::EXPAND);
+
+        let mut reader = decoder.read_info().map_err(|e| TextureError::Png(path.to_path_buf(), e))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|e| TextureError::Png(path.to_path_buf(), e))?;
+
+        let channels = info.color_type.samples();
+        let values: Vec<f64> = match info.bit_depth {
+            png::BitDepth::Sixteen => buffer[..info.buffer_size()]
+                .chunks_exact(2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as f64 / u16::MAX as f64)
+                .collect(),
+            _ => buffer[..info.buffer_size()]
+                .iter()
+                .map(|&byte| byte as f64 / u8::MAX as f64)
+                .collect(),
+        };
+
+        let texels = values
+            .chunks_exact(channels)
+            .map(|texel| {
+                let encoded = match info.color_type {
+                    png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => color::Color::new(texel[0], texel[0], texel[0]),
+                    _ => color::Color::new(texel[0], texel[1], texel[2]),
+                };
+
+                tone_mapping::srgb_decode(&encoded)
+            })
+            .collect();
+
+        Ok(Texture::new(info.width as usize, info.height as usize, texels))
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.m_width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.m_height
+    }
+
+    pub fn set_wrap(&mut self, wrap: WrapMode) {
+        self.m_wrap = wrap;
+    }
+
+    // sample blends the four texels around a texture coordinate, whose centers are half a texel in from
+    // the edges of their cells
+    pub fn sample(&self, uv: &uv::Uv) -> color::Color {
+        let x = uv.u * self.m_width as f64 - 0.5;
+        let y = (1.0 - uv.v) * self.m_height as f64 - 0.5;
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+
+        let x0 = x0 as i64;
+        let y0 = y0 as i64;
+
+        let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    fn texel(&self, x: i64, y: i64) -> color::Color {
+        let x = self.wrap(x, self.m_width);
+        let y = self.wrap(y, self.m_height);
+
+        self.m_texels[y * self.m_width + x]
+    }
+
+    fn wrap(&self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+
+        let wrapped = match self.m_wrap {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::Mirror => {
+                let index = index.rem_euclid(2 * size);
+                if index < size { index } else { 2 * size - 1 - index }
+            },
+            WrapMode::Clamp => index.clamp(0, size - 1),
+        };
+
+        wrapped as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::str::FromStr;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    // two texels side by side, black on the left and white on the right
+    fn black_white() -> Texture {
+        Texture::new(2, 1, vec![color::Color::new(0.0, 0.0, 0.0), color::Color::new(1.0, 1.0, 1.0)])
+    }
+
+    #[test]
+    fn wrap_from_str() {
+        assert_eq!(WrapMode::from_str("repeat").unwrap(), WrapMode::Repeat);
+        assert_eq!(WrapMode::from_str("mirror").unwrap(), WrapMode::Mirror);
+        assert_eq!(WrapMode::from_str("clamp").unwrap(), WrapMode::Clamp);
+        assert!(WrapMode::from_str("border").is_err());
+    }
+
+    #[test]
+    fn sample_bilinear() {
+        let texture = black_white();
+
+        // texel centers return the texels, and halfway between them is the average
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.25, 0.5)).red, 0.0, 1e-9f64);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.75, 0.5)).red, 1.0, 1e-9f64);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.5, 0.5)).red, 0.5, 1e-9f64);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.375, 0.5)).red, 0.25, 1e-9f64);
+    }
+
+    #[test]
+    fn sample_wrap_modes() {
+        let mut texture = black_white();
+
+        // at the left edge the neighbour across the edge is the white texel when repeating, and the black
+        // texel itself when mirroring or clamping
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.0, 0.5)).red, 0.5, 1e-9f64);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(1.25, 0.5)).red, 0.0, 1e-9f64);
+
+        texture.set_wrap(WrapMode::Mirror);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.0, 0.5)).red, 0.0, 1e-9f64);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(1.25, 0.5)).red, 1.0, 1e-9f64);
+
+        texture.set_wrap(WrapMode::Clamp);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(-3.0, 0.5)).red, 0.0, 1e-9f64);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(4.0, 0.5)).red, 1.0, 1e-9f64);
+    }
+
+    #[test]
+    fn sample_rows_from_the_top() {
+        let mut texture = Texture::new(1, 2, vec![color::Color::new(1.0, 0.0, 0.0), color::Color::new(0.0, 0.0, 1.0)]);
+        texture.set_wrap(WrapMode::Clamp);
+
+        // v runs up the texture, so the top row is at v = 1
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.5, 1.0)).red, 1.0, 1e-9f64);
+        assert_approx_eq!(texture.sample(&uv::Uv::new(0.5, 0.0)).blue, 1.0, 1e-9f64);
+    }
+
+    #[test]
+    fn load_png() {
+        let path = env::temp_dir().join("tdi_ray_tracer_texture.png");
+
+        let mut encoder = png::Encoder::new(fs::File::create(&path).unwrap(), 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 188, 255, 0, 0, 0, 0]).unwrap();
+        writer.finish().unwrap();
+
+        let texture = Texture::load(&path).unwrap();
+        assert_eq!(texture.get_width(), 2);
+        assert_eq!(texture.get_height(), 1);
+
+        // the texels are decoded from sRGB, and alpha is ignored
+        let texel = texture.sample(&uv::Uv::new(0.25, 0.5));
+        assert_approx_eq!(texel.red, 1.0, 1e-9f64);
+        assert_approx_eq!(texel.green, 0.0, 1e-9f64);
+        assert_approx_eq!(texel.blue, 0.5, 1e-2f64);
+    }
+
+    #[test]
+    fn load_missing_png() {
+        let result = Texture::load(path::Path::new("does_not_exist.png"));
+
+        assert!(matches!(result, Err(TextureError::Io(_, _))));
+    }
+}
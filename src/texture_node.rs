@@ -0,0 +1,510 @@
+use std::ops;
+
+use crate::{color, hit, texture, vector3};
+
+// Blend is a value that texture nodes can interpolate between, such as a color or a scalar
+pub trait Blend: Copy + ops::Add<Output = Self> + ops::Mul<f64, Output = Self> {}
+
+impl<T> Blend for T where T: Copy + ops::Add<Output = T> + ops::Mul<f64, Output = T> {}
+
+fn lerp<T: Blend>(a: T, b: T, t: f64) -> T {
+    a * (1.0 - t) + b * t
+}
+
+// TextureNode evaluates a color or scalar parameter of a material at a hit. Nodes that take other nodes as
+// inputs combine them into graphs.
+pub trait TextureNode<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T;
+}
+
+// constants are nodes that evaluate to themselves
+impl TextureNode<f64> for f64 {
+    fn evaluate(&self, _hit: &hit::Hit) -> f64 {
+        *self
+    }
+}
+
+impl TextureNode<color::Color> for color::Color {
+    fn evaluate(&self, _hit: &hit::Hit) -> color::Color {
+        *self
+    }
+}
+
+impl TextureNode<color::Color> for texture::Texture {
+    fn evaluate(&self, hit: &hit::Hit) -> color::Color {
+        self.sample(&hit.uv)
+    }
+}
+
+// Mapping chooses the coordinates that a procedural pattern is evaluated at
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mapping {
+    // texture coordinates, as (u, v, 0)
+    Uv,
+    // world space position of the hit
+    Position,
+}
+
+impl Mapping {
+    fn point(&self, hit: &hit::Hit) -> vector3::Vector3 {
+        match self {
+            Mapping::Uv => vector3::Vector3::new(hit.uv.u, hit.uv.v, 0.0),
+            Mapping::Position => hit.position,
+        }
+    }
+}
+
+// Checker alternates between two values in cells of 1 / scale units along every axis
+pub struct Checker<T> {
+    m_a: T,
+    m_b: T,
+    m_scale: f64,
+    m_mapping: Mapping,
+}
+
+impl<T: Blend> Checker<T> {
+    pub fn new(a: T, b: T) -> Self {
+        Checker {
+            m_a: a,
+            m_b: b,
+            m_scale: 1.0,
+            m_mapping: Mapping::Uv,
+        }
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.m_scale = scale;
+    }
+
+    pub fn set_mapping(&mut self, mapping: Mapping) {
+        self.m_mapping = mapping;
+    }
+}
+
+impl<T: Blend> TextureNode<T> for Checker<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T {
+        let point = self.m_mapping.point(hit) * self.m_scale;
+        let cell = point.get_x().floor() + point.get_y().floor() + point.get_z().floor();
+
+        if (cell as i64).rem_euclid(2) == 0 { self.m_a } else { self.m_b }
+    }
+}
+
+// Stripes alternates between two values in bands of 1 / scale units across an axis
+pub struct Stripes<T> {
+    m_a: T,
+    m_b: T,
+    m_scale: f64,
+    m_axis: vector3::Axis,
+    m_mapping: Mapping,
+}
+
+impl<T: Blend> Stripes<T> {
+    pub fn new(a: T, b: T, axis: vector3::Axis) -> Self {
+        Stripes {
+            m_a: a,
+            m_b: b,
+            m_scale: 1.0,
+            m_axis: axis,
+            m_mapping: Mapping::Uv,
+        }
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.m_scale = scale;
+    }
+
+    pub fn set_mapping(&mut self, mapping: Mapping) {
+        self.m_mapping = mapping;
+    }
+}
+
+impl<T: Blend> TextureNode<T> for Stripes<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T {
+        let band = (self.m_mapping.point(hit).get_component(&self.m_axis) * self.m_scale).floor();
+
+        if (band as i64).rem_euclid(2) == 0 { self.m_a } else { self.m_b }
+    }
+}
+
+// Gradient blends linearly from one value at start to another at end along an axis, and holds them beyond
+pub struct Gradient<T> {
+    m_a: T,
+    m_b: T,
+    m_start: f64,
+    m_end: f64,
+    m_axis: vector3::Axis,
+    m_mapping: Mapping,
+}
+
+impl<T: Blend> Gradient<T> {
+    pub fn new(a: T, b: T, axis: vector3::Axis) -> Self {
+        Gradient {
+            m_a: a,
+            m_b: b,
+            m_start: 0.0,
+            m_end: 1.0,
+            m_axis: axis,
+            m_mapping: Mapping::Uv,
+        }
+    }
+
+    pub fn set_range(&mut self, start: f64, end: f64) {
+        self.m_start = start;
+        self.m_end = end;
+    }
+
+    pub fn set_mapping(&mut self, mapping: Mapping) {
+        self.m_mapping = mapping;
+    }
+}
+
+impl<T: Blend> TextureNode<T> for Gradient<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T {
+        let value = self.m_mapping.point(hit).get_component(&self.m_axis);
+        let t = ((value - self.m_start) / (self.m_end - self.m_start)).clamp(0.0, 1.0);
+
+        lerp(self.m_a, self.m_b, t)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseKind {
+    // random values at the lattice points, smoothly interpolated between them
+    Value,
+    // Perlin's gradient noise, random gradients at the lattice points
+    Perlin,
+}
+
+// Noise blends between two values by fractal noise, summing octaves of noise that each have twice the
+// frequency and half the amplitude of the one before
+pub struct Noise<T> {
+    m_a: T,
+    m_b: T,
+    m_kind: NoiseKind,
+    m_frequency: f64,
+    m_octaves: u32,
+    m_seed: u32,
+    m_mapping: Mapping,
+}
+
+impl<T: Blend> Noise<T> {
+    pub fn new(a: T, b: T, kind: NoiseKind) -> Self {
+        Noise {
+            m_a: a,
+            m_b: b,
+            m_kind: kind,
+            m_frequency: 1.0,
+            m_octaves: 1,
+            m_seed: 0,
+            m_mapping: Mapping::Uv,
+        }
+    }
+
+    pub fn set_frequency(&mut self, frequency: f64) {
+        self.m_frequency = frequency;
+    }
+
+    pub fn set_octaves(&mut self, octaves: u32) {
+        self.m_octaves = octaves.max(1);
+    }
+
+    pub fn set_seed(&mut self, seed: u32) {
+        self.m_seed = seed;
+    }
+
+    pub fn set_mapping(&mut self, mapping: Mapping) {
+        self.m_mapping = mapping;
+    }
+
+    // value returns the fractal noise at a point, in [0, 1]
+    fn value(&self, point: &vector3::Vector3) -> f64 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut amplitudes = 0.0;
+        let mut frequency = self.m_frequency;
+
+        for octave in 0..self.m_octaves {
+            let seed = self.m_seed.wrapping_add(octave);
+            let noise = match self.m_kind {
+                NoiseKind::Value => value_noise(&(*point * frequency), seed),
+                NoiseKind::Perlin => (perlin_noise(&(*point * frequency), seed) + 1.0) / 2.0,
+            };
+
+            sum += noise * amplitude;
+            amplitudes += amplitude;
+            amplitude /= 2.0;
+            frequency *= 2.0;
+        }
+
+        (sum / amplitudes).clamp(0.0, 1.0)
+    }
+}
+
+impl<T: Blend> TextureNode<T> for Noise<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T {
+        lerp(self.m_a, self.m_b, self.value(&self.m_mapping.point(hit)))
+    }
+}
+
+// Mix blends between two nodes by a factor node, returning a where the factor is 0 and b where it is 1
+pub struct Mix<T> {
+    m_a: Box<dyn TextureNode<T>>,
+    m_b: Box<dyn TextureNode<T>>,
+    m_factor: Box<dyn TextureNode<f64>>,
+}
+
+impl<T: Blend> Mix<T> {
+    pub fn new(
+        a: impl TextureNode<T> + 'static,
+        b: impl TextureNode<T> + 'static,
+        factor: impl TextureNode<f64> + 'static,
+    ) -> Self {
+        Mix {
+            m_a: Box::new(a),
+            m_b: Box::new(b),
+            m_factor: Box::new(factor),
+        }
+    }
+}
+
+impl<T: Blend> TextureNode<T> for Mix<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T {
+        lerp(self.m_a.evaluate(hit), self.m_b.evaluate(hit), self.m_factor.evaluate(hit))
+    }
+}
+
+// Scale multiplies a node by a constant
+pub struct Scale<T> {
+    m_input: Box<dyn TextureNode<T>>,
+    m_factor: f64,
+}
+
+impl<T: Blend> Scale<T> {
+    pub fn new(input: impl TextureNode<T> + 'static, factor: f64) -> Self {
+        Scale {
+            m_input: Box::new(input),
+            m_factor: factor,
+        }
+    }
+}
+
+impl<T: Blend> TextureNode<T> for Scale<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T {
+        self.m_input.evaluate(hit) * self.m_factor
+    }
+}
+
+// Offset adds a constant to a node
+pub struct Offset<T> {
+    m_input: Box<dyn TextureNode<T>>,
+    m_offset: T,
+}
+
+impl<T: Blend> Offset<T> {
+    pub fn new(input: impl TextureNode<T> + 'static, offset: T) -> Self {
+        Offset {
+            m_input: Box::new(input),
+            m_offset: offset,
+        }
+    }
+}
+
+impl<T: Blend> TextureNode<T> for Offset<T> {
+    fn evaluate(&self, hit: &hit::Hit) -> T {
+        self.m_input.evaluate(hit) + self.m_offset
+    }
+}
+
+// hash scrambles the coordinates of a lattice point and a seed into 32 random bits
+fn hash(x: i64, y: i64, z: i64, seed: u32) -> u32 {
+    let mut h = seed as u64;
+
+    for value in [x, y, z] {
+        h = (h ^ value as u64).wrapping_mul(0x9e3779b97f4a7c15);
+        h ^= h >> 32;
+    }
+
+    (h.wrapping_mul(0xbf58476d1ce4e5b9) >> 32) as u32
+}
+
+// fade is Perlin's quintic smoothstep, whose first and second derivatives are zero at 0 and 1
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// lattice_noise interpolates the values that corner returns for the eight lattice points around a point,
+// passing it the lattice point and the offset of the point from it
+fn lattice_noise(point: &vector3::Vector3, corner: impl Fn(i64, i64, i64, &vector3::Vector3) -> f64) -> f64 {
+    let floor = vector3::Vector3::new(point.get_x().floor(), point.get_y().floor(), point.get_z().floor());
+    let fraction = *point - floor;
+
+    let (x, y, z) = (floor.get_x() as i64, floor.get_y() as i64, floor.get_z() as i64);
+    let (u, v, w) = (fade(fraction.get_x()), fade(fraction.get_y()), fade(fraction.get_z()));
+
+    let mut corners = [0.0; 8];
+    for (i, value) in corners.iter_mut().enumerate() {
+        let (dx, dy, dz) = ((i & 1) as i64, ((i >> 1) & 1) as i64, ((i >> 2) & 1) as i64);
+        let offset = fraction - vector3::Vector3::new(dx as f64, dy as f64, dz as f64);
+
+        *value = corner(x + dx, y + dy, z + dz, &offset);
+    }
+
+    let x00 = lerp(corners[0], corners[1], u);
+    let x10 = lerp(corners[2], corners[3], u);
+    let x01 = lerp(corners[4], corners[5], u);
+    let x11 = lerp(corners[6], corners[7], u);
+
+    lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
+}
+
+// value_noise returns noise in [0, 1]
+fn value_noise(point: &vector3::Vector3, seed: u32) -> f64 {
+    lattice_noise(point, |x, y, z, _offset| hash(x, y, z, seed) as f64 / u32::MAX as f64)
+}
+
+// perlin_noise returns noise in [-1, 1] that is zero at the lattice points, with gradients chosen from
+// the twelve directions to the edges of a cube
+fn perlin_noise(point: &vector3::Vector3, seed: u32) -> f64 {
+    const GRADIENTS: [[f64; 3]; 12] = [
+        [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+        [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+        [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+    ];
+
+    lattice_noise(point, |x, y, z, offset| {
+        let [gx, gy, gz] = GRADIENTS[hash(x, y, z, seed) as usize % GRADIENTS.len()];
+
+        gx * offset.get_x() + gy * offset.get_y() + gz * offset.get_z()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::uv;
+
+    fn hit_at_uv(u: f64, v: f64) -> hit::Hit {
+        hit::Hit {
+            uv: uv::Uv::new(u, v),
+            ..Default::default()
+        }
+    }
+
+    fn hit_at_position(x: f64, y: f64, z: f64) -> hit::Hit {
+        hit::Hit {
+            position: vector3::Vector3::new(x, y, z),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn checker() {
+        let mut checker = Checker::new(0.0, 1.0);
+        checker.set_scale(2.0);
+
+        assert_eq!(checker.evaluate(&hit_at_uv(0.25, 0.25)), 0.0);
+        assert_eq!(checker.evaluate(&hit_at_uv(0.75, 0.25)), 1.0);
+        assert_eq!(checker.evaluate(&hit_at_uv(0.75, 0.75)), 0.0);
+        assert_eq!(checker.evaluate(&hit_at_uv(-0.25, 0.25)), 1.0);
+
+        checker.set_mapping(Mapping::Position);
+        assert_eq!(checker.evaluate(&hit_at_position(0.25, 0.25, 0.75)), 1.0);
+    }
+
+    #[test]
+    fn stripes() {
+        let stripes = Stripes::new(color::Color::new(1.0, 0.0, 0.0), color::Color::new(0.0, 0.0, 1.0), vector3::Axis::Y);
+
+        assert_eq!(stripes.evaluate(&hit_at_uv(0.0, 0.5)).red, 1.0);
+        assert_eq!(stripes.evaluate(&hit_at_uv(5.0, 1.5)).blue, 1.0);
+        assert_eq!(stripes.evaluate(&hit_at_uv(0.0, 2.5)).red, 1.0);
+    }
+
+    #[test]
+    fn gradient() {
+        let mut gradient = Gradient::new(0.0, 10.0, vector3::Axis::Z);
+        gradient.set_range(-1.0, 1.0);
+        gradient.set_mapping(Mapping::Position);
+
+        assert_approx_eq!(gradient.evaluate(&hit_at_position(0.0, 0.0, 0.0)), 5.0, 1e-9f64);
+        assert_approx_eq!(gradient.evaluate(&hit_at_position(0.0, 0.0, 0.5)), 7.5, 1e-9f64);
+        assert_eq!(gradient.evaluate(&hit_at_position(0.0, 0.0, -3.0)), 0.0);
+        assert_eq!(gradient.evaluate(&hit_at_position(0.0, 0.0, 3.0)), 10.0);
+    }
+
+    #[test]
+    fn noise_range_and_continuity() {
+        for kind in [NoiseKind::Value, NoiseKind::Perlin] {
+            let mut noise = Noise::new(0.0, 1.0, kind);
+            noise.set_frequency(3.0);
+            noise.set_octaves(4);
+            noise.set_mapping(Mapping::Position);
+
+            let mut minimum = f64::INFINITY;
+            let mut maximum = f64::NEG_INFINITY;
+
+            for i in 0..1000 {
+                let x = i as f64 * 0.0137 - 5.0;
+                let value = noise.evaluate(&hit_at_position(x, 0.3 * x, 1.7));
+
+                assert!((0.0..=1.0).contains(&value));
+                minimum = minimum.min(value);
+                maximum = maximum.max(value);
+
+                // nearby points have nearby values
+                let nearby = noise.evaluate(&hit_at_position(x + 1e-6, 0.3 * x, 1.7));
+                assert!((value - nearby).abs() < 1e-3, "{:?} jumps from {} to {}", kind, value, nearby);
+
+                // and evaluating again gives the same value
+                assert_eq!(noise.evaluate(&hit_at_position(x, 0.3 * x, 1.7)), value);
+            }
+
+            // the noise isn't flat
+            assert!(maximum - minimum > 0.3, "{:?} only spans {} to {}", kind, minimum, maximum);
+        }
+    }
+
+    #[test]
+    fn noise_seed() {
+        let mut noise = Noise::new(0.0, 1.0, NoiseKind::Value);
+        let hit = hit_at_uv(0.3, 0.6);
+        let unseeded = noise.evaluate(&hit);
+
+        noise.set_seed(7);
+        assert_ne!(noise.evaluate(&hit), unseeded);
+    }
+
+    #[test]
+    fn perlin_noise_is_zero_at_lattice_points() {
+        for point in [[0.0, 0.0, 0.0], [1.0, -2.0, 3.0], [-4.0, 5.0, -6.0]] {
+            assert_eq!(perlin_noise(&vector3::Vector3::new(point[0], point[1], point[2]), 3), 0.0);
+        }
+    }
+
+    #[test]
+    fn compose() {
+        let mut checker = Checker::new(0.0, 1.0);
+        checker.set_scale(2.0);
+
+        // mix a constant red and a blue gradient by the checker
+        let mix = Mix::new(
+            color::Color::new(1.0, 0.0, 0.0),
+            Gradient::new(color::Color::default(), color::Color::new(0.0, 0.0, 1.0), vector3::Axis::X),
+            checker,
+        );
+
+        let red = mix.evaluate(&hit_at_uv(0.25, 0.25));
+        assert_eq!((red.red, red.blue), (1.0, 0.0));
+
+        let blue = mix.evaluate(&hit_at_uv(0.75, 0.25));
+        assert_eq!((blue.red, blue.blue), (0.0, 0.75));
+
+        let offset = Offset::new(Scale::new(mix, 0.5), color::Color::new(0.0, 0.25, 0.0));
+        let color = offset.evaluate(&hit_at_uv(0.75, 0.25));
+        assert_eq!((color.red, color.green, color.blue), (0.0, 0.25, 0.375));
+    }
+}
@@ -0,0 +1,212 @@
+use strum_macros::EnumString;
+
+use crate::{color, film, image, pixel};
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ToneMapOperator {
+    // clips every channel to 1.0
+    Clamp,
+    // compresses every channel with x / (1 + x), so nothing ever saturates
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve, with a toe and a soft shoulder
+    Aces,
+}
+
+// ToneMapper turns the HDR radiance accumulated on a film into a displayable sRGB image
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapper {
+    pub operator: ToneMapOperator,
+    // exposure compensation in stops, each one doubling the radiance before the operator is applied
+    pub exposure: f64,
+}
+
+impl ToneMapper {
+    pub fn new(operator: ToneMapOperator, exposure: f64) -> Self {
+        ToneMapper {
+            operator,
+            exposure,
+        }
+    }
+
+    // map applies the exposure and the operator to a linear radiance, returning linear values in [0, 1]
+    pub fn map(&self, radiance: &color::Color) -> color::Color {
+        let scale = f64::powf(2.0, self.exposure);
+
+        color::Color::new(
+            self.map_channel(radiance.red * scale),
+            self.map_channel(radiance.green * scale),
+            self.map_channel(radiance.blue * scale),
+        )
+    }
+
+    fn map_channel(&self, value: f64) -> f64 {
+        let value = value.max(0.0);
+
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => value,
+            ToneMapOperator::Reinhard => value / (1.0 + value),
+            ToneMapOperator::Aces => (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14),
+        };
+
+        mapped.clamp(0.0, 1.0)
+    }
+
+    // to_image tone maps every pixel of a film and encodes it with the sRGB transfer function, pixels
+    // that no photon reached are left transparent
+    pub fn to_image(&self, film: &film::Film) -> image::Image {
+        let mut image = image::Image::new(film.get_width(), film.get_height());
+
+        for y in 0..film.get_height() {
+            for x in 0..film.get_width() {
+                let mapped = self.map(&film.get_radiance(x, y));
+
+                let mut pixel = pixel::Pixel::from_color(&srgb_encode(&mapped));
+                if film.get_sample_count(x, y) == 0 {
+                    pixel.alpha = 0;
+                }
+
+                image.set_pixel(x, y, pixel);
+            }
+        }
+
+        image
+    }
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        ToneMapper::new(ToneMapOperator::Clamp, 0.0)
+    }
+}
+
+// srgb_encode applies the sRGB transfer function to a linear color in [0, 1]
+pub fn srgb_encode(linear: &color::Color) -> color::Color {
+    color::Color::new(
+        srgb_encode_channel(linear.red),
+        srgb_encode_channel(linear.green),
+        srgb_encode_channel(linear.blue),
+    )
+}
+
+fn srgb_encode_channel(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * f64::powf(value, 1.0 / 2.4) - 0.055
+    }
+}
+
+// srgb_decode inverts srgb_encode, turning an sRGB encoded color in [0, 1] back into a linear one
+pub fn srgb_decode(encoded: &color::Color) -> color::Color {
+    color::Color::new(
+        srgb_decode_channel(encoded.red),
+        srgb_decode_channel(encoded.green),
+        srgb_decode_channel(encoded.blue),
+    )
+}
+
+fn srgb_decode_channel(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        f64::powf((value + 0.055) / 1.055, 2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn operator_from_str() {
+        assert_eq!(ToneMapOperator::from_str("clamp").unwrap(), ToneMapOperator::Clamp);
+        assert_eq!(ToneMapOperator::from_str("reinhard").unwrap(), ToneMapOperator::Reinhard);
+        assert_eq!(ToneMapOperator::from_str("aces").unwrap(), ToneMapOperator::Aces);
+        assert!(ToneMapOperator::from_str("filmic").is_err());
+    }
+
+    #[test]
+    fn clamp() {
+        let tone_mapper = ToneMapper::new(ToneMapOperator::Clamp, 0.0);
+        let mapped = tone_mapper.map(&color::Color::new(0.25, 1000.0, -1.0));
+
+        assert_eq!(mapped.red, 0.25);
+        assert_eq!(mapped.green, 1.0);
+        assert_eq!(mapped.blue, 0.0);
+    }
+
+    #[test]
+    fn reinhard() {
+        let tone_mapper = ToneMapper::new(ToneMapOperator::Reinhard, 0.0);
+        let mapped = tone_mapper.map(&color::Color::new(1.0, 3.0, 1000.0));
+
+        assert_approx_eq!(mapped.red, 0.5, 1e-9f64);
+        assert_approx_eq!(mapped.green, 0.75, 1e-9f64);
+        assert!(mapped.blue < 1.0);
+    }
+
+    #[test]
+    fn aces() {
+        let tone_mapper = ToneMapper::new(ToneMapOperator::Aces, 0.0);
+        let mapped = tone_mapper.map(&color::Color::new(0.0, 0.18, 1000.0));
+
+        assert_eq!(mapped.red, 0.0);
+        assert!(mapped.green > 0.0 && mapped.green < 0.5);
+        assert_approx_eq!(mapped.blue, 1.0, 1e-3f64);
+    }
+
+    #[test]
+    fn exposure() {
+        let tone_mapper = ToneMapper::new(ToneMapOperator::Clamp, 2.0);
+        let mapped = tone_mapper.map(&color::Color::new(0.1, 0.2, 0.3));
+
+        assert_approx_eq!(mapped.red, 0.4, 1e-9f64);
+        assert_approx_eq!(mapped.green, 0.8, 1e-9f64);
+        assert_approx_eq!(mapped.blue, 1.0, 1e-9f64);
+
+        let tone_mapper = ToneMapper::new(ToneMapOperator::Clamp, -1.0);
+        assert_approx_eq!(tone_mapper.map(&color::Color::new(0.5, 0.5, 0.5)).red, 0.25, 1e-9f64);
+    }
+
+    #[test]
+    fn srgb() {
+        let encoded = srgb_encode(&color::Color::new(0.0, 0.001, 1.0));
+
+        assert_eq!(encoded.red, 0.0);
+        assert_approx_eq!(encoded.green, 0.01292, 1e-9f64);
+        assert_approx_eq!(encoded.blue, 1.0, 1e-9f64);
+        assert_approx_eq!(srgb_encode(&color::Color::new(0.5, 0.5, 0.5)).red, 0.7354, 1e-4f64);
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        for value in [0.0, 0.001, 0.2, 0.5, 1.0] {
+            let decoded = srgb_decode(&srgb_encode(&color::Color::new(value, value, value)));
+
+            assert_approx_eq!(decoded.red, value, 1e-9f64);
+        }
+    }
+
+    #[test]
+    fn film_to_image() {
+        let mut film = film::Film::new(2, 1);
+        film.add_photons(1);
+        film.add_sample(0, 0, color::Color::new(1000.0, 1.0, 0.0));
+
+        let image = ToneMapper::default().to_image(&film);
+
+        let lit = image.get_pixel(0, 0);
+        assert_eq!(lit.red, pixel::Pixel::MAX);
+        assert_eq!(lit.green, pixel::Pixel::MAX);
+        assert_eq!(lit.blue, 0);
+        assert_eq!(lit.alpha, pixel::Pixel::MAX);
+
+        let dark = image.get_pixel(1, 0);
+        assert_eq!(dark.red, 0);
+        assert_eq!(dark.alpha, 0);
+    }
+}
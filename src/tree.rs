@@ -1,3 +1,7 @@
+use std::io;
+use std::ops;
+
+use crate::binary;
 use crate::bounds;
 use crate::hit;
 use crate::pyramid;
@@ -13,12 +17,83 @@ pub trait Pivotable {
     fn get_pivot_component(&self, axis: &vector3::Axis) -> f64;
 }
 
-pub trait Intersectable {
+pub trait Intersectable: Sized {
     fn ray_intersects(&self, ray: &ray::Ray) -> Option<hit::Hit>;
+
+    // nearest_ray_intersection returns the nearest hit of the ray with any of the objects, types that can
+    // intersect several objects at once override it
+    fn nearest_ray_intersection(objects: &[Self], ray: &ray::Ray) -> Option<hit::Hit> {
+        let mut closest: Option<hit::Hit> = None;
+
+        for hit in objects.iter().filter_map(|object| object.ray_intersects(ray)) {
+            if closest.is_none_or(|closest_hit| hit.distance < closest_hit.distance) {
+                closest = Some(hit);
+            }
+        }
+
+        closest
+    }
+
+    // occluded_by returns whether any of the objects is hit at a distance in (0, max_distance)
+    fn occluded_by(objects: &[Self], ray: &ray::Ray, max_distance: f64) -> bool {
+        objects.iter().any(|object| {
+            object.ray_intersects(ray).is_some_and(|hit| hit.distance > 0.0 && hit.distance < max_distance)
+        })
+    }
+}
+
+pub trait NodeObject: Bounded + Pivotable + Copy {}
+impl<T> NodeObject for T where T: Bounded + Pivotable + Copy {}
+
+// SahSettings configures the binned surface area heuristic builder
+#[derive(Clone, Copy, Debug)]
+pub struct SahSettings {
+    // number of bins along each axis that the split planes are chosen from
+    pub bin_count: usize,
+    // nodes with more objects than this are always split, smaller ones only when the split is cheaper
+    pub leaf_size: usize,
+    // relative cost of visiting a child node and of intersecting a single object
+    pub traversal_cost: f64,
+    pub intersection_cost: f64,
+}
+
+impl Default for SahSettings {
+    fn default() -> Self {
+        SahSettings {
+            bin_count: 16,
+            leaf_size: 4,
+            traversal_cost: 1.0,
+            intersection_cost: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum TreeBuilder {
+    // splits at the median pivot along a cycling axis until nodes have at most page_size objects
+    Median { page_size: usize },
+    // splits at the plane with the lowest surface area heuristic cost
+    Sah(SahSettings),
 }
 
-pub trait NodeObject: Bounded + Pivotable + Intersectable + Copy {}
-impl<T> NodeObject for T where T: Bounded + Pivotable + Intersectable + Copy {}
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TreeStatistics {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    // number of nodes on the longest path from the root to a leaf
+    pub depth: usize,
+    // objects in all nodes, including the ones the median builder keeps in interior nodes
+    pub object_count: usize,
+    // occupancy of the leaves
+    pub min_leaf_objects: usize,
+    pub max_leaf_objects: usize,
+    pub mean_leaf_objects: f64,
+}
+
+struct SahBin {
+    bounds: Option<bounds::Bounds>,
+    count: usize,
+}
 
 struct Node<T: NodeObject> {
     left: Option<Box<Node<T>>>,
@@ -46,23 +121,86 @@ impl<T: NodeObject> Default for Node<T> {
 }
 
 impl<T: NodeObject> Node<T> {
-    fn count(&self) -> usize {
-        1 +
-            self.left.as_ref().map_or(0, |l| l.count()) +
-            self.right.as_ref().map_or(0, |r| r.count())
+    fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
+    // accumulate_statistics adds this node and the ones below it, returning the objects in the leaves
+    fn accumulate_statistics(&self, depth: usize, statistics: &mut TreeStatistics) -> usize {
+        statistics.node_count += 1;
+        statistics.depth = statistics.depth.max(depth);
+        statistics.object_count += self.contents.len();
+
+        if self.is_leaf() {
+            statistics.min_leaf_objects = if statistics.leaf_count == 0 {
+                self.contents.len()
+            } else {
+                statistics.min_leaf_objects.min(self.contents.len())
+            };
+            statistics.max_leaf_objects = statistics.max_leaf_objects.max(self.contents.len());
+            statistics.leaf_count += 1;
+
+            return self.contents.len();
+        }
+
+        self.left.as_ref().map_or(0, |l| l.accumulate_statistics(depth + 1, statistics)) +
+            self.right.as_ref().map_or(0, |r| r.accumulate_statistics(depth + 1, statistics))
+    }
+
+    // wide_children returns the nodes that become the children of this node in the flattened tree, which
+    // are its grandchildren except for the children that are leaves. The objects of the children that are
+    // skipped over are moved up into this node.
+    fn wide_children(&mut self) -> Vec<Node<T>> {
+        let mut children = Vec::with_capacity(WIDTH);
+
+        for child in [self.left.take(), self.right.take()].into_iter().flatten() {
+            let mut child = *child;
+
+            if child.is_leaf() {
+                children.push(child);
+            } else {
+                self.contents.append(&mut child.contents);
+                children.extend([child.left.take(), child.right.take()].into_iter().flatten().map(|grandchild| *grandchild));
+            }
+        }
+
+        children
     }
+}
 
-    fn depth(&self) -> usize {
-        let mut depth: usize = 1;
-        depth = self.left.as_ref().map_or(0, |l| l.depth() + 1).max(depth);
-        depth = self.right.as_ref().map_or(0, |r| r.depth() + 1).max(depth);
+// number of children of a node of the flattened tree
+const WIDTH: usize = 4;
+
+// LinearNode is a node of the flattened tree, which merges every two levels of the built tree into one so
+// that a ray is tested against the bounds of up to four children at once. The nodes are stored depth first
+// and the objects of every node are a contiguous range of the tree's objects.
+#[derive(Clone, Copy)]
+struct LinearNode {
+    child_bounds: bounds::Bounds4,
+    // indices of the children, of which the first child_bounds.count() are used
+    children: [u32; WIDTH],
+    object_offset: u32,
+    object_count: u32,
+}
 
-        depth
+impl LinearNode {
+    fn children(&self) -> &[u32] {
+        &self.children[..self.child_bounds.count()]
+    }
+
+    fn object_range(&self) -> ops::Range<usize> {
+        self.object_offset as usize..(self.object_offset + self.object_count) as usize
     }
 }
 
+// Tree is built as a binary tree of boxed nodes, which it then flattens into an array of four-wide nodes
+// and an array of objects that are traversed by index
 pub struct Tree<T: NodeObject> {
-    root: Node<T>,
+    m_nodes: Vec<LinearNode>,
+    m_objects: Vec<T>,
+    m_bounds: bounds::Bounds,
+    // statistics of the built binary tree
+    m_statistics: TreeStatistics,
     m_page_size: usize,
 }
 
@@ -72,10 +210,66 @@ impl<T: NodeObject> Tree<T> {
     }
 
     pub fn new_with_page_size(objects: Vec<T>, page_size: usize) -> Tree<T> {
-        Tree {
-            root: Tree::generate(objects, &vector3::Axis::X, &page_size),
+        Tree::new_with_builder(objects, TreeBuilder::Median { page_size })
+    }
+
+    pub fn new_with_builder(objects: Vec<T>, builder: TreeBuilder) -> Tree<T> {
+        let (root, page_size) = Tree::build(objects, builder);
+        Tree::from_root(root, page_size)
+    }
+
+    // build returns the root of the binary tree the builder builds, and the largest number of objects it
+    // keeps in a leaf
+    fn build(objects: Vec<T>, builder: TreeBuilder) -> (Node<T>, usize) {
+        match builder {
+            TreeBuilder::Median { page_size } => (Tree::generate(objects, &vector3::Axis::X, &page_size), page_size),
+            TreeBuilder::Sah(settings) => (Tree::generate_sah(objects, &settings), settings.leaf_size),
+        }
+    }
+
+    fn from_root(root: Node<T>, page_size: usize) -> Tree<T> {
+        let mut statistics = TreeStatistics::default();
+        let leaf_objects = root.accumulate_statistics(1, &mut statistics);
+
+        if statistics.leaf_count > 0 {
+            statistics.mean_leaf_objects = leaf_objects as f64 / statistics.leaf_count as f64;
+        }
+
+        let mut tree = Tree {
+            m_nodes: Vec::new(),
+            m_objects: Vec::new(),
+            m_bounds: root.bounds,
+            m_statistics: statistics,
             m_page_size: page_size,
+        };
+
+        tree.flatten(root);
+
+        tree
+    }
+
+    // flatten appends the node and then the nodes below it depth first, returning the node's index
+    fn flatten(&mut self, mut node: Node<T>) -> usize {
+        let index = self.m_nodes.len();
+        let children = node.wide_children();
+
+        self.m_nodes.push(LinearNode {
+            child_bounds: bounds::Bounds4::new(&children.iter().map(|child| child.bounds).collect::<Vec<_>>()),
+            children: [0; WIDTH],
+            object_offset: self.m_objects.len() as u32,
+            object_count: node.contents.len() as u32,
+        });
+        self.m_objects.extend(node.contents);
+
+        for (i, child) in children.into_iter().enumerate() {
+            self.m_nodes[index].children[i] = self.flatten(child) as u32;
         }
+
+        index
+    }
+
+    pub fn statistics(&self) -> TreeStatistics {
+        self.m_statistics
     }
 
     fn generate(objects: Vec<T>, axis: &vector3::Axis, page_size: &usize) -> Node<T> {
@@ -150,6 +344,146 @@ impl<T: NodeObject> Tree<T> {
         }
     }
 
+    fn generate_sah(objects: Vec<T>, settings: &SahSettings) -> Node<T> {
+        if objects.is_empty() {
+            return Node::default();
+        }
+
+        let mut bounds = objects[0].get_bounds();
+        let mut centroid_bounds = bounds::Bounds::from_vector(objects[0].get_pivot());
+
+        for object in objects.iter() {
+            bounds += object.get_bounds();
+            centroid_bounds += bounds::Bounds::from_vector(object.get_pivot());
+        }
+
+        let leaf = |objects: Vec<T>, bounds: bounds::Bounds| Node {
+            left: None,
+            right: None,
+            contents: objects,
+            axis: vector3::Axis::X,
+            pivot: 0.0,
+            bounds,
+            depth: 0,
+        };
+
+        if objects.len() == 1 {
+            return leaf(objects, bounds);
+        }
+
+        let Some((axis, split_bin, split_cost)) = Self::best_sah_split(&objects, &bounds, &centroid_bounds, settings) else {
+            // every centroid is in the same place, so no plane can separate the objects
+            return leaf(objects, bounds);
+        };
+
+        let leaf_cost = settings.intersection_cost * objects.len() as f64;
+
+        if objects.len() <= settings.leaf_size && split_cost >= leaf_cost {
+            return leaf(objects, bounds);
+        }
+
+        let limits = centroid_bounds.get_limits(&axis);
+        let pivot = limits.min + (limits.max - limits.min) * (split_bin + 1) as f64 / settings.bin_count as f64;
+
+        let (left_objects, right_objects): (Vec<T>, Vec<T>) = objects
+            .into_iter()
+            .partition(|object| Self::sah_bin(object, &axis, &centroid_bounds, settings.bin_count) <= split_bin);
+
+        Node {
+            left: Some(Box::new(Tree::generate_sah(left_objects, settings))),
+            right: Some(Box::new(Tree::generate_sah(right_objects, settings))),
+            contents: Vec::<T>::new(),
+            axis,
+            pivot,
+            bounds,
+            depth: 0,
+        }
+    }
+
+    // best_sah_split returns the axis and the last bin on the left of the cheapest split plane with its cost
+    fn best_sah_split(
+        objects: &[T],
+        bounds: &bounds::Bounds,
+        centroid_bounds: &bounds::Bounds,
+        settings: &SahSettings,
+    ) -> Option<(vector3::Axis, usize, f64)> {
+        let bin_count = settings.bin_count.max(2);
+        let parent_area = bounds.surface_area();
+        let mut best: Option<(vector3::Axis, usize, f64)> = None;
+
+        for axis in [vector3::Axis::X, vector3::Axis::Y, vector3::Axis::Z] {
+            let limits = centroid_bounds.get_limits(&axis);
+
+            if limits.max - limits.min <= f64::EPSILON {
+                continue;
+            }
+
+            let mut bins: Vec<SahBin> = (0..bin_count).map(|_| SahBin { bounds: None, count: 0 }).collect();
+
+            for object in objects {
+                let bin = &mut bins[Self::sah_bin(object, &axis, centroid_bounds, bin_count)];
+                bin.count += 1;
+                match &mut bin.bounds {
+                    Some(bin_bounds) => *bin_bounds += object.get_bounds(),
+                    None => bin.bounds = Some(object.get_bounds()),
+                }
+            }
+
+            // sweep from the right to get the area and count on the right of every plane
+            let mut right_areas = vec![0.0; bin_count];
+            let mut right_counts = vec![0; bin_count];
+            let mut running: Option<bounds::Bounds> = None;
+            let mut count = 0;
+
+            for i in (1..bin_count).rev() {
+                Self::extend_bounds(&mut running, &bins[i].bounds);
+                count += bins[i].count;
+                right_areas[i - 1] = running.as_ref().map_or(0.0, |b| b.surface_area());
+                right_counts[i - 1] = count;
+            }
+
+            let mut running: Option<bounds::Bounds> = None;
+            let mut count = 0;
+
+            for i in 0..bin_count - 1 {
+                Self::extend_bounds(&mut running, &bins[i].bounds);
+                count += bins[i].count;
+
+                if count == 0 || right_counts[i] == 0 {
+                    continue;
+                }
+
+                let left_area = running.as_ref().map_or(0.0, |b| b.surface_area());
+                let weighted = left_area * count as f64 + right_areas[i] * right_counts[i] as f64;
+                let relative = if parent_area > f64::EPSILON { weighted / parent_area } else { objects.len() as f64 };
+                let cost = settings.traversal_cost + settings.intersection_cost * relative;
+
+                if best.as_ref().is_none_or(|(_, _, best_cost)| cost < *best_cost) {
+                    best = Some((axis, i, cost));
+                }
+            }
+        }
+
+        best
+    }
+
+    fn sah_bin(object: &T, axis: &vector3::Axis, centroid_bounds: &bounds::Bounds, bin_count: usize) -> usize {
+        let bin_count = bin_count.max(2);
+        let limits = centroid_bounds.get_limits(axis);
+        let offset = (object.get_pivot_component(axis) - limits.min) / (limits.max - limits.min);
+
+        ((offset * bin_count as f64) as usize).min(bin_count - 1)
+    }
+
+    fn extend_bounds(running: &mut Option<bounds::Bounds>, other: &Option<bounds::Bounds>) {
+        if let Some(other) = other {
+            match running {
+                Some(running) => *running += *other,
+                None => *running = Some(*other),
+            }
+        }
+    }
+
     // Find the next non-zero bound, if available
     // TODO(cdelguercio for ignotuscaligo): this implementation differs from the C++ version in that
     // it checks if the current axis is valid LAST instead of FIRST. I'm not sure which one is correct.
@@ -182,64 +516,317 @@ impl<T: NodeObject> Tree<T> {
         values[values.len() / 2]
     }
 
-    pub fn cast_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
-        cast_buffer.clear();
+    // get_bounds returns the bounds of all of the objects in the tree
+    pub fn get_bounds(&self) -> bounds::Bounds {
+        self.m_bounds
+    }
 
-        Self::cast_ray_into_node(ray, &self.root, cast_buffer);
+    // objects returns all of the objects in the tree in storage order
+    pub fn objects(&self) -> &[T] {
+        &self.m_objects
+    }
 
-        let min_distance = f64::INFINITY;
-        let mut result: Option<hit::Hit> = None;
+    // cast_ray_with returns the nearest hit along the ray, intersecting the objects with intersect, which
+    // must return distances along the same ray
+    pub fn cast_ray_with(&self, ray: &ray::Ray, mut intersect: impl FnMut(&T) -> Option<hit::Hit>) -> Option<hit::Hit> {
+        self.cast_ray_with_objects(ray, |objects| {
+            let mut closest: Option<hit::Hit> = None;
 
-        for hit in cast_buffer {
-            if hit.distance < min_distance {
-                result = Some(hit.clone());
+            for hit in objects.iter().filter_map(&mut intersect) {
+                if closest.is_none_or(|closest_hit| hit.distance < closest_hit.distance) {
+                    closest = Some(hit);
+                }
             }
+
+            closest
+        })
+    }
+
+    // occluded_with returns whether occludes holds for any object in a node the ray enters before
+    // max_distance, stopping at the first one
+    pub fn occluded_with(&self, ray: &ray::Ray, max_distance: f64, occludes: impl Fn(&T) -> bool) -> bool {
+        self.occluded_with_objects(ray, max_distance, |objects| objects.iter().any(&occludes))
+    }
+
+    // cast_ray_with_objects is cast_ray_with for intersect functions that return the nearest hit of all of the
+    // objects of a node
+    pub fn cast_ray_with_objects(&self, ray: &ray::Ray, mut intersect: impl FnMut(&[T]) -> Option<hit::Hit>) -> Option<hit::Hit> {
+        let mut closest: Option<hit::Hit> = None;
+
+        if self.m_bounds.ray_intersects(ray) {
+            self.cast_ray_into_node(ray, ray.direction.norm(), 0, &mut intersect, &mut closest);
         }
 
-        result
+        closest
+    }
+
+    // occluded_with_objects is occluded_with for occludes functions that test all of the objects of a node
+    pub fn occluded_with_objects(&self, ray: &ray::Ray, max_distance: f64, occludes: impl Fn(&[T]) -> bool) -> bool {
+        let direction_norm = ray.direction.norm();
+
+        self.m_bounds.ray_entry_distance(ray).is_some_and(|entry_distance| entry_distance * direction_norm < max_distance) &&
+            self.occluded_in_node(ray, direction_norm, max_distance, 0, &occludes)
     }
 
     pub fn fetch_within_pyramid(&self, pyramid: &pyramid::Pyramid) -> Vec<T> {
         let mut objects: Vec<T> = Vec::new();
 
-        Self::fetch_within_pyramid_from_node(pyramid, &self.root, &mut objects);
+        if pyramid.intersects_bounds(&self.m_bounds) {
+            self.fetch_within_pyramid_from_node(pyramid, 0, &mut objects);
+        }
 
         objects
     }
 
-    fn cast_ray_into_node(ray: &ray::Ray, node: &Node<T>, hits: &mut Vec<hit::Hit>) {
-        if node.bounds.ray_intersects(ray) {
-            for content in &node.contents {
-                if let Some(hit) = content.ray_intersects(ray) {
-                    hits.push(hit);
-                }
+    // cast_ray_into_node intersects the objects of a node the ray enters and then visits its children front
+    // to back, skipping the ones that the ray enters beyond the closest hit found so far
+    fn cast_ray_into_node(
+        &self,
+        ray: &ray::Ray,
+        direction_norm: f64,
+        index: usize,
+        intersect: &mut impl FnMut(&[T]) -> Option<hit::Hit>,
+        closest: &mut Option<hit::Hit>,
+    ) {
+        let node = &self.m_nodes[index];
+
+        if let Some(hit) = intersect(&self.m_objects[node.object_range()]) {
+            if closest.is_none_or(|closest_hit| hit.distance < closest_hit.distance) {
+                *closest = Some(hit);
             }
+        }
+
+        let entry_distances = node.child_bounds.ray_entry_distances(ray);
 
-            if let Some(left) = &node.left {
-                Self::cast_ray_into_node(ray, left, hits);
+        let mut entered = [(0.0, 0); WIDTH];
+        let mut entered_count = 0;
+
+        for (&child, entry_distance) in node.children().iter().zip(entry_distances) {
+            if let Some(entry_distance) = entry_distance {
+                entered[entered_count] = (entry_distance * direction_norm, child as usize);
+                entered_count += 1;
             }
+        }
 
-            if let Some(right) = &node.right {
-                Self::cast_ray_into_node(ray, right, hits);
+        let entered = &mut entered[..entered_count];
+        entered.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        for &(entry_distance, child) in entered.iter() {
+            if closest.is_some_and(|hit| entry_distance > hit.distance) {
+                break;
             }
+
+            self.cast_ray_into_node(ray, direction_norm, child, intersect, closest);
         }
     }
 
-    fn fetch_within_pyramid_from_node(pyramid: &pyramid::Pyramid, node: &Node<T>, objects: &mut Vec<T>) {
-        if pyramid.intersects_bounds(&node.bounds) {
-            for content in &node.contents {
-                if pyramid.contains_point(&content.get_pivot()) {
-                    objects.push(*content);
+    fn occluded_in_node(
+        &self,
+        ray: &ray::Ray,
+        direction_norm: f64,
+        max_distance: f64,
+        index: usize,
+        occludes: &impl Fn(&[T]) -> bool,
+    ) -> bool {
+        let node = &self.m_nodes[index];
+
+        if occludes(&self.m_objects[node.object_range()]) {
+            return true;
+        }
+
+        node.children()
+            .iter()
+            .zip(node.child_bounds.ray_entry_distances(ray))
+            .any(|(&child, entry_distance)| {
+                entry_distance.is_some_and(|d| d * direction_norm < max_distance) &&
+                    self.occluded_in_node(ray, direction_norm, max_distance, child as usize, occludes)
+            })
+    }
+
+    fn fetch_within_pyramid_from_node(&self, pyramid: &pyramid::Pyramid, index: usize, objects: &mut Vec<T>) {
+        let node = &self.m_nodes[index];
+
+        for object in &self.m_objects[node.object_range()] {
+            if pyramid.contains_point(&object.get_pivot()) {
+                objects.push(*object);
+            }
+        }
+
+        for (lane, &child) in node.children().iter().enumerate() {
+            if pyramid.intersects_bounds(&node.child_bounds.get(lane)) {
+                self.fetch_within_pyramid_from_node(pyramid, child as usize, objects);
+            }
+        }
+    }
+
+    // write_to stores the flattened tree, writing its objects with write_object, so that it can be read back
+    // without building it again
+    pub fn write_to<W: io::Write>(&self, writer: &mut W, write_object: impl Fn(&mut W, &T) -> io::Result<()>) -> io::Result<()> {
+        binary::write_bounds(writer, &self.m_bounds)?;
+        binary::write_u64(writer, self.m_page_size as u64)?;
+
+        for value in [
+            self.m_statistics.node_count,
+            self.m_statistics.leaf_count,
+            self.m_statistics.depth,
+            self.m_statistics.object_count,
+            self.m_statistics.min_leaf_objects,
+            self.m_statistics.max_leaf_objects,
+        ] {
+            binary::write_u64(writer, value as u64)?;
+        }
+        binary::write_f64(writer, self.m_statistics.mean_leaf_objects)?;
+
+        binary::write_u32(writer, self.m_nodes.len() as u32)?;
+        for node in &self.m_nodes {
+            binary::write_u32(writer, node.child_bounds.count() as u32)?;
+            for (lane, &child) in node.children().iter().enumerate() {
+                binary::write_bounds(writer, &node.child_bounds.get(lane))?;
+                binary::write_u32(writer, child)?;
+            }
+            binary::write_u32(writer, node.object_offset)?;
+            binary::write_u32(writer, node.object_count)?;
+        }
+
+        binary::write_u32(writer, self.m_objects.len() as u32)?;
+        for object in &self.m_objects {
+            write_object(writer, object)?;
+        }
+
+        Ok(())
+    }
+
+    // read_from reads a tree stored by write_to, checking that the nodes only refer to nodes after them and
+    // to objects that exist
+    pub fn read_from<R: io::Read>(reader: &mut R, read_object: impl Fn(&mut R) -> io::Result<T>) -> io::Result<Tree<T>> {
+        let bounds = binary::read_bounds(reader)?;
+        let page_size = binary::read_u64(reader)? as usize;
+
+        let mut counts = [0; 6];
+        for count in &mut counts {
+            *count = binary::read_u64(reader)? as usize;
+        }
+        let statistics = TreeStatistics {
+            node_count: counts[0],
+            leaf_count: counts[1],
+            depth: counts[2],
+            object_count: counts[3],
+            min_leaf_objects: counts[4],
+            max_leaf_objects: counts[5],
+            mean_leaf_objects: binary::read_f64(reader)?,
+        };
+
+        let node_count = binary::read_u32(reader)? as usize;
+        if node_count == 0 {
+            return Err(binary::invalid_data("tree has no nodes"));
+        }
+
+        let mut nodes = Vec::<LinearNode>::new();
+        for index in 0..node_count {
+            let child_count = binary::read_u32(reader)? as usize;
+            if child_count > WIDTH {
+                return Err(binary::invalid_data(&format!("node {} has {} children", index, child_count)));
+            }
+
+            let mut child_bounds = Vec::<bounds::Bounds>::with_capacity(child_count);
+            let mut children = [0; WIDTH];
+            for child in children.iter_mut().take(child_count) {
+                child_bounds.push(binary::read_bounds(reader)?);
+                *child = binary::read_u32(reader)?;
+
+                if *child as usize <= index || *child as usize >= node_count {
+                    return Err(binary::invalid_data(&format!("node {} has child {} out of order", index, child)));
                 }
             }
 
-            if let Some(left) = &node.left {
-                Self::fetch_within_pyramid_from_node(pyramid, left, objects);
+            nodes.push(LinearNode {
+                child_bounds: bounds::Bounds4::new(&child_bounds),
+                children,
+                object_offset: binary::read_u32(reader)?,
+                object_count: binary::read_u32(reader)?,
+            });
+        }
+
+        let object_count = binary::read_u32(reader)? as usize;
+        let mut objects = Vec::<T>::new();
+        for _ in 0..object_count {
+            objects.push(read_object(reader)?);
+        }
+
+        for (index, node) in nodes.iter().enumerate() {
+            if node.object_offset as usize + node.object_count as usize > object_count {
+                return Err(binary::invalid_data(&format!("node {} has objects out of range", index)));
+            }
+        }
+
+        Ok(Tree {
+            m_nodes: nodes,
+            m_objects: objects,
+            m_bounds: bounds,
+            m_statistics: statistics,
+            m_page_size: page_size,
+        })
+    }
+}
+
+impl<T: NodeObject + Intersectable> Tree<T> {
+    // cast_ray returns the nearest hit along the ray
+    pub fn cast_ray(&self, ray: &ray::Ray) -> Option<hit::Hit> {
+        self.cast_ray_with_objects(ray, |objects| T::nearest_ray_intersection(objects, ray))
+    }
+
+    // occluded returns whether any object is hit at a distance in (0, max_distance), stopping at the first one
+    pub fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.occluded_with_objects(ray, max_distance, |objects| T::occluded_by(objects, ray, max_distance))
+    }
+}
+
+// BoxedTree is the binary tree of boxed nodes that Tree flattens, kept as it is built so that benches can
+// compare casting rays through it against the flattened layout
+#[doc(hidden)]
+pub struct BoxedTree<T: NodeObject> {
+    m_root: Node<T>,
+}
+
+impl<T: NodeObject + Intersectable> BoxedTree<T> {
+    pub fn new_with_builder(objects: Vec<T>, builder: TreeBuilder) -> BoxedTree<T> {
+        BoxedTree {
+            m_root: Tree::build(objects, builder).0,
+        }
+    }
+
+    // cast_ray returns the nearest hit along the ray
+    pub fn cast_ray(&self, ray: &ray::Ray) -> Option<hit::Hit> {
+        let mut closest: Option<hit::Hit> = None;
+
+        if self.m_root.bounds.ray_intersects(ray) {
+            BoxedTree::cast_ray_into_node(&self.m_root, ray, ray.direction.norm(), &mut closest);
+        }
+
+        closest
+    }
+
+    // cast_ray_into_node is Tree::cast_ray_into_node for the boxed nodes, visiting both children front to back
+    fn cast_ray_into_node(node: &Node<T>, ray: &ray::Ray, direction_norm: f64, closest: &mut Option<hit::Hit>) {
+        if let Some(hit) = T::nearest_ray_intersection(&node.contents, ray) {
+            if closest.is_none_or(|closest_hit| hit.distance < closest_hit.distance) {
+                *closest = Some(hit);
             }
+        }
 
-            if let Some(right) = &node.right {
-                Self::fetch_within_pyramid_from_node(pyramid, right, objects);
+        let mut entered: Vec<(f64, &Node<T>)> = [&node.left, &node.right]
+            .into_iter()
+            .flatten()
+            .filter_map(|child| child.bounds.ray_entry_distance(ray).map(|distance| (distance * direction_norm, &**child)))
+            .collect();
+        entered.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (entry_distance, child) in entered {
+            if closest.is_some_and(|hit| entry_distance > hit.distance) {
+                break;
             }
+
+            BoxedTree::cast_ray_into_node(child, ray, direction_norm, closest);
         }
     }
 }
@@ -248,11 +835,11 @@ impl<T: NodeObject> Tree<T> {
 mod tests {
     use super::*;
 
-    use crate::{angle, limits, quaternion, triangle};
+    use crate::{angle, limits, quaternion, random_generator, triangle};
+    use crate::tree::Intersectable;
 
     #[test]
     fn cast_ray() {
-        let mut hits: Vec<hit::Hit> = Vec::new();
         let ray = ray::Ray::new(
             vector3::Vector3::new(0.25, 0.25, -1.0),
             vector3::Vector3::new(0.0, 0.0, 1.0),
@@ -275,19 +862,15 @@ mod tests {
             ),
         };
 
-        let tree = Tree {
-            root: node,
-            m_page_size: 1,
-        };
+        let tree = Tree::from_root(node, 1);
 
-        tree.cast_ray(&ray, &mut hits);
+        let hit = tree.cast_ray(&ray).unwrap();
 
-        assert_eq!(hits.len(), 1);
+        assert_eq!(hit.distance, 1.0);
     }
 
     #[test]
     fn cast_ray_into_node() {
-        let mut hits: Vec<hit::Hit> = Vec::new();
         let ray = ray::Ray::new(
             vector3::Vector3::new(0.25, 0.25, -1.0),
             vector3::Vector3::new(0.0, 0.0, 1.0),
@@ -310,14 +893,13 @@ mod tests {
             ),
         };
 
-        let tree = Tree {
-            root: node,
-            m_page_size: 1,
-        };
+        let tree = Tree::from_root(node, 1);
+
+        let mut closest: Option<hit::Hit> = None;
 
-        Tree::cast_ray_into_node(&ray, &tree.root, &mut hits);
+        tree.cast_ray_into_node(&ray, 1.0, 0, &mut |objects: &[triangle::Triangle]| triangle::Triangle::nearest_ray_intersection(objects, &ray), &mut closest);
 
-        assert_eq!(hits.len(), 1);
+        assert_eq!(closest.unwrap().distance, 1.0);
     }
 
     #[test]
@@ -346,10 +928,7 @@ mod tests {
             ),
         };
 
-        let tree = Tree {
-            root: node,
-            m_page_size: 1,
-        };
+        let tree = Tree::from_root(node, 1);
 
         let objects = tree.fetch_within_pyramid(&pyramid);
 
@@ -383,12 +962,9 @@ mod tests {
             ),
         };
 
-        let tree = Tree {
-            root: node,
-            m_page_size: 1,
-        };
+        let tree = Tree::from_root(node, 1);
 
-        Tree::fetch_within_pyramid_from_node(&pyramid, &tree.root, &mut objects);
+        tree.fetch_within_pyramid_from_node(&pyramid, 0, &mut objects);
 
         assert_eq!(objects.len(), 1);
     }
@@ -448,6 +1024,276 @@ mod tests {
 
         let tree = Tree::<triangle::Triangle>::new_with_page_size(objects, 1);
 
-        assert_eq!(tree.root.contents.len(), 1);
+        assert_eq!(tree.m_nodes[0].object_count, 1);
+    }
+
+    // grid_triangles places a small triangle at every point of a slightly skewed size x size grid in the
+    // z = 0 plane, so that no two triangles share a pivot component
+    fn grid_triangles(size: usize) -> Vec<triangle::Triangle> {
+        let mut triangles = Vec::new();
+
+        for y in 0..size {
+            for x in 0..size {
+                let corner = vector3::Vector3::new(x as f64 + y as f64 * 0.01, y as f64 + x as f64 * 0.01, 0.0);
+                triangles.push(triangle::Triangle::new(
+                    corner,
+                    corner + vector3::Vector3::new(0.0, 0.5, 0.0),
+                    corner + vector3::Vector3::new(0.5, 0.0, 0.0),
+                ));
+            }
+        }
+
+        triangles
+    }
+
+    // node_count_and_depth counts the nodes below a node of the boxed tree and the depth of the deepest one
+    fn node_count_and_depth(node: &Node<triangle::Triangle>) -> (usize, usize) {
+        [&node.left, &node.right]
+            .into_iter()
+            .flatten()
+            .map(|child| node_count_and_depth(child))
+            .fold((1, 1), |(count, depth), (child_count, child_depth)| (count + child_count, depth.max(child_depth + 1)))
+    }
+
+    #[test]
+    fn median_statistics() {
+        let tree = Tree::new_with_builder(grid_triangles(10), TreeBuilder::Median { page_size: 1 });
+
+        let statistics = tree.statistics();
+
+        assert_eq!(statistics.object_count, 100);
+        assert_eq!((statistics.node_count, statistics.depth), node_count_and_depth(&Tree::generate(grid_triangles(10), &vector3::Axis::X, &1)));
+        assert!(statistics.leaf_count > 0);
+        assert!(statistics.max_leaf_objects <= 1);
+    }
+
+    #[test]
+    fn sah_statistics() {
+        let settings = SahSettings {
+            leaf_size: 4,
+            ..Default::default()
+        };
+        let tree = Tree::new_with_builder(grid_triangles(10), TreeBuilder::Sah(settings));
+
+        let statistics = tree.statistics();
+
+        // every object is in a leaf and every interior node has two children
+        assert_eq!(statistics.object_count, 100);
+        assert_eq!(statistics.node_count, 2 * statistics.leaf_count - 1);
+        assert!(statistics.min_leaf_objects >= 1);
+        assert!(statistics.max_leaf_objects <= 4);
+        assert_eq!(statistics.mean_leaf_objects, 100.0 / statistics.leaf_count as f64);
+        assert_eq!((statistics.node_count, statistics.depth), node_count_and_depth(&Tree::generate_sah(grid_triangles(10), &settings)));
+    }
+
+    #[test]
+    fn sah_leaf_when_splitting_is_expensive() {
+        let settings = SahSettings {
+            leaf_size: 8,
+            traversal_cost: 100.0,
+            ..Default::default()
+        };
+        let tree = Tree::new_with_builder(grid_triangles(2), TreeBuilder::Sah(settings));
+
+        let statistics = tree.statistics();
+
+        assert_eq!(statistics.node_count, 1);
+        assert_eq!(statistics.max_leaf_objects, 4);
+    }
+
+    #[test]
+    fn sah_coincident_objects() {
+        let triangles = vec![grid_triangles(1)[0]; 10];
+        let tree = Tree::new_with_builder(triangles, TreeBuilder::Sah(SahSettings::default()));
+
+        let statistics = tree.statistics();
+
+        assert_eq!(statistics.node_count, 1);
+        assert_eq!(statistics.object_count, 10);
+    }
+
+    #[test]
+    fn sah_cast_ray() {
+        let triangles = grid_triangles(6);
+        let tree = Tree::new_with_builder(triangles.clone(), TreeBuilder::Sah(SahSettings::default()));
+
+        for t in &triangles {
+            let ray = ray::Ray::new(
+                t.get_pivot() - vector3::Vector3::new(0.0, 0.0, 1.0),
+                vector3::Vector3::new(0.0, 0.0, 1.0),
+            );
+
+            assert!(tree.cast_ray(&ray).is_some());
+        }
+    }
+
+    // random_triangles scatters overlapping triangles facing -Z through a 10 x 10 x 10 box
+    fn random_triangles(count: usize, rg: &mut random_generator::RandomGenerator) -> Vec<triangle::Triangle> {
+        (0..count).map(|_| {
+            let corner = vector3::Vector3::new(rg.value(10.0), rg.value(10.0), rg.value(10.0));
+            triangle::Triangle::new(
+                corner,
+                corner + vector3::Vector3::new(rg.value(1.0), 0.5 + rg.value(2.0), rg.value(1.0) - 0.5),
+                corner + vector3::Vector3::new(0.5 + rg.value(2.0), rg.value(1.0), rg.value(1.0) - 0.5),
+            )
+        }).collect()
+    }
+
+    fn brute_force_cast_ray(triangles: &[triangle::Triangle], ray: &ray::Ray) -> Option<hit::Hit> {
+        triangles.iter()
+            .filter_map(|t| t.ray_intersects(ray))
+            .reduce(|a, b| if a.distance <= b.distance { a } else { b })
+    }
+
+    #[test]
+    fn cast_ray_matches_brute_force() {
+        let mut rg = random_generator::RandomGenerator::from_seed(11);
+        let triangles = random_triangles(300, &mut rg);
+
+        let trees = [
+            Tree::new_with_builder(triangles.clone(), TreeBuilder::Median { page_size: 1 }),
+            Tree::new_with_builder(triangles.clone(), TreeBuilder::Median { page_size: 8 }),
+            Tree::new_with_builder(triangles.clone(), TreeBuilder::Sah(SahSettings::default())),
+        ];
+        let boxed_tree = BoxedTree::new_with_builder(triangles.clone(), TreeBuilder::Sah(SahSettings::default()));
+
+        let mut hit_count = 0;
+
+        for _ in 0..500 {
+            // rays travel mostly along +Z through the box, either from below or from inside of it
+            let ray = ray::Ray::new(
+                vector3::Vector3::new(rg.value(10.0), rg.value(10.0), rg.value(6.0) - 1.0),
+                vector3::Vector3::new(rg.value(1.0) - 0.5, rg.value(1.0) - 0.5, 1.0),
+            );
+
+            let expected = brute_force_cast_ray(&triangles, &ray);
+
+            for tree in &trees {
+                let hit = tree.cast_ray(&ray);
+
+                assert_eq!(hit.is_some(), expected.is_some());
+                if let (Some(hit), Some(expected)) = (hit, expected) {
+                    assert_eq!(hit.distance, expected.distance);
+                }
+            }
+
+            assert_eq!(boxed_tree.cast_ray(&ray).map(|hit| hit.distance), expected.map(|hit| hit.distance));
+
+            if expected.is_some() {
+                hit_count += 1;
+            }
+        }
+
+        // the rays have to exercise the nearest hit search through several layers of triangles
+        assert!(hit_count > 100);
+    }
+
+    #[test]
+    fn cast_ray_returns_nearest_of_stacked_triangles() {
+        let triangles: Vec<triangle::Triangle> = (0..8).rev().map(|z| triangle::Triangle::new(
+            vector3::Vector3::new(0.0, 0.0, z as f64),
+            vector3::Vector3::new(0.0, 1.0, z as f64),
+            vector3::Vector3::new(1.0, 0.0, z as f64),
+        )).collect();
+
+        let up = ray::Ray::new(vector3::Vector3::new(0.25, 0.25, -1.0), vector3::Vector3::new(0.0, 0.0, 1.0));
+
+        for tree in [
+            Tree::new_with_builder(triangles.clone(), TreeBuilder::Median { page_size: 1 }),
+            Tree::new_with_builder(triangles.clone(), TreeBuilder::Sah(SahSettings::default())),
+        ] {
+            assert_eq!(tree.cast_ray(&up).unwrap().distance, 1.0);
+        }
+    }
+
+    #[test]
+    fn occluded_matches_brute_force() {
+        let mut rg = random_generator::RandomGenerator::from_seed(13);
+        let triangles = random_triangles(300, &mut rg);
+
+        let trees = [
+            Tree::new_with_builder(triangles.clone(), TreeBuilder::Median { page_size: 1 }),
+            Tree::new_with_builder(triangles.clone(), TreeBuilder::Sah(SahSettings::default())),
+        ];
+
+        let mut occluded_count = 0;
+
+        for _ in 0..500 {
+            let ray = ray::Ray::new(
+                vector3::Vector3::new(rg.value(10.0), rg.value(10.0), rg.value(6.0) - 1.0),
+                vector3::Vector3::new(rg.value(1.0) - 0.5, rg.value(1.0) - 0.5, 1.0),
+            );
+            let max_distance = rg.value(8.0);
+
+            let expected = triangles.iter()
+                .filter_map(|t| t.ray_intersects(&ray))
+                .any(|hit| hit.distance > 0.0 && hit.distance < max_distance);
+
+            for tree in &trees {
+                assert_eq!(tree.occluded(&ray, max_distance), expected);
+            }
+
+            if expected {
+                occluded_count += 1;
+            }
+        }
+
+        assert!(occluded_count > 50 && occluded_count < 450);
+    }
+
+    #[test]
+    fn occluded_within_range() {
+        let tree = Tree::new(vec![triangle::Triangle::new(
+            vector3::Vector3::new(0.0, 0.0, 2.0),
+            vector3::Vector3::new(0.0, 1.0, 2.0),
+            vector3::Vector3::new(1.0, 0.0, 2.0),
+        )]);
+
+        let ray = ray::Ray::new(vector3::Vector3::new(0.25, 0.25, 0.0), vector3::Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(tree.occluded(&ray, 3.0));
+        assert!(!tree.occluded(&ray, 2.0));
+        assert!(!tree.occluded(&ray, 1.0));
+    }
+
+    #[test]
+    fn flatten_four_wide() {
+        for builder in [TreeBuilder::Median { page_size: 1 }, TreeBuilder::Sah(SahSettings::default())] {
+            let tree = Tree::new_with_builder(grid_triangles(10), builder);
+
+            let mut covered = vec![false; tree.m_objects.len()];
+            let mut visited = vec![false; tree.m_nodes.len()];
+
+            for (index, node) in tree.m_nodes.iter().enumerate() {
+                assert!(node.children().len() <= WIDTH);
+
+                // children come after their parents, and have the bounds their parents test them with
+                for (lane, &child) in node.children().iter().enumerate() {
+                    let child = child as usize;
+                    assert!(child > index);
+                    assert!(!visited[child]);
+                    visited[child] = true;
+
+                    let child_node = &tree.m_nodes[child];
+                    for object in &tree.m_objects[child_node.object_range()] {
+                        let bounds = node.child_bounds.get(lane);
+                        assert!(bounds.get_limits(&vector3::Axis::X).contains(object.get_pivot().get_x()));
+                        assert!(bounds.get_limits(&vector3::Axis::Y).contains(object.get_pivot().get_y()));
+                    }
+                }
+
+                // the object ranges of the nodes don't overlap
+                for i in node.object_range() {
+                    assert!(!covered[i]);
+                    covered[i] = true;
+                }
+            }
+
+            assert!(covered.into_iter().all(|c| c));
+            assert_eq!(visited.into_iter().filter(|v| !v).count(), 1);
+            assert_eq!(tree.m_objects.len(), 100);
+            // merging two levels into one leaves fewer nodes than the binary tree has
+            assert!(tree.m_nodes.len() < tree.statistics().node_count);
+        }
     }
 }
@@ -1,8 +1,15 @@
+use std::io;
+use std::simd;
+use std::simd::cmp::SimdPartialOrd;
+use std::simd::num::SimdFloat;
+
+use crate::binary;
 use crate::bounds;
 use crate::hit;
 use crate::limits;
 use crate::ray;
 use crate::tree;
+use crate::uv;
 use crate::vector3;
 
 #[derive(Copy, Clone)]
@@ -17,17 +24,18 @@ pub struct Triangle {
     a_normal: vector3::Vector3,
     b_normal: vector3::Vector3,
     c_normal: vector3::Vector3,
+
+    a_uv: uv::Uv,
+    b_uv: uv::Uv,
+    c_uv: uv::Uv,
 }
 
 impl Triangle {
+    // new returns a flat shaded triangle, whose vertex normals are all its face normal
     pub fn new(a: vector3::Vector3, b: vector3::Vector3, c: vector3::Vector3) -> Self {
         let center = (a + b + c) / 3.0;
         let normal = vector3::Vector3::cross(&(b - a), &(c - a)).normalize();
 
-        let a_normal = vector3::Vector3::cross(&(b - a), &(center - a)).normalize();
-        let b_normal = vector3::Vector3::cross(&(c - b), &(center - b)).normalize();
-        let c_normal = vector3::Vector3::cross(&(a - c), &(center - c)).normalize();
-
         Triangle {
             a,
             b,
@@ -36,12 +44,84 @@ impl Triangle {
             center,
             normal,
 
-            a_normal,
-            b_normal,
-            c_normal,
+            a_normal: normal,
+            b_normal: normal,
+            c_normal: normal,
+
+            a_uv: uv::Uv::default(),
+            b_uv: uv::Uv::default(),
+            c_uv: uv::Uv::default(),
         }
     }
 
+    // with_normals returns a smooth shaded triangle, whose shading normal is interpolated from the vertex
+    // normals, such as the ones authored in OBJ files
+    pub fn with_normals(
+        a: vector3::Vector3,
+        b: vector3::Vector3,
+        c: vector3::Vector3,
+        a_normal: vector3::Vector3,
+        b_normal: vector3::Vector3,
+        c_normal: vector3::Vector3,
+    ) -> Self {
+        let mut triangle = Triangle::new(a, b, c);
+
+        triangle.a_normal = a_normal.normalize();
+        triangle.b_normal = b_normal.normalize();
+        triangle.c_normal = c_normal.normalize();
+
+        triangle
+    }
+
+    // set_uvs sets the texture coordinates of the vertices, which are all zero otherwise
+    pub fn set_uvs(&mut self, a_uv: uv::Uv, b_uv: uv::Uv, c_uv: uv::Uv) {
+        self.a_uv = a_uv;
+        self.b_uv = b_uv;
+        self.c_uv = c_uv;
+    }
+
+    // write_to stores the vertices, vertex normals and texture coordinates, the center and face normal are
+    // derived from them
+    pub fn write_to(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        for vector in [&self.a, &self.b, &self.c, &self.a_normal, &self.b_normal, &self.c_normal] {
+            binary::write_vector(writer, vector)?;
+        }
+
+        for uv in [&self.a_uv, &self.b_uv, &self.c_uv] {
+            binary::write_f64(writer, uv.u)?;
+            binary::write_f64(writer, uv.v)?;
+        }
+
+        Ok(())
+    }
+
+    // read_from reads a triangle stored by write_to, keeping the vertex normals exactly as they were written
+    pub fn read_from(reader: &mut impl io::Read) -> io::Result<Self> {
+        let mut triangle = Triangle::new(
+            binary::read_vector(reader)?,
+            binary::read_vector(reader)?,
+            binary::read_vector(reader)?,
+        );
+
+        triangle.a_normal = binary::read_vector(reader)?;
+        triangle.b_normal = binary::read_vector(reader)?;
+        triangle.c_normal = binary::read_vector(reader)?;
+
+        triangle.a_uv = uv::Uv::new(binary::read_f64(reader)?, binary::read_f64(reader)?);
+        triangle.b_uv = uv::Uv::new(binary::read_f64(reader)?, binary::read_f64(reader)?);
+        triangle.c_uv = uv::Uv::new(binary::read_f64(reader)?, binary::read_f64(reader)?);
+
+        Ok(triangle)
+    }
+
+    pub fn get_face_normal(&self) -> vector3::Vector3 {
+        self.normal
+    }
+
+    pub fn area(&self) -> f64 {
+        vector3::Vector3::cross(&(self.b - self.a), &(self.c - self.a)).norm() / 2.0
+    }
+
     pub fn get_position(&self, coords: vector3::Vector3) -> vector3::Vector3 {
         self.a * coords.get_x() + self.b * coords.get_y() + self.c * coords.get_z()
     }
@@ -50,6 +130,10 @@ impl Triangle {
         self.a_normal * coords.get_x() + self.b_normal * coords.get_y() + self.c_normal * coords.get_z()
     }
 
+    pub fn get_uv(&self, coords: vector3::Vector3) -> uv::Uv {
+        self.a_uv * coords.get_x() + self.b_uv * coords.get_y() + self.c_uv * coords.get_z()
+    }
+
     fn get_limits(&self, axis: &vector3::Axis) -> limits::Limits {
         limits::Limits::new(
             self.a.get_component(axis).min(self.b.get_component(axis)).min(self.c.get_component(axis)),
@@ -78,6 +162,74 @@ impl tree::Bounded for Triangle {
 
 impl tree::Intersectable for Triangle {
     fn ray_intersects(&self, ray: &ray::Ray) -> Option<hit::Hit> {
+        self.ray_intersects_sided(ray, false)
+    }
+
+    fn nearest_ray_intersection(objects: &[Self], ray: &ray::Ray) -> Option<hit::Hit> {
+        Triangle::nearest_sided_intersection(objects, ray, false)
+    }
+
+    fn occluded_by(objects: &[Self], ray: &ray::Ray, max_distance: f64) -> bool {
+        Triangle::sided_occluded_by(objects, ray, max_distance, false)
+    }
+}
+
+impl Triangle {
+    // ray_intersects_sided is ray_intersects, which also hits the back of the triangle when two_sided is set
+    pub fn ray_intersects_sided(&self, ray: &ray::Ray, two_sided: bool) -> Option<hit::Hit> {
+        let (v, w, d) = self.ray_barycentrics(ray, two_sided)?;
+
+        Some(self.hit_at(ray, v, w, d))
+    }
+
+    // nearest_sided_intersection is nearest_ray_intersection, which also hits the backs of the triangles when
+    // two_sided is set
+    pub fn nearest_sided_intersection(objects: &[Self], ray: &ray::Ray, two_sided: bool) -> Option<hit::Hit> {
+        if !cfg!(feature = "simd") {
+            return Triangle::nearest_ray_intersection_scalar(objects, ray, two_sided);
+        }
+
+        let mut closest: Option<hit::Hit> = None;
+
+        for triangles in objects.chunks(4) {
+            let barycentrics = Triangle4::new(triangles).ray_barycentrics(ray, two_sided);
+
+            for (triangle, barycentrics) in triangles.iter().zip(barycentrics) {
+                if let Some((v, w, d)) = barycentrics {
+                    let hit = triangle.hit_at(ray, v, w, d);
+
+                    if closest.is_none_or(|closest_hit| hit.distance < closest_hit.distance) {
+                        closest = Some(hit);
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+
+    // sided_occluded_by is occluded_by, which also hits the backs of the triangles when two_sided is set
+    pub fn sided_occluded_by(objects: &[Self], ray: &ray::Ray, max_distance: f64, two_sided: bool) -> bool {
+        if !cfg!(feature = "simd") {
+            return Triangle::occluded_by_scalar(objects, ray, max_distance, two_sided);
+        }
+
+        objects.chunks(4).any(|triangles| {
+            let barycentrics = Triangle4::new(triangles).ray_barycentrics(ray, two_sided);
+
+            triangles.iter().zip(barycentrics).any(|(triangle, barycentrics)| {
+                barycentrics.is_some_and(|(v, w, d)| {
+                    let distance = triangle.hit_at(ray, v, w, d).distance;
+                    distance > 0.0 && distance < max_distance
+                })
+            })
+        })
+    }
+
+    // ray_barycentrics is the one-sided Moller-Trumbore test, returning the barycentric coordinates v and w
+    // of the hit scaled by the denominator d. When two_sided is set the back of the triangle is hit as well,
+    // with the terms negated for hits from behind so that d is positive.
+    fn ray_barycentrics(&self, ray: &ray::Ray, two_sided: bool) -> Option<(f64, f64, f64)> {
         let ab = self.b - self.a;
         let ac = self.c - self.a;
         let qp = -ray.direction;
@@ -86,52 +238,166 @@ impl tree::Intersectable for Triangle {
 
         let d = vector3::Vector3::dot(&qp, &n);
 
-        if d <= 0.0 {
+        if d == 0.0 || (d < 0.0 && !two_sided) {
             return None;
         }
 
+        let sign = d.signum();
+        let d = d * sign;
+
         let ap = ray.origin - self.a;
-        let t = vector3::Vector3::dot(&ap, &n);
+        let t = vector3::Vector3::dot(&ap, &n) * sign;
 
         if t < 0.0 {
             return None;
         }
 
         let e = vector3::Vector3::cross(&qp, &ap);
-        let mut v = vector3::Vector3::dot(&ac, &e);
+        let v = vector3::Vector3::dot(&ac, &e) * sign;
 
         if v < 0.0 || v > d {
             return None;
         }
 
-        let mut w = -vector3::Vector3::dot(&ab, &e);
+        let w = -vector3::Vector3::dot(&ab, &e) * sign;
         if w < 0.0 || v + w > d {
             return None;
         }
 
+        Some((v, w, d))
+    }
+
+    fn hit_at(&self, ray: &ray::Ray, v: f64, w: f64, d: f64) -> hit::Hit {
         let ood = 1.0 / d;
-        v *= ood;
-        w *= ood;
+        let v = v * ood;
+        let w = w * ood;
         let u = 1.0 - v - w;
 
         let coords = vector3::Vector3::new(u, v, w);
 
         let position = self.get_position(coords);
-        Some(
-            hit::Hit::new(
-                position,
-                self.get_normal(coords).normalize(),
-                (position - ray.origin).norm(),
-                0,
-            )
+
+        // vertex normals that point in opposite directions can cancel out, leaving only the face normal
+        let shading_normal = self.get_normal(coords);
+        let shading_normal = if shading_normal.norm() > f64::EPSILON { shading_normal.normalize() } else { self.normal };
+
+        hit::Hit::with_shading(
+            position,
+            shading_normal,
+            self.normal,
+            coords,
+            self.get_uv(coords),
+            (position - ray.origin).norm(),
+            0,
         )
     }
+
+    fn nearest_ray_intersection_scalar(objects: &[Self], ray: &ray::Ray, two_sided: bool) -> Option<hit::Hit> {
+        let mut closest: Option<hit::Hit> = None;
+
+        for hit in objects.iter().filter_map(|triangle| triangle.ray_intersects_sided(ray, two_sided)) {
+            if closest.is_none_or(|closest_hit| hit.distance < closest_hit.distance) {
+                closest = Some(hit);
+            }
+        }
+
+        closest
+    }
+
+    fn occluded_by_scalar(objects: &[Self], ray: &ray::Ray, max_distance: f64, two_sided: bool) -> bool {
+        objects.iter().any(|triangle| {
+            triangle.ray_intersects_sided(ray, two_sided).is_some_and(|hit| hit.distance > 0.0 && hit.distance < max_distance)
+        })
+    }
+}
+
+// Triangle4 holds the corners and edges of up to four triangles with every component of the four in one
+// vector, so that a ray can be intersected with all of them at once
+pub struct Triangle4 {
+    a: [simd::f64x4; 3],
+    ab: [simd::f64x4; 3],
+    ac: [simd::f64x4; 3],
+    count: usize,
+}
+
+impl Triangle4 {
+    pub fn new(triangles: &[Triangle]) -> Self {
+        assert!(triangles.len() <= 4, "Triangle4 holds at most four triangles, got {}", triangles.len());
+
+        let mut a = [[0.0; 4]; 3];
+        let mut ab = [[0.0; 4]; 3];
+        let mut ac = [[0.0; 4]; 3];
+
+        for (lane, triangle) in triangles.iter().enumerate() {
+            let triangle_ab = triangle.b - triangle.a;
+            let triangle_ac = triangle.c - triangle.a;
+
+            for (i, axis) in [vector3::Axis::X, vector3::Axis::Y, vector3::Axis::Z].iter().enumerate() {
+                a[i][lane] = triangle.a.get_component(axis);
+                ab[i][lane] = triangle_ab.get_component(axis);
+                ac[i][lane] = triangle_ac.get_component(axis);
+            }
+        }
+
+        Triangle4 {
+            a: a.map(simd::f64x4::from_array),
+            ab: ab.map(simd::f64x4::from_array),
+            ac: ac.map(simd::f64x4::from_array),
+            count: triangles.len(),
+        }
+    }
+
+    // ray_barycentrics runs Triangle::ray_barycentrics on the four triangles at once, returning None past
+    // the last one
+    pub fn ray_barycentrics(&self, ray: &ray::Ray, two_sided: bool) -> [Option<(f64, f64, f64)>; 4] {
+        let qp = [-ray.direction.get_x(), -ray.direction.get_y(), -ray.direction.get_z()].map(simd::f64x4::splat);
+        let origin = [ray.origin.get_x(), ray.origin.get_y(), ray.origin.get_z()].map(simd::f64x4::splat);
+
+        let zero = simd::f64x4::splat(0.0);
+
+        let n = cross4(&self.ab, &self.ac);
+        let d = dot4(&qp, &n);
+        let sign = if two_sided { d.signum() } else { simd::f64x4::splat(1.0) };
+        let d = d * sign;
+
+        let ap = [origin[0] - self.a[0], origin[1] - self.a[1], origin[2] - self.a[2]];
+        let t = dot4(&ap, &n) * sign;
+
+        let e = cross4(&qp, &ap);
+        let v = dot4(&self.ac, &e) * sign;
+        let w = -dot4(&self.ab, &e) * sign;
+
+        let hit = simd::mask64x4::from_array([0, 1, 2, 3].map(|lane| lane < self.count)) &
+            d.simd_gt(zero) &
+            t.simd_ge(zero) &
+            v.simd_ge(zero) & v.simd_le(d) &
+            w.simd_ge(zero) & (v + w).simd_le(d);
+
+        let hit = hit.to_array();
+        let (v, w, d) = (v.to_array(), w.to_array(), d.to_array());
+
+        [0, 1, 2, 3].map(|lane| hit[lane].then_some((v[lane], w[lane], d[lane])))
+    }
+}
+
+// dot4 and cross4 are Vector3::dot and Vector3::cross on four vectors, with the same order of operations
+fn dot4(lhs: &[simd::f64x4; 3], rhs: &[simd::f64x4; 3]) -> simd::f64x4 {
+    lhs[0] * rhs[0] + lhs[1] * rhs[1] + lhs[2] * rhs[2]
+}
+
+fn cross4(lhs: &[simd::f64x4; 3], rhs: &[simd::f64x4; 3]) -> [simd::f64x4; 3] {
+    [
+        lhs[1] * rhs[2] - lhs[2] * rhs[1],
+        lhs[2] * rhs[0] - lhs[0] * rhs[2],
+        lhs[0] * rhs[1] - lhs[1] * rhs[0],
+    ]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::random_generator;
     use crate::tree::Intersectable;
 
     use std::f64::consts;
@@ -170,6 +436,26 @@ mod tests {
         assert_approx_eq!(triangle.c_normal.get_z(), consts::FRAC_1_SQRT_2, 1e-6f64);
     }
 
+    #[test]
+    fn triangle_with_normals() {
+        let triangle = Triangle::with_normals(
+            vector3::Vector3::new(0.0, 0.0, 0.0),
+            vector3::Vector3::new(0.0, 1.0, 0.0),
+            vector3::Vector3::new(1.0, 0.0, 0.0),
+            vector3::Vector3::new(0.0, 0.0, -2.0),
+            vector3::Vector3::new(0.0, 1.0, -1.0),
+            vector3::Vector3::new(1.0, 0.0, -1.0),
+        );
+
+        assert_approx_eq!(triangle.normal.get_z(), -1.0, 1e-6f64);
+
+        assert_approx_eq!(triangle.a_normal.get_z(), -1.0, 1e-6f64);
+        assert_approx_eq!(triangle.b_normal.get_y(), consts::FRAC_1_SQRT_2, 1e-6f64);
+        assert_approx_eq!(triangle.b_normal.get_z(), -consts::FRAC_1_SQRT_2, 1e-6f64);
+        assert_approx_eq!(triangle.c_normal.get_x(), consts::FRAC_1_SQRT_2, 1e-6f64);
+        assert_approx_eq!(triangle.c_normal.get_z(), -consts::FRAC_1_SQRT_2, 1e-6f64);
+    }
+
     #[test]
     fn ray_intersects() {
         // intersection from the correct side
@@ -198,5 +484,122 @@ mod tests {
         assert!(triangle.ray_intersects(&ray1).is_some());
         assert!(triangle.ray_intersects(&ray2).is_none());
         assert!(triangle.ray_intersects(&near_miss_ray).is_none());
+
+        // two sided, hits from behind keep the normal of the front, which they are below
+        let hit = triangle.ray_intersects_sided(&ray2, true).unwrap();
+        assert_eq!(hit.distance, 1.0);
+        assert_eq!(hit.geometric_normal, triangle.ray_intersects(&ray1).unwrap().geometric_normal);
+        assert!(!hit.is_above(&-ray2.direction));
+    }
+
+    #[test]
+    fn ray_intersects_smooth_shading() {
+        let triangle = Triangle::with_normals(
+            vector3::Vector3::new(0.0, 0.0, 0.0),
+            vector3::Vector3::new(0.0, 1.0, 0.0),
+            vector3::Vector3::new(1.0, 0.0, 0.0),
+            vector3::Vector3::new(0.0, 0.0, -1.0),
+            vector3::Vector3::new(0.0, 1.0, -1.0),
+            vector3::Vector3::new(1.0, 0.0, -1.0),
+        );
+
+        let ray = ray::Ray::new(
+            vector3::Vector3::new(0.25, 0.5, -1.0),
+            vector3::Vector3::new(0.0, 0.0, 1.0),
+        );
+
+        let hit = triangle.ray_intersects(&ray).unwrap();
+
+        // the hit is a quarter of the way to c and half of the way to b
+        assert_approx_eq!(hit.barycentrics.get_x(), 0.25, 1e-9f64);
+        assert_approx_eq!(hit.barycentrics.get_y(), 0.5, 1e-9f64);
+        assert_approx_eq!(hit.barycentrics.get_z(), 0.25, 1e-9f64);
+
+        // the shading normal leans towards b and c, the geometric normal is the face's
+        let expected = (vector3::Vector3::new(0.0, 0.0, -0.25) +
+            vector3::Vector3::new(0.0, 1.0, -1.0).normalize() * 0.5 +
+            vector3::Vector3::new(1.0, 0.0, -1.0).normalize() * 0.25).normalize();
+        assert_approx_eq!(hit.normal.get_x(), expected.get_x(), 1e-9f64);
+        assert_approx_eq!(hit.normal.get_y(), expected.get_y(), 1e-9f64);
+        assert_approx_eq!(hit.normal.get_z(), expected.get_z(), 1e-9f64);
+        assert_eq!(hit.geometric_normal, vector3::Vector3::new(0.0, 0.0, -1.0));
+
+        // flat triangles shade with the face normal
+        let flat = Triangle::new(triangle.a, triangle.b, triangle.c).ray_intersects(&ray).unwrap();
+        assert_eq!(flat.normal, flat.geometric_normal);
+    }
+
+    fn random_triangles(count: usize, rg: &mut random_generator::RandomGenerator) -> Vec<Triangle> {
+        (0..count)
+            .map(|_| {
+                let a = vector3::Vector3::new(rg.value(4.0), rg.value(4.0), rg.value(4.0));
+                Triangle::new(
+                    a,
+                    a + vector3::Vector3::new(rg.value(4.0) - 2.0, rg.value(4.0) - 2.0, rg.value(4.0) - 2.0),
+                    a + vector3::Vector3::new(rg.value(4.0) - 2.0, rg.value(4.0) - 2.0, rg.value(4.0) - 2.0),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn triangle4_matches_ray_barycentrics() {
+        let mut rg = random_generator::RandomGenerator::from_seed(16);
+        let mut hit_count = 0;
+
+        for count in 0..=4 {
+            for _ in 0..500 {
+                let triangles = random_triangles(count, &mut rg);
+                let ray = ray::Ray::new(
+                    vector3::Vector3::new(rg.value(4.0), rg.value(4.0), rg.value(4.0) - 4.0),
+                    vector3::Vector3::new(rg.value(0.5) - 0.25, rg.value(0.5) - 0.25, 1.0),
+                );
+
+                for two_sided in [false, true] {
+                    let barycentrics = Triangle4::new(&triangles).ray_barycentrics(&ray, two_sided);
+
+                    for (lane, barycentrics) in barycentrics.into_iter().enumerate() {
+                        let expected = triangles.get(lane).and_then(|triangle| triangle.ray_barycentrics(&ray, two_sided));
+                        assert_eq!(barycentrics, expected);
+
+                        if expected.is_some() {
+                            hit_count += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        assert!(hit_count > 50);
+    }
+
+    #[test]
+    fn nearest_ray_intersection_matches_scalar() {
+        let mut rg = random_generator::RandomGenerator::from_seed(16);
+
+        for count in [1, 3, 4, 7, 16] {
+            for _ in 0..200 {
+                let triangles = random_triangles(count, &mut rg);
+                let ray = ray::Ray::new(
+                    vector3::Vector3::new(rg.value(4.0), rg.value(4.0), rg.value(4.0) - 4.0),
+                    vector3::Vector3::new(rg.value(0.5) - 0.25, rg.value(0.5) - 0.25, 1.0),
+                );
+
+                let max_distance = rg.value(8.0);
+
+                for two_sided in [false, true] {
+                    let expected = Triangle::nearest_ray_intersection_scalar(&triangles, &ray, two_sided);
+                    let actual = Triangle::nearest_sided_intersection(&triangles, &ray, two_sided);
+
+                    assert_eq!(actual.map(|hit| hit.position), expected.map(|hit| hit.position));
+                    assert_eq!(actual.map(|hit| hit.distance), expected.map(|hit| hit.distance));
+
+                    assert_eq!(
+                        Triangle::sided_occluded_by(&triangles, &ray, max_distance, two_sided),
+                        Triangle::occluded_by_scalar(&triangles, &ray, max_distance, two_sided),
+                    );
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,31 @@
+use std::ops;
+
+// Uv is a texture coordinate, with u running from the left edge of a texture to the right one and v from
+// the bottom edge to the top one
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Uv {
+    pub u: f64,
+    pub v: f64,
+}
+
+impl Uv {
+    pub fn new(u: f64, v: f64) -> Self {
+        Uv { u, v }
+    }
+}
+
+impl ops::Add for Uv {
+    type Output = Uv;
+
+    fn add(self, rhs: Uv) -> Uv {
+        Uv::new(self.u + rhs.u, self.v + rhs.v)
+    }
+}
+
+impl ops::Mul<f64> for Uv {
+    type Output = Uv;
+
+    fn mul(self, rhs: f64) -> Uv {
+        Uv::new(self.u * rhs, self.v * rhs)
+    }
+}
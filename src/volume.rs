@@ -1,17 +1,31 @@
-use crate::{hit, object, ray};
+use crate::{bounds, hit, object, ray, triangle};
 
 pub trait VolumeStrategy {
     fn cast_transformed_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit>;
+    fn transformed_ray_occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool;
+    // get_transformed_bounds returns the bounds in the volume's space, or None if the volume is unbounded
+    fn get_transformed_bounds(&self) -> Option<bounds::Bounds>;
 }
 
 pub trait VolumePublicInterface {
     fn get_material_index(&self) -> usize;
     fn set_material_index(&mut self, index: usize);
     fn cast_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit>;
+    // occluded returns whether the volume is hit at a distance in (0, max_distance) along the ray
+    fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool;
+    // get_bounds returns the bounds in world space, or None if the volume is unbounded
+    fn get_bounds(&self) -> Option<bounds::Bounds>;
+    // get_triangles returns the triangles the volume is made of in world space, or none if it isn't made of
+    // triangles
+    fn get_triangles(&self) -> Vec<triangle::Triangle> {
+        Vec::new()
+    }
 }
 
 pub trait VolumeProtectedInterface {
     fn cast_transformed_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit>;
+    fn transformed_ray_occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool;
+    fn get_transformed_bounds(&self) -> Option<bounds::Bounds>;
 }
 
 pub struct Volume<T> {
@@ -53,6 +67,7 @@ impl<T: VolumeStrategy> VolumePublicInterface for Volume<T> {
         return if let Some(mut hit) = hit {
             hit.position = self.object.position() + (self.object.rotation() * hit.position);
             hit.normal = self.object.rotation() * hit.normal;
+            hit.geometric_normal = self.object.rotation() * hit.geometric_normal;
             hit.material_index = self.m_material_index;
 
             Some(hit)
@@ -60,11 +75,29 @@ impl<T: VolumeStrategy> VolumePublicInterface for Volume<T> {
             None
         }
     }
+
+    fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        // volumes are only rotated and translated, so distances are the same in the volume's space
+        self.transformed_ray_occluded(&self.transform_ray(ray), max_distance)
+    }
+
+    fn get_bounds(&self) -> Option<bounds::Bounds> {
+        self.get_transformed_bounds()
+            .map(|bounds| bounds.transformed(self.object.rotation(), self.object.position()))
+    }
 }
 
 impl<T: VolumeStrategy> VolumeProtectedInterface for Volume<T> {
     fn cast_transformed_ray(&self, ray: &ray::Ray, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
         self.specialization.cast_transformed_ray(ray, cast_buffer)
     }
+
+    fn transformed_ray_occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.specialization.transformed_ray_occluded(ray, max_distance)
+    }
+
+    fn get_transformed_bounds(&self) -> Option<bounds::Bounds> {
+        self.specialization.get_transformed_bounds()
+    }
 }
 
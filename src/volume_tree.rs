@@ -0,0 +1,205 @@
+use std::ops;
+
+use crate::{bounds, hit, ray, tree, vector3, volume};
+
+// VolumeReference is the entry of a bounded volume in the top-level tree
+#[derive(Clone, Copy)]
+struct VolumeReference {
+    index: usize,
+    bounds: bounds::Bounds,
+}
+
+impl tree::Bounded for VolumeReference {
+    fn get_bounds(&self) -> bounds::Bounds {
+        self.bounds
+    }
+}
+
+impl tree::Pivotable for VolumeReference {
+    fn get_pivot(&self) -> vector3::Vector3 {
+        (self.bounds.minimum() + self.bounds.maximum()) / 2.0
+    }
+
+    fn get_pivot_component(&self, axis: &vector3::Axis) -> f64 {
+        self.get_pivot().get_component(axis)
+    }
+}
+
+// VolumeTree is the top level of a two-level tree, a tree over the world space bounds of the volumes
+// whose leaves cast into the volumes, and through them into the trees of their meshes. Unbounded volumes
+// such as infinite planes can't be placed in the tree and are tested against every ray.
+pub struct VolumeTree {
+    m_volumes: Vec<Box<dyn volume::VolumePublicInterface>>,
+    m_tree: tree::Tree<VolumeReference>,
+    m_unbounded: Vec<usize>,
+}
+
+impl VolumeTree {
+    pub fn new(volumes: Vec<Box<dyn volume::VolumePublicInterface>>) -> Self {
+        let mut references = Vec::<VolumeReference>::new();
+        let mut unbounded = Vec::<usize>::new();
+
+        for (index, volume) in volumes.iter().enumerate() {
+            match volume.get_bounds() {
+                Some(bounds) => references.push(VolumeReference { index, bounds }),
+                None => unbounded.push(index),
+            }
+        }
+
+        VolumeTree {
+            m_volumes: volumes,
+            m_tree: tree::Tree::new_with_builder(references, tree::TreeBuilder::Sah(tree::SahSettings {
+                leaf_size: 1,
+                ..Default::default()
+            })),
+            m_unbounded: unbounded,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.m_volumes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.m_volumes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Box<dyn volume::VolumePublicInterface>> {
+        self.m_volumes.iter()
+    }
+
+    pub fn statistics(&self) -> tree::TreeStatistics {
+        self.m_tree.statistics()
+    }
+
+    // cast_ray returns the nearest hit along the ray that is farther than min_distance
+    pub fn cast_ray(&self, ray: &ray::Ray, min_distance: f64, cast_buffer: &mut Vec<hit::Hit>) -> Option<hit::Hit> {
+        let mut cast_volume = |index: usize| {
+            self.m_volumes[index].cast_ray(ray, cast_buffer)
+                .filter(|hit| hit.distance > min_distance)
+        };
+
+        let mut closest: Option<hit::Hit> = None;
+
+        for &index in &self.m_unbounded {
+            if let Some(hit) = cast_volume(index) {
+                if closest.is_none_or(|closest_hit| hit.distance < closest_hit.distance) {
+                    closest = Some(hit);
+                }
+            }
+        }
+
+        let tree_hit = self.m_tree.cast_ray_with(ray, |reference| cast_volume(reference.index));
+
+        match (closest, tree_hit) {
+            (Some(closest_hit), Some(hit)) if hit.distance < closest_hit.distance => Some(hit),
+            (None, hit) => hit,
+            (closest, _) => closest,
+        }
+    }
+
+    // occluded returns whether any volume is hit at a distance in (0, max_distance) along the ray
+    pub fn occluded(&self, ray: &ray::Ray, max_distance: f64) -> bool {
+        self.m_unbounded.iter().any(|&index| self.m_volumes[index].occluded(ray, max_distance)) ||
+            self.m_tree.occluded_with(ray, max_distance, |reference| self.m_volumes[reference.index].occluded(ray, max_distance))
+    }
+}
+
+impl ops::Index<usize> for VolumeTree {
+    type Output = Box<dyn volume::VolumePublicInterface>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.m_volumes[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{mesh, mesh_volume, plane_volume, quaternion, random_generator, triangle};
+
+    fn cube_volume(position: vector3::Vector3) -> Box<dyn volume::VolumePublicInterface> {
+        // the two triangles of the -z face of a unit cube, facing rays that travel along +z
+        let triangles = vec![
+            triangle::Triangle::new(
+                vector3::Vector3::new(0.0, 0.0, 0.0),
+                vector3::Vector3::new(0.0, 1.0, 0.0),
+                vector3::Vector3::new(1.0, 0.0, 0.0),
+            ),
+            triangle::Triangle::new(
+                vector3::Vector3::new(1.0, 0.0, 0.0),
+                vector3::Vector3::new(0.0, 1.0, 0.0),
+                vector3::Vector3::new(1.0, 1.0, 0.0),
+            ),
+        ];
+
+        let mut mesh_volume = mesh_volume::MeshVolume::new(0, mesh::Mesh::new("cube", triangles));
+        mesh_volume.set_position(position);
+
+        Box::new(mesh_volume)
+    }
+
+    fn random_volumes(count: usize, rg: &mut random_generator::RandomGenerator) -> Vec<Box<dyn volume::VolumePublicInterface>> {
+        (0..count)
+            .map(|_| cube_volume(vector3::Vector3::new(rg.value(20.0), rg.value(20.0), rg.value(20.0))))
+            .collect()
+    }
+
+    #[test]
+    fn cast_ray_matches_linear_search() {
+        let mut rg = random_generator::RandomGenerator::from_seed(14);
+        let mut cast_buffer = Vec::<hit::Hit>::new();
+
+        let mut volumes = random_volumes(100, &mut rg);
+        let mut plane = plane_volume::PlaneVolume::new(1);
+        plane.set_position(vector3::Vector3::new(0.0, 0.0, 15.0));
+        plane.set_rotation(quaternion::Quaternion::from_axis_angle(&vector3::Vector3::new(1.0, 0.0, 0.0), std::f64::consts::FRAC_PI_2));
+        volumes.push(Box::new(plane));
+
+        let volume_tree = VolumeTree::new(volumes);
+        assert_eq!(volume_tree.len(), 101);
+        assert_eq!(volume_tree.statistics().object_count, 100);
+
+        let mut hit_count = 0;
+
+        for _ in 0..500 {
+            let ray = ray::Ray::new(
+                vector3::Vector3::new(rg.value(20.0), rg.value(20.0), -1.0),
+                vector3::Vector3::new(rg.value(0.2) - 0.1, rg.value(0.2) - 0.1, 1.0),
+            );
+
+            let expected = volume_tree.iter()
+                .filter_map(|volume| volume.cast_ray(&ray, &mut cast_buffer))
+                .filter(|hit| hit.distance > 0.0)
+                .reduce(|a, b| if a.distance < b.distance { a } else { b });
+
+            let actual = volume_tree.cast_ray(&ray, 0.0, &mut cast_buffer);
+
+            assert_eq!(actual.map(|hit| hit.distance), expected.map(|hit| hit.distance));
+            assert_eq!(actual.map(|hit| hit.material_index), expected.map(|hit| hit.material_index));
+
+            let max_distance = rg.value(20.0);
+            let expected_occluded = expected.is_some_and(|hit| hit.distance < max_distance);
+            assert_eq!(volume_tree.occluded(&ray, max_distance), expected_occluded);
+
+            if expected.is_some_and(|hit| hit.material_index == 0) {
+                hit_count += 1;
+            }
+        }
+
+        // most rays pass between the cubes and reach the plane, but some have to hit a cube
+        assert!(hit_count > 0);
+    }
+
+    #[test]
+    fn empty() {
+        let volume_tree = VolumeTree::new(Vec::new());
+
+        let ray = ray::Ray::new(vector3::Vector3::default(), vector3::Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(volume_tree.is_empty());
+        assert!(volume_tree.cast_ray(&ray, 0.0, &mut Vec::new()).is_none());
+        assert!(!volume_tree.occluded(&ray, f64::INFINITY));
+    }
+}
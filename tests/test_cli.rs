@@ -0,0 +1,103 @@
+use std::env;
+use std::path;
+use std::process;
+
+fn binary() -> process::Command {
+    process::Command::new(env!("CARGO_BIN_EXE_tdi_ray_tracer"))
+}
+
+fn scene_path() -> path::PathBuf {
+    path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("triangle.toml")
+}
+
+#[test]
+fn test_cli_render() {
+    let output = env::temp_dir().join("tdi_ray_tracer_test_cli_render.png");
+    let _ = std::fs::remove_file(&output);
+
+    let result = binary()
+        .arg(scene_path())
+        .arg("--output").arg(&output)
+        .args(["--width", "32", "--height", "16", "--photons", "100", "--bounces", "2", "--threads", "2"])
+        .output()
+        .unwrap();
+
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let decoder = png::Decoder::new(std::fs::File::open(&output).unwrap());
+    let reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().width, 32);
+    assert_eq!(reader.info().height, 16);
+}
+
+#[test]
+fn test_cli_missing_scene() {
+    let result = binary()
+        .arg("does_not_exist.toml")
+        .output()
+        .unwrap();
+
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("does_not_exist.toml"));
+}
+
+#[test]
+fn test_cli_render_hdr_formats() {
+    for name in ["tdi_ray_tracer_test_cli_render.hdr", "tdi_ray_tracer_test_cli_render.exr"] {
+        let output = env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&output);
+
+        let result = binary()
+            .arg(scene_path())
+            .arg("--output").arg(&output)
+            .args(["--width", "8", "--height", "8", "--photons", "100"])
+            .output()
+            .unwrap();
+
+        assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+        assert!(std::fs::metadata(&output).unwrap().len() > 0);
+    }
+}
+
+#[test]
+fn test_cli_unsupported_output() {
+    let result = binary()
+        .arg(scene_path())
+        .arg("--output").arg("render.jpg")
+        .output()
+        .unwrap();
+
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("render.jpg"));
+}
+
+#[test]
+fn test_cli_render_sixteen_bit_png() {
+    let output = env::temp_dir().join("tdi_ray_tracer_test_cli_render_sixteen_bit.png");
+    let _ = std::fs::remove_file(&output);
+
+    let result = binary()
+        .arg(scene_path())
+        .arg("--output").arg(&output)
+        .args(["--width", "8", "--height", "8", "--photons", "100", "--seed", "17", "--bit-depth", "16", "--alpha"])
+        .output()
+        .unwrap();
+
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let decoder = png::Decoder::new(std::fs::File::open(&output).unwrap());
+    let reader = decoder.read_info().unwrap();
+    let info = reader.info();
+
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert!(info.srgb.is_some());
+
+    let text = |keyword: &str| info.uncompressed_latin1_text.iter()
+        .find(|chunk| chunk.keyword == keyword)
+        .map(|chunk| chunk.text.clone());
+
+    assert_eq!(text("Photons").as_deref(), Some("100"));
+    assert_eq!(text("Seed").as_deref(), Some("17"));
+    assert!(text("Scene").unwrap().ends_with("triangle.toml"));
+}
@@ -0,0 +1,150 @@
+use std::fs;
+use std::path;
+use tdi_ray_tracer::{pipeline, render_settings, renderer, scene_reader, tone_mapping};
+
+#[test]
+fn test_render_scene() {
+    let scene = scene_reader::load_scene(&path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("triangle.toml")).unwrap();
+
+    let settings = render_settings::RenderSettings {
+        photon_count: 2000,
+        thread_count: 2,
+        ..Default::default()
+    };
+
+    let p = pipeline::Pipeline::new(renderer::Renderer::new(), settings);
+
+    let film = p.render_scene(&scene);
+
+    assert_eq!(film.get_photon_count(), 2000);
+
+    let image = tone_mapping::ToneMapper::default().to_image(&film);
+
+    assert_eq!(image.get_width(), scene.camera.width());
+    assert_eq!(image.get_height(), scene.camera.height());
+
+    let mut lit_pixels = 0;
+    for y in 0..image.get_height() {
+        for x in 0..image.get_width() {
+            let pixel = image.get_pixel(x, y);
+            if pixel.red > 0 || pixel.green > 0 || pixel.blue > 0 {
+                lit_pixels += 1;
+            }
+        }
+    }
+
+    assert!(lit_pixels > 0);
+}
+
+fn render_with_seed(seed: u64, thread_count: usize) -> Vec<u8> {
+    let scene = scene_reader::load_scene(&path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("triangle.toml")).unwrap();
+
+    let settings = render_settings::RenderSettings {
+        photon_count: 2000,
+        bounce_threshold: 2,
+        thread_count,
+        seed: Some(seed),
+        ..Default::default()
+    };
+
+    let p = pipeline::Pipeline::new(renderer::Renderer::new(), settings);
+
+    tone_mapping::ToneMapper::default().to_image(&p.render_scene(&scene)).unpack()
+}
+
+#[test]
+fn test_render_scene_reproducible() {
+    assert_eq!(render_with_seed(1234, 3), render_with_seed(1234, 3));
+    assert_eq!(render_with_seed(1234, 1), render_with_seed(1234, 1));
+    assert_ne!(render_with_seed(1234, 3), render_with_seed(4321, 3));
+}
+
+fn render_source(source: &str) -> Vec<u8> {
+    let scene = scene_reader::parse_scene(source, path::Path::new("")).unwrap();
+
+    let settings = render_settings::RenderSettings {
+        photon_count: 2000,
+        thread_count: 2,
+        seed: Some(5),
+        ..Default::default()
+    };
+
+    let p = pipeline::Pipeline::new(renderer::Renderer::new(), settings);
+
+    tone_mapping::ToneMapper::default().to_image(&p.render_scene(&scene)).unpack()
+}
+
+#[test]
+fn test_render_scene_through_back_faces() {
+    let source = fs::read_to_string(path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("triangle.toml")).unwrap();
+
+    // an open diffuse sheet between the camera and the light faces the camera, so the photons reflected by the
+    // triangle and the rays from the triangle to the camera reach it from behind and pass through it, leaving
+    // the image unchanged
+    let sheet_source = format!(
+        "{}\n[[meshes]]\nname = \"sheet\"\nmaterial = \"White\"\ntriangles = [[[-4.0, -4.0, 1.0], [-4.0, 4.0, 1.0], [4.0, -4.0, 1.0]]]\n",
+        source,
+    );
+
+    assert_eq!(render_source(&sheet_source), render_source(&source));
+}
+
+fn total_radiance(photon_count: usize) -> f64 {
+    let scene = scene_reader::load_scene(&path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("triangle.toml")).unwrap();
+
+    let settings = render_settings::RenderSettings {
+        photon_count,
+        thread_count: 2,
+        seed: Some(99),
+        ..Default::default()
+    };
+
+    let p = pipeline::Pipeline::new(renderer::Renderer::new(), settings);
+
+    let film = p.render_scene(&scene);
+
+    let mut total = 0.0;
+    for y in 0..film.get_height() {
+        for x in 0..film.get_width() {
+            total += film.get_radiance(x, y).brightness();
+        }
+    }
+
+    total
+}
+
+#[test]
+fn test_render_scene_converges() {
+    // the film is normalized by the emitted photons, so the total radiance doesn't grow with the budget
+    let coarse = total_radiance(2000);
+    let fine = total_radiance(16000);
+
+    assert!(coarse > 0.0);
+    assert!((coarse - fine).abs() < 0.1 * fine, "coarse {} fine {}", coarse, fine);
+}
+
+#[test]
+fn test_render_emissive_scene() {
+    let scene = scene_reader::load_scene(&path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("light_panel.toml")).unwrap();
+
+    assert!(scene.lights.is_empty());
+
+    let settings = render_settings::RenderSettings {
+        photon_count: 4000,
+        thread_count: 2,
+        seed: Some(7),
+        ..Default::default()
+    };
+
+    let film = pipeline::Pipeline::new(renderer::Renderer::new(), settings).render_scene(&scene);
+
+    // the panel is seen glowing above the middle of the image, and the floor it lights below
+    let lit_rows = |rows: std::ops::Range<usize>| {
+        rows.flat_map(|y| (0..film.get_width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| film.get_radiance(x, y).brightness() > 0.0)
+            .count()
+    };
+
+    assert!(lit_rows(0..film.get_height() / 2) > 0);
+    assert!(lit_rows(film.get_height() / 2..film.get_height()) > 0);
+}
@@ -1,7 +1,8 @@
 use std::path;
 use tdi_ray_tracer::{angle, camera, hit, image, library, mesh, mesh_volume, parallel_light,
                      parallel_light::LightPublicInterface, photon, pixel, png_writer, quaternion,
-                     random_generator, renderer, triangle, vector3, volume};
+                     random_generator, renderer, tone_mapping, triangle, vector3, volume,
+                     volume_tree};
 
 #[test]
 fn test_renderer() {
@@ -26,7 +27,7 @@ fn test_renderer() {
         ),
     ];
 
-    let volumes: Vec<Box<dyn volume::VolumePublicInterface>> = vec![Box::new(mesh_volume::MeshVolume::new(0, mesh::Mesh::new("test_mesh", triangles)))];
+    let volumes = volume_tree::VolumeTree::new(vec![Box::new(mesh_volume::MeshVolume::new(0, mesh::Mesh::new("test_mesh", triangles)))]);
 
     let mut light = parallel_light::ParallelLight::new();
     light.set_radius(1.0);
@@ -38,8 +39,6 @@ fn test_renderer() {
 
     let mut cast_buffer = Vec::<hit::Hit>::new();
 
-    let mut volume_hit_buffer = Vec::<photon::PhotonHit>::new();
-
     let mut image = image::Image::new(image_width, image_height);
 
     for _ in 0..1000 {
@@ -51,7 +50,7 @@ fn test_renderer() {
 
         println!("photon = \n{:?}\n", photon);
 
-        let photon_hit = renderer.process_photon(&mut photon, &mut cast_buffer, &mut volume_hit_buffer, &volumes);
+        let photon_hit = renderer.process_photon(&mut photon, &mut cast_buffer, &volumes);
 
         println!("photon_hit = \n{:?}\n", photon_hit);
 
@@ -61,7 +60,7 @@ fn test_renderer() {
 
         renderer.bounce_photon_hit(&photon_hit, &mut rg, &material_library);
 
-        if renderer.process_hit(&photon_hit, &mut cast_buffer, &camera, &volumes) {
+        if renderer.process_hit(&photon_hit, &camera, &volumes) {
             let result = renderer.process_final_hit(&photon_hit, &camera, &material_library);
 
             if let Some((pc, c)) = result {
@@ -70,7 +69,38 @@ fn test_renderer() {
         }
     }
 
-    let png_w = png_writer::PngWriter::new(image_width as u32, image_height as u32, path::Path::new("test.png"));
+    let png_w = png_writer::PngWriter::new(path::Path::new("test.png"), tone_mapping::ToneMapper::default());
+
+    png_w.write_image(&image).unwrap();
+}
+
+#[test]
+fn test_process_hit_occluded() {
+    let camera = camera::Camera::new(
+        100,
+        100,
+        &angle::Angle::from_degrees(90.0),
+    );
+
+    let renderer = renderer::Renderer::new();
 
-    png_w.write(&image);
+    let photon_hit = photon::PhotonHit {
+        hit: hit::Hit::new(vector3::Vector3::new(0.0, 0.0, 5.0), vector3::Vector3::new(0.0, 0.0, -1.0), 1.0, 0),
+        photon: photon::Photon::default(),
+    };
+
+    // triangles facing away from the camera, so the ray from the hit back to the camera strikes their front
+    let blocker = |z: f64| -> Box<dyn volume::VolumePublicInterface> {
+        Box::new(mesh_volume::MeshVolume::new(0, mesh::Mesh::new("blocker", vec![
+            triangle::Triangle::new(
+                vector3::Vector3::new(-1.0, -1.0, z),
+                vector3::Vector3::new(1.0, 0.0, z),
+                vector3::Vector3::new(-1.0, 1.0, z),
+            ),
+        ])))
+    };
+
+    assert!(renderer.process_hit(&photon_hit, &camera, &volume_tree::VolumeTree::new(Vec::new())));
+    assert!(!renderer.process_hit(&photon_hit, &camera, &volume_tree::VolumeTree::new(vec![blocker(3.0)])));
+    assert!(renderer.process_hit(&photon_hit, &camera, &volume_tree::VolumeTree::new(vec![blocker(6.0)])));
 }